@@ -0,0 +1,309 @@
+use crate::*;
+use bevy::render::{mesh::Indices, mesh::PrimitiveTopology, render_asset::RenderAssetUsages};
+
+const CARTOON_STEPS: usize = 8;
+const CARTOON_SIDES: usize = 12;
+// consecutive CA farther than this are not bonded
+const MAX_CA_DISTANCE: f32 = 4.2;
+const COIL_RADIUS: f32 = 0.3;
+const HELIX_WIDTH: f32 = 2.4;
+const HELIX_THICKNESS: f32 = 0.4;
+const STRAND_WIDTH: f32 = 2.0;
+const STRAND_THICKNESS: f32 = 0.5;
+const ARROW_WIDTH: f32 = 3.2;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Shape {
+    Helix,
+    Strand,
+    Coil,
+}
+
+impl Shape {
+    fn new(ss: &mogura_ss::SS) -> Self {
+        match ss {
+            mogura_ss::SS::H | mogura_ss::SS::G | mogura_ss::SS::I => Shape::Helix,
+            mogura_ss::SS::E => Shape::Strand,
+            _ => Shape::Coil,
+        }
+    }
+
+    // width along C=O, thickness
+    fn section(&self) -> (f32, f32) {
+        match self {
+            Shape::Helix => (HELIX_WIDTH, HELIX_THICKNESS),
+            Shape::Strand => (STRAND_WIDTH, STRAND_THICKNESS),
+            Shape::Coil => (COIL_RADIUS * 2., COIL_RADIUS * 2.),
+        }
+    }
+
+    fn color(&self) -> [f32; 4] {
+        match self {
+            Shape::Helix => Color::srgb(0.9, 0.2, 0.4),
+            Shape::Strand => Color::srgb(0.95, 0.8, 0.2),
+            Shape::Coil => Color::srgb(0.7, 0.7, 0.7),
+        }
+        .to_linear()
+        .to_f32_array()
+    }
+}
+
+#[derive(Debug, Clone)]
+struct CartoonResidue {
+    ca: usize,
+    o: usize,
+    // index in the protein residues, as in SSTimeline
+    protein_index: usize,
+    ss: mogura_ss::SS,
+}
+
+/// Guide points of the cartoon, CA and O atom ids of each residue
+/// with the SS assigned on the structure file.
+#[derive(Component, Debug, Clone)]
+pub struct Cartoon {
+    // bonded pieces of the chains
+    segments: Vec<Vec<CartoonResidue>>,
+}
+
+impl Cartoon {
+    pub fn new(
+        structure_data: &dyn StructureData,
+        selected_atoms: &std::collections::HashSet<usize>,
+    ) -> Self {
+        let protein: Vec<&Residue> = structure_data
+            .residues()
+            .iter()
+            .filter(|residue| residue.is_protein())
+            .collect();
+        let ss = mogura_ss::assign_ss(
+            &protein
+                .iter()
+                .map(|residue| mogura_ss::Residue::from(*residue))
+                .collect(),
+            mogura_ss::SSAlgorithm::DSSP,
+        );
+        let atoms = structure_data.atoms();
+
+        let mut segments: Vec<Vec<CartoonResidue>> = Vec::new();
+        let mut prev: Option<(usize, &str)> = None;
+        for (protein_index, residue) in protein.iter().enumerate() {
+            let find = |name: &str| {
+                residue
+                    .atoms()
+                    .iter()
+                    .find(|atom| atom.atom_name() == name)
+                    .map(|atom| atom.id())
+            };
+            let (ca, o) = match (find("CA"), find("O")) {
+                (Some(ca), Some(o)) if selected_atoms.contains(&ca) => (ca, o),
+                _ => {
+                    prev = None;
+                    continue;
+                }
+            };
+            let bonded = prev.is_some_and(|(prev_ca, prev_chain)| {
+                prev_chain == residue.chain_name()
+                    && atoms[prev_ca].distance(&atoms[ca]) <= MAX_CA_DISTANCE
+            });
+            let cartoon_residue = CartoonResidue {
+                ca,
+                o,
+                protein_index,
+                ss: ss[protein_index].clone(),
+            };
+            match segments.last_mut() {
+                Some(segment) if bonded => segment.push(cartoon_residue),
+                _ => segments.push(vec![cartoon_residue]),
+            }
+            prev = Some((ca, residue.chain_name()));
+        }
+        segments.retain(|segment| segment.len() > 1);
+
+        Self { segments }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.segments.is_empty()
+    }
+
+    /// `ss` of the protein residues overrides the SS of the structure file, e.g. SSTimeline::frame
+    pub fn mesh(&self, positions: &[[f32; 3]], ss: Option<&[mogura_ss::SS]>) -> Mesh {
+        let mut builder = SweptMesh::default();
+        for segment in &self.segments {
+            let ca: Vec<Vec3> = segment
+                .iter()
+                .map(|residue| Vec3::from(positions[residue.ca]))
+                .collect();
+            let o: Vec<Vec3> = segment
+                .iter()
+                .map(|residue| Vec3::from(positions[residue.o]))
+                .collect();
+            let shapes: Vec<Shape> = segment
+                .iter()
+                .map(|residue| {
+                    Shape::new(
+                        ss.and_then(|ss| ss.get(residue.protein_index))
+                            .unwrap_or(&residue.ss),
+                    )
+                })
+                .collect();
+            builder.sweep(&ca, &o, &shapes);
+        }
+        builder.build()
+    }
+}
+
+// cross section at one point of the trace
+struct Ring {
+    center: Vec3,
+    // direction of the width
+    side: Vec3,
+    width: f32,
+    thickness: f32,
+    color: [f32; 4],
+}
+
+#[derive(Default)]
+struct SweptMesh {
+    positions: Vec<[f32; 3]>,
+    normals: Vec<[f32; 3]>,
+    colors: Vec<[f32; 4]>,
+    indices: Vec<u32>,
+}
+
+impl SweptMesh {
+    // one tube along the CA trace, its cross section follows the SS
+    fn sweep(&mut self, ca: &[Vec3], o: &[Vec3], shapes: &[Shape]) {
+        let n = ca.len();
+
+        // C=O direction, flipped so that the ribbon does not twist
+        let mut sides: Vec<Vec3> = Vec::with_capacity(n);
+        for i in 0..n {
+            let tangent = (ca[(i + 1).min(n - 1)] - ca[i.saturating_sub(1)]).normalize_or_zero();
+            let side = o[i] - ca[i];
+            let mut side = (side - tangent * side.dot(tangent)).normalize_or_zero();
+            if let Some(prev) = sides.last() {
+                if side.dot(*prev) < 0. {
+                    side = -side;
+                }
+            }
+            sides.push(side);
+        }
+
+        let mut rings: Vec<Ring> = Vec::new();
+        for i in 0..n - 1 {
+            let arrow = shapes[i] == Shape::Strand && shapes[i + 1] != Shape::Strand;
+            let steps = if i == n - 2 {
+                CARTOON_STEPS + 1
+            } else {
+                CARTOON_STEPS
+            };
+            for step in 0..steps {
+                let t = step as f32 / CARTOON_STEPS as f32;
+                let center = catmull_rom_interpolate(
+                    ca[i.saturating_sub(1)],
+                    ca[i],
+                    ca[i + 1],
+                    ca[(i + 2).min(n - 1)],
+                    t,
+                );
+                let side = sides[i].lerp(sides[i + 1], t);
+                let ((width, thickness), color) = if arrow {
+                    // from ARROW_WIDTH at the last residue of the strand to the coil
+                    let (_, thickness) = Shape::Strand.section();
+                    let (coil, _) = Shape::Coil.section();
+                    (
+                        (ARROW_WIDTH + (coil - ARROW_WIDTH) * t, thickness),
+                        Shape::Strand.color(),
+                    )
+                } else {
+                    let shape = if t < 0.5 { shapes[i] } else { shapes[i + 1] };
+                    (shape.section(), shape.color())
+                };
+                rings.push(Ring {
+                    center,
+                    side,
+                    width,
+                    thickness,
+                    color,
+                });
+            }
+        }
+        if rings.len() < 2 {
+            return;
+        }
+
+        let first = self.positions.len() as u32;
+        for k in 0..rings.len() {
+            let ring = &rings[k];
+            let tangent = (rings[(k + 1).min(rings.len() - 1)].center
+                - rings[k.saturating_sub(1)].center)
+                .normalize_or_zero();
+            let normal = (ring.side - tangent * ring.side.dot(tangent)).normalize_or_zero();
+            let binormal = tangent.cross(normal);
+            for j in 0..CARTOON_SIDES {
+                let theta = std::f32::consts::TAU * j as f32 / CARTOON_SIDES as f32;
+                let (sin, cos) = theta.sin_cos();
+                let offset =
+                    normal * (ring.width / 2. * cos) + binormal * (ring.thickness / 2. * sin);
+                self.positions.push((ring.center + offset).into());
+                // gradient of the ellipse
+                self.normals.push(
+                    (normal * (cos / ring.width) + binormal * (sin / ring.thickness))
+                        .normalize_or_zero()
+                        .into(),
+                );
+                self.colors.push(ring.color);
+            }
+        }
+        let sides = CARTOON_SIDES as u32;
+        for k in 0..rings.len() as u32 - 1 {
+            for j in 0..sides {
+                let a = first + k * sides + j;
+                let b = first + k * sides + (j + 1) % sides;
+                let c = a + sides;
+                let d = b + sides;
+                self.indices.extend([a, b, c, b, d, c]);
+            }
+        }
+
+        // caps at both ends
+        for (k, sign) in [(0, -1.), (rings.len() - 1, 1.)] {
+            let tangent = (rings[(k + 1).min(rings.len() - 1)].center
+                - rings[k.saturating_sub(1)].center)
+                .normalize_or_zero()
+                * sign;
+            let center_index = self.positions.len() as u32;
+            self.positions.push(rings[k].center.into());
+            self.normals.push(tangent.into());
+            self.colors.push(rings[k].color);
+            let ring_first = first + k as u32 * sides;
+            for j in 0..sides {
+                self.positions
+                    .push(self.positions[(ring_first + j) as usize]);
+                self.normals.push(tangent.into());
+                self.colors.push(rings[k].color);
+            }
+            for j in 0..sides {
+                let a = center_index + 1 + j;
+                let b = center_index + 1 + (j + 1) % sides;
+                if sign > 0. {
+                    self.indices.extend([center_index, a, b]);
+                } else {
+                    self.indices.extend([center_index, b, a]);
+                }
+            }
+        }
+    }
+
+    fn build(self) -> Mesh {
+        Mesh::new(
+            PrimitiveTopology::TriangleList,
+            RenderAssetUsages::default(),
+        )
+        .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, self.positions)
+        .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, self.normals)
+        .with_inserted_attribute(Mesh::ATTRIBUTE_COLOR, self.colors)
+        .with_inserted_indices(Indices::U32(self.indices))
+    }
+}
@@ -18,6 +18,7 @@ impl Plugin for MoguraGuiPlugins {
             .add_systems(Update, poll_rfd_structure)
             .add_systems(Update, poll_rfd_trajectory)
             .add_systems(Update, poll_downloadpdb)
+            .add_systems(Update, poll_ss_timeline)
             .add_systems(Update, update_gui);
     }
 }
@@ -31,9 +32,9 @@ pub struct OccupiedScreenSpace {
     bottom: f32,
 }
 
-// path: String, content: String
+// path: String, content: Vec<u8> (may be compressed)
 #[derive(Component)]
-pub struct SelectedStructureFile(bevy::tasks::Task<Option<(String, String)>>);
+pub struct SelectedStructureFile(bevy::tasks::Task<Option<(String, Vec<u8>)>>);
 
 // path: String
 #[derive(Component)]
@@ -65,7 +66,8 @@ fn poll_rfd_trajectory(
                     trajectory_loader(mogura_state.structure_file.as_ref().unwrap(), &path);
                 match trajectory_data {
                     Ok(trajectory_data) => {
-                        mogura_state.trajectory_data = Some(trajectory_data);
+                        mogura_state.trajectory_data = Some(trajectory_data.into());
+                        mogura_state.ss_timeline = None;
                         mogura_state.logs.push("Trajectory file loaded".to_string());
                     }
                     Err(e) => {
@@ -100,46 +102,21 @@ fn poll_rfd_structure(
         ) {
             commands.entity(entity).despawn_recursive();
 
-            #[allow(unused_variables)]
             let (path, content) = if let Some(result) = result {
                 result
             } else {
                 return;
             };
 
-            #[cfg(not(target_arch = "wasm32"))]
-            {
-                match structure_loader(&path) {
-                    Ok(structure_data) => {
-                        mogura_state.structure_data = Some(structure_data);
-                        mogura_state
-                            .logs
-                            .push("Structure file loaded from path".to_string());
-                    }
-                    Err(e) => {
-                        mogura_state.logs.push(e.to_string());
-                    }
+            match structure_loader_from_content(&content, Some(&path)) {
+                Ok(structure_data) => {
+                    mogura_state.logs.push("Structure file loaded".to_string());
+                    mogura_state.push_diagnostics(structure_data.diagnostics());
+                    mogura_state.structure_data = Some(structure_data.into());
+                    mogura_state.highlighted_atoms.clear();
                 }
-            }
-
-            #[cfg(target_arch = "wasm32")]
-            {
-                let extension = std::path::Path::new(&path)
-                    .extension()
-                    .unwrap()
-                    .to_str()
-                    .unwrap();
-
-                match structure_loader_from_content(&content, &extension) {
-                    Ok(structure_data) => {
-                        mogura_state.structure_data = Some(structure_data);
-                        mogura_state
-                            .logs
-                            .push("Structure file loaded from content".to_string());
-                    }
-                    Err(e) => {
-                        mogura_state.logs.push(e.to_string());
-                    }
+                Err(e) => {
+                    mogura_state.logs.push(e.to_string());
                 }
             }
 
@@ -165,26 +142,70 @@ fn poll_downloadpdb(
         ) {
             commands.entity(entity).despawn_recursive();
 
-            if let Ok(structure_data) = result {
-                if mogura_selections.0.is_empty() {
-                    mogura_selections.0.push(EachSelection::default());
+            match result {
+                Ok(structure_data) => {
+                    if mogura_selections.0.is_empty() {
+                        mogura_selections.0.push(EachSelection::default());
+                    }
+                    mogura_selections.0[0].redraw = true;
+                    mogura_state
+                        .logs
+                        .push("Structure file downloaded".to_string());
+                    mogura_state.push_diagnostics(structure_data.diagnostics());
+                    mogura_state.structure_data = Some(structure_data.into());
+                    mogura_state.highlighted_atoms.clear();
+                    mogura_state.structure_file = None;
+                    mogura_state.init_look_at = true;
+                }
+                Err(e) => {
+                    mogura_state
+                        .logs
+                        .push(format!("Structure file download failed\n{}", e));
                 }
-                mogura_selections.0[0].redraw = true;
-                mogura_state.structure_data = Some(Box::new(structure_data));
-                mogura_state.structure_file = None;
-                mogura_state.init_look_at = true;
+            }
+        }
+    }
+}
+
+// progress: frames done
+#[derive(Component)]
+pub struct ComputeSSTimeline {
+    task: bevy::tasks::Task<mogura_ss::SSTimeline>,
+    progress: std::sync::Arc<std::sync::atomic::AtomicUsize>,
+    n_frame: usize,
+    // the timeline is dropped when another trajectory has been loaded meanwhile
+    trajectory_data: std::sync::Arc<dyn TrajectoryData>,
+}
+
+fn poll_ss_timeline(
+    mut commands: Commands,
+    mut tasks: Query<(Entity, &mut ComputeSSTimeline)>,
+    mut mogura_state: ResMut<MoguraState>,
+) {
+    for (entity, mut compute) in tasks.iter_mut() {
+        if let Some(timeline) = bevy::tasks::futures_lite::future::block_on(
+            bevy::tasks::futures_lite::future::poll_once(&mut compute.task),
+        ) {
+            commands.entity(entity).despawn_recursive();
+
+            let current = mogura_state
+                .trajectory_data
+                .as_ref()
+                .is_some_and(|trajectory_data| {
+                    std::sync::Arc::ptr_eq(trajectory_data, &compute.trajectory_data)
+                });
+            if current {
+                mogura_state.ss_timeline = Some(timeline);
                 mogura_state
                     .logs
-                    .push("Structure file downloaded".to_string());
-            } else {
-                mogura_state.logs.push("Structure file download failed\nPDB ID may not correct. Or some PDB files have dirty format so pdbtbx could not parse".to_string());
+                    .push("Secondary structure timeline computed".to_string());
             }
         }
     }
 }
 
 #[derive(Component)]
-pub struct DownloadPDB(bevy::tasks::Task<Result<PDBData, anyhow::Error>>);
+pub struct DownloadPDB(bevy::tasks::Task<Result<Box<dyn StructureData>, MoguraIoError>>);
 
 #[allow(clippy::too_many_arguments)]
 fn update_gui(
@@ -194,27 +215,54 @@ fn update_gui(
     mut mogura_state: ResMut<MoguraState>,
     mut mogura_selections: ResMut<MoguraSelections>,
     mut target_pdbid: Local<String>,
+    mut fetch_source: Local<FetchSource>,
     mut trackball_camera: Query<&mut TrackballCamera, With<Camera>>,
     mut open_help_window: Local<bool>,
+    mut ss_algo: Local<mogura_ss::SSAlgorithm>,
+    mut open_ss_timeline_window: Local<bool>,
+    mut ss_timeline_texture: Local<Option<egui::TextureHandle>>,
+    mut rama_window: Local<RamaWindow>,
     diagnostics: Res<bevy::diagnostic::DiagnosticsStore>,
+    ss_timeline_tasks: Query<&ComputeSSTimeline>,
 ) {
     let ctx = contexts.ctx_mut();
     let task_pool = bevy::tasks::AsyncComputeTaskPool::get();
 
+    match &mogura_state.ss_timeline {
+        None => *ss_timeline_texture = None,
+        // computed since the last frame
+        Some(timeline) if ss_timeline_texture.is_none() => {
+            *ss_timeline_texture = Some(load_ss_timeline_texture(ctx, timeline));
+            *open_ss_timeline_window = true;
+        }
+        Some(_) => {}
+    }
+
     occupied_screen_space.left = egui::SidePanel::left("left")
         .resizable(true)
         .show(ctx, |ui| {
             ui.label("Controlpanel");
             ui.separator();
             egui::ScrollArea::vertical().show(ui, |ui| {
+                ui.horizontal_wrapped(|ui| {
+                    for source in FetchSource::ALL {
+                        ui.radio_value(&mut *fetch_source, source, source.name());
+                    }
+                });
+
+                let hint_text = match *fetch_source {
+                    FetchSource::AlphaFold => "UniProt accession here. e.g. P69905",
+                    _ => "PDB ID here. e.g. 8GNG",
+                };
                 let _response = egui::TextEdit::singleline(&mut *target_pdbid)
-                    .hint_text("PDB ID here. e.g. 8GNG")
+                    .hint_text(hint_text)
                     .show(ui);
 
                 if ui.button("Start to download").clicked() {
                     let target_pdbid_clone = target_pdbid.clone();
-                    let task = task_pool
-                        .spawn(async move { PDBData::download(&target_pdbid_clone).await });
+                    let fetcher = Fetcher::new(*fetch_source);
+                    let task =
+                        task_pool.spawn(async move { fetcher.fetch(&target_pdbid_clone).await });
                     commands.spawn(DownloadPDB(task));
                 }
 
@@ -232,12 +280,13 @@ fn update_gui(
                         {
                             let task = task_pool.spawn(async move {
                                 if let Some(path) = rfd::FileDialog::new()
-                                    .add_filter("structure file", &["pdb", "gro"])
+                                    .add_filter(
+                                        "structure file",
+                                        &["pdb", "ent", "cif", "mmcif", "gro", "gz", "bz2", "xz"],
+                                    )
                                     .pick_file()
                                 {
-                                    let content =
-                                        std::fs::read_to_string(path.display().to_string())
-                                            .unwrap();
+                                    let content = std::fs::read(&path).ok()?;
                                     Some((path.display().to_string(), content))
                                 } else {
                                     None
@@ -249,13 +298,15 @@ fn update_gui(
                         {
                             let task = task_pool.spawn(async move {
                                 let path = rfd::AsyncFileDialog::new()
-                                    .add_filter("structure file", &["pdb"])
+                                    .add_filter(
+                                        "structure file",
+                                        &["pdb", "ent", "cif", "mmcif", "gz", "bz2"],
+                                    )
                                     .pick_file()
                                     .await;
                                 if let Some(path) = path {
                                     let content = path.read().await;
-                                    let content_str = String::from_utf8(content).unwrap();
-                                    Some((path.file_name(), content_str))
+                                    Some((path.file_name(), content))
                                 } else {
                                     None
                                 }
@@ -267,6 +318,8 @@ fn update_gui(
                     if ui.button("Clear").clicked() {
                         mogura_state.structure_file = None;
                         mogura_state.structure_data = None;
+                        mogura_state.ss_timeline = None;
+                        mogura_state.highlighted_atoms.clear();
                         mogura_state.logs.push("Structure file cleared".to_string());
                     }
                 });
@@ -285,7 +338,7 @@ fn update_gui(
                         {
                             let task = task_pool.spawn(async move {
                                 rfd::FileDialog::new()
-                                    .add_filter("trajectory file", &["xtc"])
+                                    .add_filter("trajectory file", &["xtc", "gz", "bz2", "xz"])
                                     .pick_file()
                                     .map(|path| path.display().to_string())
                             });
@@ -313,6 +366,7 @@ fn update_gui(
                     if ui.button("Clear").clicked() {
                         mogura_state.trajectory_file = None;
                         mogura_state.trajectory_data = None;
+                        mogura_state.ss_timeline = None;
                         mogura_state
                             .logs
                             .push("Trajectory file cleared".to_string());
@@ -378,6 +432,90 @@ fn update_gui(
 
                 ui.separator();
 
+                ui.label("Secondary Structure Timeline");
+                ui.horizontal_wrapped(|ui| {
+                    for algo in mogura_ss::SSAlgorithm::ALL {
+                        ui.radio_value(&mut *ss_algo, algo, algo.name());
+                    }
+                });
+                let computing = ss_timeline_tasks.iter().next();
+                ui.horizontal(|ui| {
+                    if ui
+                        .add_enabled(computing.is_none(), egui::Button::new("Compute"))
+                        .clicked()
+                    {
+                        match (
+                            mogura_state.structure_data.clone(),
+                            mogura_state.trajectory_data.clone(),
+                        ) {
+                            (Some(structure_data), Some(trajectory_data)) => {
+                                let progress =
+                                    std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+                                let task = task_pool.spawn({
+                                    let trajectory_data = trajectory_data.clone();
+                                    let progress = progress.clone();
+                                    let algo = *ss_algo;
+                                    async move {
+                                        mogura_ss::assign_ss_trajectory_with_progress(
+                                            structure_data.as_ref(),
+                                            trajectory_data.as_ref(),
+                                            algo,
+                                            |done| {
+                                                progress.store(
+                                                    done,
+                                                    std::sync::atomic::Ordering::Relaxed,
+                                                )
+                                            },
+                                        )
+                                    }
+                                });
+                                commands.spawn(ComputeSSTimeline {
+                                    task,
+                                    progress,
+                                    n_frame: trajectory_data.n_frame(),
+                                    trajectory_data,
+                                });
+                                mogura_state.ss_timeline = None;
+                                *ss_timeline_texture = None;
+                            }
+                            _ => {
+                                mogura_state.logs.push(
+                                    "Structure and trajectory files are required".to_string(),
+                                );
+                            }
+                        }
+                    }
+
+                    if ui.button("Show").clicked() {
+                        *open_ss_timeline_window = !*open_ss_timeline_window;
+                    }
+                });
+                if let Some(compute) = computing {
+                    let done = compute.progress.load(std::sync::atomic::Ordering::Relaxed);
+                    ui.add(
+                        egui::ProgressBar::new(done as f32 / compute.n_frame.max(1) as f32)
+                            .text(format!("{} / {} frames", done, compute.n_frame)),
+                    );
+                }
+
+                ui.separator();
+
+                ui.label("Ramachandran Plot");
+                ui.horizontal_wrapped(|ui| {
+                    for id in 0..mogura_selections.0.len() {
+                        ui.radio_value(
+                            &mut rama_window.selection_id,
+                            id,
+                            format!("Selection {}", id + 1),
+                        );
+                    }
+                });
+                if ui.button("Show").clicked() {
+                    rama_window.open = !rama_window.open;
+                }
+
+                ui.separator();
+
                 if let Some(value) = diagnostics
                     .get(&bevy::diagnostic::FrameTimeDiagnosticsPlugin::FPS)
                     .and_then(|fps| fps.smoothed())
@@ -436,6 +574,15 @@ fn update_gui(
         .rect
         .width();
 
+    show_ss_timeline_window(
+        ctx,
+        &mut mogura_state,
+        &mut open_ss_timeline_window,
+        ss_timeline_texture.as_ref(),
+    );
+
+    show_rama_window(ctx, &mut mogura_state, &mogura_selections, &mut rama_window);
+
     occupied_screen_space.right = egui::SidePanel::right("right")
         .resizable(true)
         .show(ctx, |ui| {
@@ -444,21 +591,43 @@ fn update_gui(
             let mut logs = Vec::with_capacity(10);
             egui::ScrollArea::vertical().show(ui, |ui| {
                 if let Some(structure_data) = &mogura_state.structure_data {
+                    let macros = mogura_selections.macros(&mogura_state.macros);
                     for selection in mogura_selections.0.iter_mut() {
+                        ui.horizontal(|ui| {
+                            ui.label("$");
+                            egui::TextEdit::singleline(&mut selection.name)
+                                .hint_text("name")
+                                .show(ui);
+                        });
                         let _response = egui::TextEdit::singleline(&mut selection.atom_selection)
                             .hint_text("protein")
                             .show(ui);
                         if ui.button("Apply").clicked() {
-                            let selection_result = selection.apply_selection(structure_data);
+                            let bonds = structure_data.bonds_indirected();
+                            let selection_result = selection.apply_selection(
+                                structure_data.as_ref(),
+                                None,
+                                &bonds,
+                                &macros,
+                            );
                             match selection_result {
                                 Ok(_) => {
                                     selection.redraw = true;
+                                    selection.error = None;
                                 }
                                 Err(e) => {
-                                    logs.push(format!("Atom Selection Error: {}", e));
+                                    selection.error = Some(e);
                                 }
                             }
                         }
+                        // with a caret under the position
+                        if let Some(error) = &selection.error {
+                            ui.label(
+                                egui::RichText::new(error)
+                                    .monospace()
+                                    .color(ui.visuals().error_fg_color),
+                            );
+                        }
 
                         ui.label("Select Drawing Method");
                         let pre_drawing_method = selection.drawing_method;
@@ -486,7 +655,11 @@ fn update_gui(
                             "Stick",
                         );
                         ui.radio_value(&mut selection.drawing_method, DrawingMethod::Tube, "Tube");
-                        // ui.radio_value(&mut mogura_state.drawingMethod, DrawingMethod::NewCartoon, "NewCartoon");
+                        ui.radio_value(
+                            &mut selection.drawing_method,
+                            DrawingMethod::Cartoon,
+                            "Cartoon",
+                        );
                         // ui.radio_value(&mut mogura_state.drawingMethod, DrawingMethod::NewCartoon, "NewCartoon");
                         if pre_drawing_method != selection.drawing_method {
                             logs.push("Drawing method changed".to_string());
@@ -543,6 +716,353 @@ fn update_gui(
         .height();
 }
 
+// points per column of the texture
+const SS_TIMELINE_FRAME_WIDTH: f32 = 2.0;
+const SS_TIMELINE_RESIDUE_HEIGHT: f32 = 4.0;
+
+// close to the colors of gmx dssp
+fn ss_color(ss: &mogura_ss::SS) -> egui::Color32 {
+    match ss {
+        mogura_ss::SS::H => egui::Color32::from_rgb(0, 0, 255),
+        mogura_ss::SS::B => egui::Color32::from_rgb(0, 0, 0),
+        mogura_ss::SS::E => egui::Color32::from_rgb(255, 0, 0),
+        mogura_ss::SS::G => egui::Color32::from_rgb(128, 128, 128),
+        mogura_ss::SS::I => egui::Color32::from_rgb(128, 0, 128),
+        mogura_ss::SS::P => egui::Color32::from_rgb(0, 192, 192),
+        mogura_ss::SS::S => egui::Color32::from_rgb(0, 128, 0),
+        mogura_ss::SS::T => egui::Color32::from_rgb(255, 255, 0),
+        mogura_ss::SS::Break => egui::Color32::from_rgb(64, 64, 64),
+        mogura_ss::SS::Loop => egui::Color32::from_rgb(255, 255, 255),
+    }
+}
+
+// max texture side of wgpu, WebGL2 only guarantees 2048
+#[cfg(not(target_arch = "wasm32"))]
+const SS_TIMELINE_MAX_WIDTH: usize = 8192;
+#[cfg(target_arch = "wasm32")]
+const SS_TIMELINE_MAX_WIDTH: usize = 2048;
+
+// consecutive frames in one column of the texture
+fn ss_timeline_frames_per_column(n_frame: usize) -> usize {
+    n_frame.div_ceil(SS_TIMELINE_MAX_WIDTH).max(1)
+}
+
+// x: frame, y: residue, the most frequent SS of the frames in a column
+fn load_ss_timeline_texture(
+    ctx: &egui::Context,
+    timeline: &mogura_ss::SSTimeline,
+) -> egui::TextureHandle {
+    let frames_per_column = ss_timeline_frames_per_column(timeline.n_frame());
+    let n_column = timeline.n_frame().div_ceil(frames_per_column);
+    let mut image = egui::ColorImage::new(
+        [n_column.max(1), timeline.n_residue().max(1)],
+        egui::Color32::WHITE,
+    );
+    let mut counts: Vec<(&mogura_ss::SS, usize)> = Vec::new();
+    for column in 0..n_column {
+        let frames =
+            column * frames_per_column..((column + 1) * frames_per_column).min(timeline.n_frame());
+        for residue_id in 0..timeline.n_residue() {
+            counts.clear();
+            for frame_id in frames.clone() {
+                let ss = &timeline.frame(frame_id)[residue_id];
+                match counts.iter_mut().find(|(other, _)| *other == ss) {
+                    Some((_, count)) => *count += 1,
+                    None => counts.push((ss, 1)),
+                }
+            }
+            if let Some((ss, _)) = counts.iter().max_by_key(|(_, count)| *count) {
+                image[(column, residue_id)] = ss_color(ss);
+            }
+        }
+    }
+    ctx.load_texture("ss_timeline", image, egui::TextureOptions::NEAREST)
+}
+
+fn show_ss_timeline_window(
+    ctx: &egui::Context,
+    mogura_state: &mut MoguraState,
+    open: &mut bool,
+    texture: Option<&egui::TextureHandle>,
+) {
+    let (timeline, texture) = match (&mogura_state.ss_timeline, texture) {
+        (Some(timeline), Some(texture)) => (timeline, texture),
+        _ => return,
+    };
+    let (n_frame, n_residue) = (timeline.n_frame(), timeline.n_residue());
+    let frames_per_column = ss_timeline_frames_per_column(n_frame);
+    let current_frame_id = mogura_state.current_frame_id;
+    let playing = mogura_state.update_trajectory || mogura_state.loop_trajectory;
+    let mut selected_frame_id = None;
+
+    egui::Window::new("Secondary Structure Timeline")
+        .open(open)
+        .resizable(true)
+        .collapsible(true)
+        .show(ctx, |ui| {
+            ui.label(format!(
+                "{}: {} frames x {} residues",
+                timeline.algo().name(),
+                n_frame,
+                n_residue
+            ));
+            ui.horizontal_wrapped(|ui| {
+                for ss in [
+                    mogura_ss::SS::H,
+                    mogura_ss::SS::G,
+                    mogura_ss::SS::I,
+                    mogura_ss::SS::E,
+                    mogura_ss::SS::B,
+                    mogura_ss::SS::P,
+                    mogura_ss::SS::T,
+                    mogura_ss::SS::S,
+                    mogura_ss::SS::Loop,
+                    mogura_ss::SS::Break,
+                ] {
+                    ui.label(
+                        egui::RichText::new(format!(" {} ", ss.to_char()))
+                            .background_color(ss_color(&ss))
+                            .color(egui::Color32::GRAY),
+                    );
+                }
+            });
+            egui::ScrollArea::both().show(ui, |ui| {
+                let size = egui::vec2(
+                    texture.size()[0] as f32 * SS_TIMELINE_FRAME_WIDTH,
+                    n_residue as f32 * SS_TIMELINE_RESIDUE_HEIGHT,
+                );
+                let response = ui.add(
+                    egui::Image::new(egui::load::SizedTexture::new(texture.id(), size))
+                        .sense(egui::Sense::click_and_drag()),
+                );
+                let rect = response.rect;
+                let to_frame_residue = |pos: egui::Pos2| {
+                    (
+                        (((pos.x - rect.left()) / SS_TIMELINE_FRAME_WIDTH
+                            * frames_per_column as f32) as usize)
+                            .min(n_frame.saturating_sub(1)),
+                        (((pos.y - rect.top()) / SS_TIMELINE_RESIDUE_HEIGHT) as usize)
+                            .min(n_residue.saturating_sub(1)),
+                    )
+                };
+
+                let x = rect.left()
+                    + (current_frame_id as f32 + 0.5) / frames_per_column as f32
+                        * SS_TIMELINE_FRAME_WIDTH;
+                ui.painter().vline(
+                    x,
+                    rect.y_range(),
+                    egui::Stroke::new(1.0, egui::Color32::from_rgb(255, 128, 0)),
+                );
+                if playing {
+                    let visible = ui.clip_rect();
+                    ui.scroll_to_rect(egui::Rect::from_x_y_ranges(x..=x, visible.y_range()), None);
+                }
+
+                if let Some(pos) = response.interact_pointer_pos() {
+                    selected_frame_id = Some(to_frame_residue(pos).0);
+                }
+                if let Some(pos) = response.hover_pos() {
+                    let (frame_id, residue_id) = to_frame_residue(pos);
+                    response.on_hover_text(format!(
+                        "frame {}, residue {}: {}",
+                        frame_id,
+                        residue_id + 1,
+                        timeline.frame(frame_id)[residue_id].to_char()
+                    ));
+                }
+            });
+        });
+
+    if let Some(frame_id) = selected_frame_id {
+        mogura_state.current_frame_id = frame_id;
+        mogura_state.update_tmp_trajectory = true;
+    }
+}
+
+const RAMA_PLOT_SIZE: f32 = 360.0;
+// 2 degrees per pixel
+const RAMA_TEXTURE_SIZE: usize = 180;
+
+#[derive(Default)]
+pub struct RamaWindow {
+    open: bool,
+    // index of MoguraSelections
+    selection_id: usize,
+    texture: Option<egui::TextureHandle>,
+}
+
+fn rama_region_color(region: mogura_ss::RamaRegion) -> egui::Color32 {
+    match region {
+        mogura_ss::RamaRegion::Favored => egui::Color32::from_rgb(120, 160, 220),
+        mogura_ss::RamaRegion::Allowed => egui::Color32::from_rgb(200, 220, 245),
+        mogura_ss::RamaRegion::Outlier => egui::Color32::WHITE,
+    }
+}
+
+// x: phi, y: psi from +180 (top) to -180
+fn load_rama_texture(ctx: &egui::Context) -> egui::TextureHandle {
+    let mut image =
+        egui::ColorImage::new([RAMA_TEXTURE_SIZE, RAMA_TEXTURE_SIZE], egui::Color32::WHITE);
+    let degree = |k: usize| (k as f32 + 0.5) * 360. / RAMA_TEXTURE_SIZE as f32 - 180.;
+    for x in 0..RAMA_TEXTURE_SIZE {
+        for y in 0..RAMA_TEXTURE_SIZE {
+            image[(x, y)] = rama_region_color(mogura_ss::rama_region(degree(x), -degree(y)));
+        }
+    }
+    ctx.load_texture("ramachandran", image, egui::TextureOptions::LINEAR)
+}
+
+// phi/psi of the protein residues in the selection, at the current frame
+fn show_rama_window(
+    ctx: &egui::Context,
+    mogura_state: &mut MoguraState,
+    mogura_selections: &MoguraSelections,
+    rama_window: &mut RamaWindow,
+) {
+    let RamaWindow {
+        open,
+        selection_id,
+        texture,
+    } = rama_window;
+    if !*open {
+        return;
+    }
+    let (structure_data, selection) = match (
+        &mogura_state.structure_data,
+        mogura_selections.0.get(*selection_id),
+    ) {
+        (Some(structure_data), Some(selection)) => (structure_data.as_ref(), selection),
+        _ => return,
+    };
+    let texture = texture.get_or_insert_with(|| load_rama_texture(ctx));
+
+    let torsions = match &mogura_state.trajectory_data {
+        Some(trajectory_data) => mogura_ss::torsions_frame(
+            structure_data,
+            trajectory_data
+                .frame(mogura_state.current_frame_id)
+                .positions(),
+        ),
+        None => {
+            let positions: Vec<[f32; 3]> = structure_data
+                .atoms()
+                .iter()
+                .map(|atom| [atom.x(), atom.y(), atom.z()])
+                .collect();
+            mogura_ss::torsions_frame(structure_data, &positions)
+        }
+    };
+    // label, atom ids, phi, psi
+    let points: Vec<(String, Vec<usize>, f32, f32)> = structure_data
+        .residues()
+        .iter()
+        .filter(|residue| residue.is_protein())
+        .zip(torsions)
+        .filter_map(|(residue, torsion)| {
+            let atom_ids: Vec<usize> = residue.atoms().iter().map(|atom| atom.id()).collect();
+            if !atom_ids
+                .iter()
+                .any(|id| selection.selected_atoms.contains(id))
+            {
+                return None;
+            }
+            let first = residue.atoms().first()?;
+            let label = format!(
+                "{} {} {}",
+                residue.chain_name(),
+                residue.residue_name(),
+                first.residue_id()
+            );
+            Some((label, atom_ids, torsion.phi?, torsion.psi?))
+        })
+        .collect();
+
+    let mut clicked = None;
+    egui::Window::new("Ramachandran Plot")
+        .open(open)
+        .resizable(false)
+        .collapsible(true)
+        .show(ctx, |ui| {
+            ui.label(format!(
+                "Selection {}: {} residues",
+                *selection_id + 1,
+                points.len()
+            ));
+            ui.horizontal_wrapped(|ui| {
+                for region in [
+                    mogura_ss::RamaRegion::Favored,
+                    mogura_ss::RamaRegion::Allowed,
+                ] {
+                    ui.label(
+                        egui::RichText::new(format!(" {:?} ", region))
+                            .background_color(rama_region_color(region))
+                            .color(egui::Color32::BLACK),
+                    );
+                }
+            });
+
+            let (response, painter) = ui.allocate_painter(
+                egui::vec2(RAMA_PLOT_SIZE, RAMA_PLOT_SIZE),
+                egui::Sense::click(),
+            );
+            let rect = response.rect;
+            painter.image(
+                texture.id(),
+                rect,
+                egui::Rect::from_min_max(egui::pos2(0., 0.), egui::pos2(1., 1.)),
+                egui::Color32::WHITE,
+            );
+            let to_screen = |phi: f32, psi: f32| {
+                egui::pos2(
+                    rect.left() + (phi + 180.) / 360. * rect.width(),
+                    rect.top() + (180. - psi) / 360. * rect.height(),
+                )
+            };
+            let axis = egui::Stroke::new(1.0, egui::Color32::GRAY);
+            painter.line_segment([to_screen(0., -180.), to_screen(0., 180.)], axis);
+            painter.line_segment([to_screen(-180., 0.), to_screen(180., 0.)], axis);
+
+            for (_, atom_ids, phi, psi) in &points {
+                let color = if *atom_ids == mogura_state.highlighted_atoms {
+                    egui::Color32::from_rgb(255, 128, 0)
+                } else {
+                    egui::Color32::BLACK
+                };
+                painter.circle_filled(to_screen(*phi, *psi), 2.5, color);
+            }
+
+            let nearest = |pos: egui::Pos2| {
+                points
+                    .iter()
+                    .enumerate()
+                    .map(|(k, (_, _, phi, psi))| (k, to_screen(*phi, *psi).distance(pos)))
+                    .filter(|(_, distance)| *distance < 5.)
+                    .min_by(|a, b| a.1.total_cmp(&b.1))
+                    .map(|(k, _)| k)
+            };
+            if response.clicked() {
+                clicked = Some(response.interact_pointer_pos().and_then(nearest));
+            }
+            if let Some(k) = response.hover_pos().and_then(nearest) {
+                let (label, _, phi, psi) = &points[k];
+                response.on_hover_text(format!("{}: phi {:.1}, psi {:.1}", label, phi, psi));
+            }
+            ui.label("x: phi, y: psi (-180..180)");
+        });
+
+    // clicking the background clears the highlight
+    match clicked {
+        Some(Some(k)) => {
+            let (label, atom_ids, _, _) = &points[k];
+            mogura_state.highlighted_atoms = atom_ids.clone();
+            mogura_state.logs.push(format!("{} highlighted", label));
+        }
+        Some(None) => mogura_state.highlighted_atoms.clear(),
+        None => {}
+    }
+}
+
 // https://github.com/vladbat00/bevy_egui/blob/main/examples/side_panel.rs
 // pub fn update_camera_window_transform(
 //     occupied_screen_space: Res<OccupiedScreenSpace>,
@@ -3,6 +3,7 @@ use bevy::prelude::*;
 use mogura_io::prelude::*;
 
 mod camera;
+mod cartoon;
 mod gui;
 mod light;
 mod structure;
@@ -51,42 +52,83 @@ impl Plugin for MoguraPlugins {
 
 #[derive(Debug, Clone)]
 pub struct EachSelection {
+    /// referenced by the other selections as `$name`
+    pub name: String,
     pub atom_selection: String,
     pub drawing_method: structure::DrawingMethod,
     pub selected_atoms: std::collections::HashSet<usize>,
     pub selected_bonds: std::collections::HashSet<(usize, usize)>,
     pub redraw: bool,
     pub delete: bool,
+    /// of the last apply, shown under the selection
+    pub error: Option<String>,
 }
 
 impl EachSelection {
-    #[allow(clippy::borrowed_box)]
+    // `define <name> as ...` also names the selection
+    fn statement(&self) -> Result<(Option<String>, mogura_asl::Selection), String> {
+        match mogura_asl::parse_statement(&self.atom_selection).map_err(|e| e.to_string())? {
+            mogura_asl::Statement::Define(name, selection) => Ok((Some(name), selection)),
+            mogura_asl::Statement::Select(selection) => Ok((None, selection)),
+        }
+    }
+
+    /// `positions` of the current frame, for selections depending on the distance,
+    /// and `bonds` of the structure, for `bymolecule`
     pub fn apply_selection(
         &mut self,
-        structure_data: &Box<dyn StructureData>,
+        structure_data: &dyn StructureData,
+        positions: Option<&[[f32; 3]]>,
+        bonds: &[(usize, usize)],
+        macros: &mogura_asl::Macros,
     ) -> Result<(), String> {
-        let selection = mogura_asl::parse_selection(&self.atom_selection)?;
-        let (selected_atoms, selected_bonds) = {
-            let atoms = structure_data.as_ref().atoms();
-            let bonds = structure_data.as_ref().bonds_indirected();
-            let (selected_atoms, selected_bonds) = selection.select_atoms_bonds(atoms, &bonds);
-            (selected_atoms, selected_bonds)
+        let (name, selection) = self.statement()?;
+        let selection = selection.expand(macros)?;
+        if let Some(name) = name {
+            self.name = name;
+        }
+        let atoms = structure_data.atoms();
+        let context = SelectionContext::new(atoms).with_bonds(bonds);
+        let context = match positions {
+            Some(positions) => context.with_positions(positions),
+            None => context,
         };
-        self.selected_atoms = selected_atoms;
-        self.selected_bonds = selected_bonds;
+        self.selected_atoms = selection
+            .select(&context)
+            .into_iter()
+            .map(|i| atoms[i].id())
+            .collect();
         Ok(())
     }
+
+    pub fn apply_bonds(&mut self, bonds: &[(usize, usize)]) {
+        self.selected_bonds = bonds
+            .iter()
+            .filter(|bond| {
+                self.selected_atoms.contains(&bond.0) && self.selected_atoms.contains(&bond.1)
+            })
+            .copied()
+            .collect();
+    }
+
+    pub fn is_dynamic(&self, macros: &mogura_asl::Macros) -> bool {
+        self.statement()
+            .and_then(|(_, selection)| selection.expand(macros))
+            .is_ok_and(|s| s.is_dynamic())
+    }
 }
 
 impl Default for EachSelection {
     fn default() -> Self {
         Self {
+            name: String::new(),
             atom_selection: "all".to_string(),
             drawing_method: structure::DrawingMethod::BallAndStick,
             selected_atoms: std::collections::HashSet::new(),
             selected_bonds: std::collections::HashSet::new(),
             redraw: true,
             delete: false,
+            error: None,
         }
     }
 }
@@ -98,20 +140,39 @@ impl MoguraSelections {
     pub fn new(init_num: usize) -> Self {
         Self(vec![EachSelection::default(); init_num])
     }
+
+    /// `macros` from the config file and the named selections
+    pub fn macros(&self, macros: &mogura_asl::Macros) -> mogura_asl::Macros {
+        let mut macros = macros.clone();
+        for selection in &self.0 {
+            match selection.statement() {
+                Ok((Some(name), selection)) => macros.define(&name, selection),
+                Ok((None, s)) if !selection.name.is_empty() => macros.define(&selection.name, s),
+                _ => {}
+            }
+        }
+        macros
+    }
 }
 
 #[derive(Resource)]
 pub struct MoguraState {
     pub structure_file: Option<String>,
-    pub structure_data: Option<Box<dyn StructureData>>,
+    // shared with tasks such as the SS timeline
+    pub structure_data: Option<std::sync::Arc<dyn StructureData>>,
     pub trajectory_file: Option<String>,
-    pub trajectory_data: Option<Box<dyn TrajectoryData>>,
+    pub trajectory_data: Option<std::sync::Arc<dyn TrajectoryData>>,
     pub update_trajectory: bool,
     pub update_tmp_trajectory: bool,
     pub loop_trajectory: bool,
     pub current_frame_id: usize,
+    pub ss_timeline: Option<mogura_ss::SSTimeline>,
+    // atom ids picked in the GUI, drawn with a wireframe in the 3D view
+    pub highlighted_atoms: Vec<usize>,
     pub init_look_at: bool,
     pub logs: Vec<String>,
+    // user macros of mogura_asl::default_macro_file
+    pub macros: mogura_asl::Macros,
     // pub selections: Vec<EachSelection>,
 }
 
@@ -138,21 +199,42 @@ impl MoguraState {
             None
         };
 
+        let mut logs = Vec::new();
+        if let Some(structure_data) = &structure_data {
+            logs.extend(structure_data.diagnostics().iter().map(|d| d.to_string()));
+        }
+
+        let macros = match mogura_asl::default_macro_file().filter(|path| path.exists()) {
+            Some(path) => mogura_asl::Macros::load(&path).unwrap_or_else(|e| {
+                logs.push(format!("Macro Error: {}", e));
+                mogura_asl::Macros::default()
+            }),
+            None => mogura_asl::Macros::default(),
+        };
+
         Self {
-            structure_data,
+            structure_data: structure_data.map(Into::into),
             structure_file,
-            trajectory_data,
+            trajectory_data: trajectory_data.map(Into::into),
             trajectory_file,
             update_trajectory: false,
             update_tmp_trajectory: false,
             loop_trajectory: false,
             current_frame_id: 0,
+            ss_timeline: None,
+            highlighted_atoms: Vec::new(),
             init_look_at: true,
-            logs: Vec::new(),
+            logs,
+            macros,
             // selections: vec![EachSelection::default()],
         }
     }
 
+    pub fn push_diagnostics(&mut self, diagnostics: &[Diagnostic]) {
+        self.logs
+            .extend(diagnostics.iter().map(|diagnostic| diagnostic.to_string()));
+    }
+
     pub fn n_frame(&self) -> Option<usize> {
         self.trajectory_data.as_ref().map(|td| td.n_frame())
     }
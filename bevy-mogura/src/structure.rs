@@ -12,7 +12,6 @@ use bevy::{
         },
     },
 };
-use itertools::Itertools;
 
 pub(crate) const INTERPOLATION_STEPS: usize = 30;
 
@@ -22,7 +21,37 @@ pub struct MoguraStructurePlugins;
 impl Plugin for MoguraStructurePlugins {
     fn build(&self, app: &mut App) {
         app.add_plugins(MaterialPlugin::<LineMaterial>::default())
-            .add_systems(Update, update_structure);
+            .add_systems(Update, update_structure)
+            .add_systems(Update, draw_highlighted_atoms);
+    }
+}
+
+const HIGHLIGHT_RADIUS: f32 = 0.8;
+
+fn draw_highlighted_atoms(mogura_state: Res<MoguraState>, mut gizmos: Gizmos) {
+    let structure_data = match &mogura_state.structure_data {
+        Some(structure_data) => structure_data,
+        None => return,
+    };
+    let frame = mogura_state
+        .trajectory_data
+        .as_ref()
+        .map(|trajectory_data| trajectory_data.frame(mogura_state.current_frame_id));
+    for &id in &mogura_state.highlighted_atoms {
+        let position = match frame {
+            Some(frame) => frame.positions().get(id).copied(),
+            None => structure_data
+                .atoms()
+                .get(id)
+                .map(|atom| [atom.x(), atom.y(), atom.z()]),
+        };
+        if let Some(position) = position {
+            gizmos.sphere(
+                Isometry3d::from_translation(Vec3::from(position)),
+                HIGHLIGHT_RADIUS,
+                Color::srgb(1.0, 0.5, 0.0),
+            );
+        }
     }
 }
 
@@ -33,7 +62,7 @@ pub enum DrawingMethod {
     BallAndStick,
     Stick,
     Tube,
-    // Cartoon,
+    Cartoon,
     // NewCartoon,
 }
 
@@ -97,60 +126,6 @@ impl InterpolationID {
     }
 }
 
-pub trait MoguraSelection {
-    fn eval(&self, atom: &Atom) -> bool;
-    fn select_atoms(&self, atoms: &[Atom]) -> std::collections::HashSet<usize> {
-        atoms
-            .iter()
-            .filter(|atom| self.eval(atom))
-            .map(|atom| atom.id())
-            .collect()
-    }
-    fn select_atoms_bonds(
-        &self,
-        atoms: &[Atom],
-        bonds: &[(usize, usize)],
-    ) -> (
-        std::collections::HashSet<usize>,
-        std::collections::HashSet<(usize, usize)>,
-    ) {
-        let selected_atoms = self.select_atoms(atoms);
-        let selected_bonds = bonds
-            .iter()
-            .filter(|bond| selected_atoms.contains(&bond.0) && selected_atoms.contains(&bond.1))
-            .copied()
-            .collect();
-        (selected_atoms, selected_bonds)
-    }
-}
-
-impl MoguraSelection for mogura_asl::Selection {
-    fn eval(&self, atom: &Atom) -> bool {
-        match self {
-            mogura_asl::Selection::All => true,
-            mogura_asl::Selection::ResName(names) => {
-                names.iter().any(|name| name == atom.residue_name())
-            }
-            mogura_asl::Selection::ResId(ids) => {
-                ids.iter().any(|id| *id == atom.residue_id() as usize)
-            }
-            mogura_asl::Selection::Index(indices) => {
-                indices.iter().any(|index| *index == atom.atom_id())
-            }
-            mogura_asl::Selection::Name(names) => names.iter().any(|name| name == atom.atom_name()),
-            mogura_asl::Selection::Not(selection) => !selection.eval(atom),
-            mogura_asl::Selection::And(selections) => selections.iter().all(|s| s.eval(atom)),
-            mogura_asl::Selection::Or(selections) => selections.iter().any(|s| s.eval(atom)),
-            mogura_asl::Selection::Bracket(selection) => selection.eval(atom),
-            mogura_asl::Selection::Protein => atom.is_protein(),
-            mogura_asl::Selection::Water => atom.is_water(),
-            mogura_asl::Selection::Ion => atom.is_ion(),
-            mogura_asl::Selection::Backbone => atom.is_backbone(),
-            mogura_asl::Selection::Sidechain => atom.is_sidechain(),
-        }
-    }
-}
-
 #[allow(clippy::too_many_arguments)]
 fn update_structure(
     mut commands: Commands,
@@ -200,6 +175,7 @@ fn update_structure(
         }
     }
 
+    let macros = mogura_selections.macros(&mogura_state.macros);
     for (selection_id, selection) in mogura_selections.0.iter_mut().enumerate() {
         if !selection.redraw {
             continue;
@@ -233,9 +209,26 @@ fn update_structure(
             }
         };
 
-        selection
-            .apply_selection(mogura_state.structure_data.as_ref().unwrap())
-            .unwrap(); // already checked
+        let positions = mogura_state
+            .trajectory_data
+            .as_ref()
+            .map(|trajectory_data| {
+                trajectory_data
+                    .frame(mogura_state.current_frame_id)
+                    .positions()
+                    .as_slice()
+            });
+        if let Err(e) = selection.apply_selection(
+            mogura_state.structure_data.as_deref().unwrap(),
+            positions,
+            &bonds,
+            &macros,
+        ) {
+            // checked when applied, but a referenced selection may have changed since
+            selection.error = Some(e);
+            selection.selected_atoms.clear();
+        }
+        selection.apply_bonds(&bonds);
 
         commands
             .spawn((
@@ -531,169 +524,30 @@ fn update_structure(
                             InterpolationID::new(start_id, end_id),
                         ));
                     }
-                } //
-                  // TODO
-                  // accuracy of ss is low
-                  // cartoon is not used currently
-                  //
-                  // DrawingMethod::Cartoon => {
-                  //     let cylinder = meshes.add(Cylinder {
-                  //         radius: 1.,
-                  //         ..default()
-                  //     });
-                  //
-                  //     let protein = {
-                  //         let mut protein = Vec::with_capacity(atoms.len());
-                  //         for atom in atoms {
-                  //             if atom.is_protein() {
-                  //                 protein.push(atom.clone());
-                  //             }
-                  //         }
-                  //         protein
-                  //     };
-                  //
-                  //     let ss = mogura_ss::assign_ss(
-                  //         &SSConverter(protein.clone()).into(),
-                  //         mogura_ss::SSAlgorithm::Ramachandran,
-                  //     );
-                  //     let ss_grouped = ss
-                  //         .iter()
-                  //         .enumerate()
-                  //         .chunk_by(|&(_, ss)| ss.clone())
-                  //         .into_iter()
-                  //         .map(|(_, group)| group.map(|(i, ss)| (i, ss.clone())).collect())
-                  //         .collect::<Vec<Vec<(usize, mogura_ss::SS)>>>();
-                  //     dbg!(&ss_grouped);
-                  //
-                  //     let mut target_atoms = Vec::with_capacity(atoms.len());
-                  //
-                  //     for atom in atoms {
-                  //         if !atom.is_backbone()
-                  //             || (atom.is_backbone() && atom.atom_name() == "HA")
-                  //             || (atom.is_backbone() && atom.atom_name() == "O")
-                  //         {
-                  //             continue;
-                  //         }
-                  //         target_atoms.push(atom.id());
-                  //     }
-                  //
-                  //     let mut points = Vec::with_capacity(target_atoms.len() * INTERPOLATION_STEPS);
-                  //     for i in 1..target_atoms.len() - 2 {
-                  //         for j in 0..=INTERPOLATION_STEPS {
-                  //             let t = j as f32 / INTERPOLATION_STEPS as f32;
-                  //             let point = catmull_rom_interpolate(
-                  //                 Vec3::new(
-                  //                     atoms[target_atoms[i - 1]].x(),
-                  //                     atoms[target_atoms[i - 1]].y(),
-                  //                     atoms[target_atoms[i - 1]].z(),
-                  //                 ),
-                  //                 Vec3::new(
-                  //                     atoms[target_atoms[i]].x(),
-                  //                     atoms[target_atoms[i]].y(),
-                  //                     atoms[target_atoms[i]].z(),
-                  //                 ),
-                  //                 Vec3::new(
-                  //                     atoms[target_atoms[i + 1]].x(),
-                  //                     atoms[target_atoms[i + 1]].y(),
-                  //                     atoms[target_atoms[i + 1]].z(),
-                  //                 ),
-                  //                 Vec3::new(
-                  //                     atoms[target_atoms[i + 2]].x(),
-                  //                     atoms[target_atoms[i + 2]].y(),
-                  //                     atoms[target_atoms[i + 2]].z(),
-                  //                 ),
-                  //                 t,
-                  //             );
-                  //             points.push(point);
-                  //         }
-                  //     }
-                  //
-                  //     // Loop
-                  //     for point in points.windows(2) {
-                  //         let start = point[0];
-                  //         let end = point[1];
-                  //         let direction = end - start;
-                  //         let length = direction.length();
-                  //         let rotation = Quat::from_rotation_arc(Vec3::Y, direction.normalize());
-                  //         if length > GENERAL_BOND_CUTOFF / INTERPOLATION_STEPS as f32 * 2. {
-                  //             continue;
-                  //         }
-                  //         parent.spawn((
-                  //             Mesh3d(cylinder.clone()),
-                  //             Transform {
-                  //                 translation: start,
-                  //                 rotation,
-                  //                 scale: Vec3::ONE * length,
-                  //             },
-                  //         ));
-                  //     }
-                  //
-                  //     for group in ss_grouped {
-                  //         let ty = &group.first().unwrap().1;
-                  //         let resid_start = group.first().unwrap().0;
-                  //         let resid_end = group.last().unwrap().0;
-                  //
-                  //         dbg!(&resid_start, &resid_end);
-                  //
-                  //         let atom_start = match residues[resid_start]
-                  //             .atoms()
-                  //             .iter()
-                  //             .find(|atom| atom.atom_name() == "N")
-                  //             .map(|atom| atom.id())
-                  //         {
-                  //             Some(id) => id,
-                  //             None => continue,
-                  //         };
-                  //
-                  //         let atom_end = match residues[resid_end]
-                  //             .atoms()
-                  //             .iter()
-                  //             .find(|atom| atom.atom_name() == "C")
-                  //             .map(|atom| atom.id())
-                  //         {
-                  //             Some(id) => id,
-                  //             None => continue,
-                  //         };
-                  //
-                  //         match ty {
-                  //             mogura_ss::SS::H => {
-                  //                 let start = Vec3::new(
-                  //                     atoms[atom_start].x(),
-                  //                     atoms[atom_start].y(),
-                  //                     atoms[atom_start].z(),
-                  //                 );
-                  //                 let end = Vec3::new(
-                  //                     atoms[atom_end].x(),
-                  //                     atoms[atom_end].y(),
-                  //                     atoms[atom_end].z(),
-                  //                 );
-                  //                 let direction = end - start;
-                  //                 let length = direction.length();
-                  //                 let rotation =
-                  //                     Quat::from_rotation_arc(Vec3::Y, direction.normalize());
-                  //                 parent.spawn((
-                  //                     Mesh3d(meshes.add(Cylinder {
-                  //                         radius: 0.7 / length,
-                  //                         ..default()
-                  //                     })),
-                  //                     Transform {
-                  //                         translation: (start + end) / 2.,
-                  //                         rotation,
-                  //                         scale: Vec3::ONE * length,
-                  //                     },
-                  //                 ));
-                  //             }
-                  //             mogura_ss::SS::E => {}
-                  //             mogura_ss::SS::Loop => (),
-                  //         }
-                  //     }
-                  // }
-                  // DrawingMethod::NewCartoon => {}
+                }
+                DrawingMethod::Cartoon => {
+                    let structure_data = mogura_state.structure_data.as_ref().unwrap().as_ref();
+                    let cartoon = cartoon::Cartoon::new(structure_data, &selection.selected_atoms);
+                    if cartoon.is_empty() {
+                        return;
+                    }
+                    let positions: Vec<[f32; 3]> = atoms.iter().map(|atom| atom.xyz()).collect();
+                    parent.spawn((
+                        Mesh3d(meshes.add(cartoon.mesh(&positions, None))),
+                        // colored by the vertex colors
+                        MeshMaterial3d(materials.add(Color::WHITE)),
+                        cartoon,
+                    ));
+                } // DrawingMethod::NewCartoon => {}
             });
 
         if mogura_state.init_look_at {
             mogura_state.init_look_at = false;
         }
+        // drawn with the positions of the structure file, move to the current frame
+        if mogura_state.trajectory_data.is_some() {
+            mogura_state.update_tmp_trajectory = true;
+        }
     }
 }
 
@@ -708,33 +562,6 @@ pub fn catmull_rom_interpolate(p0: Vec3, p1: Vec3, p2: Vec3, p3: Vec3, t: f32) -
     (term1 + term2 + term3 + term4) * 0.5
 }
 
-struct SSConverter(Vec<mogura_io::prelude::Atom>);
-impl From<SSConverter> for Vec<mogura_ss::Residue> {
-    fn from(atoms: SSConverter) -> Self {
-        atoms
-            .0
-            .into_iter()
-            .chunk_by(|atom| atom.residue_name().to_string())
-            .into_iter()
-            .map(|(res_name, group)| {
-                mogura_ss::Residue::new(
-                    res_name.to_string(),
-                    group
-                        .map(|atom| {
-                            mogura_ss::Atom::new(
-                                atom.atom_name().to_string(),
-                                atom.x(),
-                                atom.y(),
-                                atom.z(),
-                            )
-                        })
-                        .collect(),
-                )
-            })
-            .collect()
-    }
-}
-
 trait AtomColor {
     fn color(&self) -> Color;
 }
@@ -11,9 +11,10 @@ impl Plugin for MoguraTrajectoryPlugins {
 }
 
 #[allow(clippy::type_complexity)]
+#[allow(clippy::too_many_arguments)]
 fn update_trajectory(
     mut mogura_state: ResMut<MoguraState>,
-    mogura_selections: Res<MoguraSelections>,
+    mut mogura_selections: ResMut<MoguraSelections>,
     mut current_visualized_atoms: Query<
         (&mut Transform, &AtomID),
         (Without<InterpolationID>, Without<BondID>),
@@ -26,6 +27,8 @@ fn update_trajectory(
         (&mut Transform, &InterpolationID),
         (Without<AtomID>, Without<BondID>),
     >,
+    current_visualized_cartoons: Query<(&Mesh3d, &cartoon::Cartoon)>,
+    mut meshes: ResMut<Assets<Mesh>>,
     parent_query: Query<(&StructureParams, &Children)>,
 ) {
     if (mogura_state.update_trajectory
@@ -44,6 +47,25 @@ fn update_trajectory(
             .unwrap()
             .frame(current_frame_id);
 
+        // selections depending on the distance follow the frame
+        let structure_data = mogura_state.structure_data.as_deref().unwrap();
+        let macros = mogura_selections.macros(&mogura_state.macros);
+        let mut bonds = None;
+        for selection in mogura_selections.0.iter_mut() {
+            if selection.redraw || !selection.is_dynamic(&macros) {
+                continue;
+            }
+            let bonds = bonds.get_or_insert_with(|| structure_data.bonds_indirected());
+            let selected_atoms = selection.selected_atoms.clone();
+            if selection
+                .apply_selection(structure_data, Some(frame.positions()), bonds, &macros)
+                .is_ok()
+                && selection.selected_atoms != selected_atoms
+            {
+                selection.redraw = true;
+            }
+        }
+
         for (structure_params, childlen) in parent_query.iter() {
             let drawing_method = mogura_selections.0[structure_params.id].drawing_method;
 
@@ -133,6 +155,21 @@ fn update_trajectory(
                         }
                     }
                 }
+                DrawingMethod::Cartoon => {
+                    // SS of the frame when the timeline is computed
+                    let ss = mogura_state
+                        .ss_timeline
+                        .as_ref()
+                        .filter(|timeline| current_frame_id < timeline.n_frame())
+                        .map(|timeline| timeline.frame(current_frame_id));
+                    for child in childlen.iter() {
+                        if let Ok((mesh, cartoon)) = current_visualized_cartoons.get(*child) {
+                            if let Some(mesh) = meshes.get_mut(&mesh.0) {
+                                *mesh = cartoon.mesh(frame.positions(), ss);
+                            }
+                        }
+                    }
+                }
             }
         }
 
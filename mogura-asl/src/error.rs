@@ -0,0 +1,305 @@
+use nom::Parser;
+
+// candidates of "did you mean"
+const KEYWORDS: [&str; 52] = [
+    "all",
+    "protein",
+    "water",
+    "ion",
+    "backbone",
+    "sidechain",
+    "hetero",
+    "nucleic",
+    "lipid",
+    "sugar",
+    "cofactor",
+    "ligand",
+    "hydrophobic",
+    "acidic",
+    "basic",
+    "polar",
+    "aromatic",
+    "resname",
+    "resid",
+    "name",
+    "index",
+    "chain",
+    "segname",
+    "model",
+    "element",
+    "altloc",
+    "insertion",
+    "within",
+    "exwithin",
+    "around",
+    "of",
+    "same",
+    "residue",
+    "molecule",
+    "as",
+    "byres",
+    "bymolecule",
+    "and",
+    "or",
+    "not",
+    "to",
+    "by",
+    "define",
+    "x",
+    "y",
+    "z",
+    "bfactor",
+    "beta",
+    "occupancy",
+    "occ",
+    "charge",
+    "mass",
+];
+
+/// Error of parse_selection and parse_statement.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub input: String,
+    /// character offset in `input`
+    pub offset: usize,
+    /// what was found at `offset`, empty at the end of `input`
+    pub unexpected: String,
+    /// e.g. "`and`" or "a selection"
+    pub expected: Vec<String>,
+    /// a keyword close to `unexpected`, e.g. `resname` for `resnmae`
+    pub suggestion: Option<String>,
+}
+
+impl ParseError {
+    // `rest` is the remaining input where parsing failed
+    fn new(input: &str, rest: &str, expected: Vec<String>) -> Self {
+        let rest = rest.trim_start();
+        let offset = input[..input.len() - rest.len()].chars().count();
+        let word: String = rest.chars().take_while(|c| is_ident_char(*c)).collect();
+        let unexpected = if word.is_empty() {
+            rest.chars().next().map(String::from).unwrap_or_default()
+        } else {
+            word
+        };
+        let suggestion = suggest(&unexpected).map(String::from);
+        Self {
+            input: input.to_string(),
+            offset,
+            unexpected,
+            expected,
+            suggestion,
+        }
+    }
+
+    /// one line, e.g. "unexpected `resnmae` at offset 0, expected a selection"
+    pub fn message(&self) -> String {
+        let unexpected = if self.unexpected.is_empty() {
+            "end of selection".to_string()
+        } else {
+            format!("`{}`", self.unexpected)
+        };
+        let mut message = format!("unexpected {} at offset {}", unexpected, self.offset);
+        if let Some((last, rest)) = self.expected.split_last() {
+            message.push_str(", expected ");
+            if !rest.is_empty() {
+                message.push_str(&rest.join(", "));
+                message.push_str(" or ");
+            }
+            message.push_str(last);
+        }
+        message
+    }
+
+    /// the input with carets under the unexpected token
+    pub fn snippet(&self) -> String {
+        let width = self.unexpected.chars().count().max(1);
+        format!(
+            "{}\n{}{}",
+            self.input,
+            " ".repeat(self.offset),
+            "^".repeat(width)
+        )
+    }
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}\n{}", self.message(), self.snippet())?;
+        if let Some(suggestion) = &self.suggestion {
+            write!(f, "\ndid you mean `{}`?", suggestion)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+// the furthest failure over the alternatives, and what was expected there
+#[derive(Debug)]
+pub(crate) struct Error<'a> {
+    input: &'a str,
+    expected: Vec<String>,
+}
+
+impl<'a> nom::error::ParseError<&'a str> for Error<'a> {
+    fn from_error_kind(input: &'a str, _kind: nom::error::ErrorKind) -> Self {
+        Self {
+            input,
+            expected: Vec::new(),
+        }
+    }
+
+    fn append(_input: &'a str, _kind: nom::error::ErrorKind, other: Self) -> Self {
+        other
+    }
+
+    fn or(mut self, other: Self) -> Self {
+        // shorter rest is further
+        match self.input.len().cmp(&other.input.len()) {
+            std::cmp::Ordering::Less => self,
+            std::cmp::Ordering::Greater => other,
+            std::cmp::Ordering::Equal => {
+                for expected in other.expected {
+                    if !self.expected.contains(&expected) {
+                        self.expected.push(expected);
+                    }
+                }
+                self
+            }
+        }
+    }
+}
+
+impl<'a, E> nom::error::FromExternalError<&'a str, E> for Error<'a> {
+    fn from_external_error(input: &'a str, kind: nom::error::ErrorKind, _e: E) -> Self {
+        nom::error::ParseError::from_error_kind(input, kind)
+    }
+}
+
+pub(crate) type IResult<'a, O> = nom::IResult<&'a str, O, Error<'a>>;
+
+pub(crate) fn is_ident_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// tag, expecting `word` on failure. Alphabetic words must end there, `waters` is not `water`.
+pub(crate) fn keyword<'a>(
+    word: &'static str,
+) -> impl Parser<&'a str, Output = &'a str, Error = Error<'a>> {
+    let boundary = word.starts_with(|c: char| c.is_alphabetic());
+    move |input: &'a str| {
+        let result = if boundary {
+            nom::sequence::terminated(
+                nom::bytes::complete::tag(word),
+                nom::combinator::not(nom::combinator::peek(nom::character::complete::satisfy(
+                    is_ident_char,
+                ))),
+            )
+            .parse(input)
+        } else {
+            nom::bytes::complete::tag(word).parse(input)
+        };
+        result.map_err(|e: nom::Err<Error<'a>>| {
+            e.map(|_| Error {
+                input,
+                expected: vec![format!("`{}`", word)],
+            })
+        })
+    }
+}
+
+/// `parser`, expecting `label` when it fails right at the start, e.g. "a selection"
+pub(crate) fn expect<'a, O>(
+    label: &'static str,
+    mut parser: impl Parser<&'a str, Output = O, Error = Error<'a>>,
+) -> impl Parser<&'a str, Output = O, Error = Error<'a>> {
+    move |input: &'a str| {
+        parser.parse(input).map_err(|e| {
+            e.map(|mut error| {
+                if error.input.len() >= input.trim_start().len() {
+                    error.expected = vec![label.to_string()];
+                }
+                error
+            })
+        })
+    }
+}
+
+/// ParseError of the result of parsing the whole `input`
+pub(crate) fn finish<O>(input: &str, result: IResult<'_, O>) -> Result<O, ParseError> {
+    match result {
+        Ok(("", output)) => Ok(output),
+        Ok((rest, _)) => Err(ParseError::new(
+            input,
+            rest,
+            vec!["`and`".to_string(), "`or`".to_string()],
+        )),
+        Err(nom::Err::Error(error) | nom::Err::Failure(error)) => {
+            Err(ParseError::new(input, error.input, error.expected))
+        }
+        Err(nom::Err::Incomplete(_)) => Err(ParseError::new(input, "", Vec::new())),
+    }
+}
+
+// closest keyword to a misspelled word
+fn suggest(word: &str) -> Option<&'static str> {
+    if word.len() < 3 || !word.chars().all(|c| c.is_ascii_alphabetic()) || KEYWORDS.contains(&word)
+    {
+        return None;
+    }
+    let word = word.to_ascii_lowercase();
+    let max_distance = (word.len() / 3).max(1);
+    KEYWORDS
+        .iter()
+        .map(|keyword| (edit_distance(&word, keyword), *keyword))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, keyword)| keyword)
+}
+
+// Levenshtein distance where swapping two adjacent characters counts as one edit
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut d = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in d.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    d[0] = (0..=b.len()).collect();
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            d[i][j] = (d[i - 1][j] + 1)
+                .min(d[i][j - 1] + 1)
+                .min(d[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                d[i][j] = d[i][j].min(d[i - 2][j - 2] + 1);
+            }
+        }
+    }
+    d[a.len()][b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::*;
+
+    #[test]
+    fn distance() {
+        assert_eq!(edit_distance("resnmae", "resname"), 1);
+        assert_eq!(edit_distance("protien", "protein"), 1);
+        assert_eq!(edit_distance("wter", "water"), 1);
+        assert_eq!(edit_distance("chain", "chain"), 0);
+        assert_eq!(edit_distance("", "all"), 3);
+    }
+
+    #[test]
+    fn suggestion() {
+        assert_eq!(suggest("resnmae"), Some("resname"));
+        assert_eq!(suggest("Protein"), Some("protein"));
+        assert_eq!(suggest("backbon"), Some("backbone"));
+        assert_eq!(suggest("resname"), None);
+        assert_eq!(suggest("LIG"), None);
+        assert_eq!(suggest("10"), None);
+    }
+}
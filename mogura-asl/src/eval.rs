@@ -0,0 +1,558 @@
+use crate::grid::Grid;
+use crate::{Property, Selection};
+
+/// Atoms seen by the evaluator, indexed by 0..n_atoms().
+pub trait AtomContext {
+    fn n_atoms(&self) -> usize;
+    fn atom_name(&self, i: usize) -> &str;
+    fn residue_name(&self, i: usize) -> &str;
+    fn residue_id(&self, i: usize) -> isize;
+    /// atoms of the same residue share it
+    fn residue_index(&self, i: usize) -> usize;
+    fn chain_name(&self, i: usize) -> &str;
+    fn model_id(&self, _i: usize) -> usize {
+        0
+    }
+    /// `None` when the format has none
+    fn segment_name(&self, _i: usize) -> Option<&str> {
+        None
+    }
+    fn insertion_code(&self, _i: usize) -> Option<&str> {
+        None
+    }
+    fn alternative_location(&self, _i: usize) -> Option<&str> {
+        None
+    }
+    /// element symbol, matched ignoring case
+    fn element(&self, _i: usize) -> &str {
+        ""
+    }
+    fn is_hetero(&self, _i: usize) -> bool {
+        false
+    }
+    fn b_factor(&self, _i: usize) -> f32 {
+        0.
+    }
+    fn occupancy(&self, _i: usize) -> f32 {
+        1.
+    }
+    fn charge(&self, _i: usize) -> f32 {
+        0.
+    }
+    /// dalton
+    fn mass(&self, _i: usize) -> f32 {
+        0.
+    }
+    /// serial number in the file, matched by `index`
+    fn atom_id(&self, i: usize) -> usize;
+    /// angstrom
+    fn position(&self, i: usize) -> [f32; 3];
+    /// atoms bonded to `i`
+    fn neighbors(&self, i: usize) -> Vec<usize>;
+    fn is_protein(&self, i: usize) -> bool;
+    fn is_water(&self, i: usize) -> bool;
+    fn is_ion(&self, i: usize) -> bool;
+    fn is_backbone(&self, i: usize) -> bool;
+    fn is_sidechain(&self, i: usize) -> bool;
+    fn is_nucleic(&self, _i: usize) -> bool {
+        false
+    }
+    fn is_lipid(&self, _i: usize) -> bool {
+        false
+    }
+    fn is_sugar(&self, _i: usize) -> bool {
+        false
+    }
+    fn is_cofactor(&self, _i: usize) -> bool {
+        false
+    }
+    /// hetero atoms other than water
+    fn is_ligand(&self, _i: usize) -> bool {
+        false
+    }
+    // amino acids by the side chain
+    fn is_hydrophobic(&self, _i: usize) -> bool {
+        false
+    }
+    fn is_acidic(&self, _i: usize) -> bool {
+        false
+    }
+    fn is_basic(&self, _i: usize) -> bool {
+        false
+    }
+    fn is_polar(&self, _i: usize) -> bool {
+        false
+    }
+    fn is_aromatic(&self, _i: usize) -> bool {
+        false
+    }
+}
+
+impl Selection {
+    /// `true` for the selected atoms, one for each atom of `context`
+    pub fn mask<C: AtomContext + ?Sized>(&self, context: &C) -> Vec<bool> {
+        let n = context.n_atoms();
+        let each = |f: &dyn Fn(usize) -> bool| (0..n).map(f).collect();
+        match self {
+            Selection::All => vec![true; n],
+            Selection::ResName(names) => each(&|i| {
+                names
+                    .iter()
+                    .any(|name| glob_match(name, context.residue_name(i)))
+            }),
+            Selection::ResNameRegex(pattern) => match full_match_regex(pattern) {
+                Some(regex) => each(&|i| regex.is_match(context.residue_name(i))),
+                None => vec![false; n],
+            },
+            Selection::ResId(ids) => each(&|i| ids.contains(&context.residue_id(i))),
+            Selection::ResIdRange { first, last, step } => each(&|i| {
+                let id = context.residue_id(i);
+                (*first..=*last).contains(&id) && id.abs_diff(*first) % step == 0
+            }),
+            Selection::ResIdInsertion(ids) => each(&|i| {
+                ids.iter().any(|(id, code)| {
+                    *id == context.residue_id(i) && context.insertion_code(i) == Some(code.as_str())
+                })
+            }),
+            Selection::Name(names) => each(&|i| {
+                names
+                    .iter()
+                    .any(|name| glob_match(name, context.atom_name(i)))
+            }),
+            Selection::NameRegex(pattern) => match full_match_regex(pattern) {
+                Some(regex) => each(&|i| regex.is_match(context.atom_name(i))),
+                None => vec![false; n],
+            },
+            Selection::Index(indices) => {
+                each(&|i| indices.iter().any(|index| *index == context.atom_id(i)))
+            }
+            Selection::Protein => each(&|i| context.is_protein(i)),
+            Selection::Water => each(&|i| context.is_water(i)),
+            Selection::Ion => each(&|i| context.is_ion(i)),
+            Selection::Backbone => each(&|i| context.is_backbone(i)),
+            Selection::Sidechain => each(&|i| context.is_sidechain(i)),
+            Selection::Chain(names) => {
+                each(&|i| names.iter().any(|name| name == context.chain_name(i)))
+            }
+            Selection::SegName(names) => each(&|i| {
+                context
+                    .segment_name(i)
+                    .is_some_and(|segment_name| names.iter().any(|name| name == segment_name))
+            }),
+            Selection::Model(ids) => each(&|i| ids.contains(&context.model_id(i))),
+            Selection::Element(symbols) => each(&|i| {
+                symbols
+                    .iter()
+                    .any(|symbol| symbol.eq_ignore_ascii_case(context.element(i)))
+            }),
+            Selection::AltLoc(names) => each(&|i| {
+                context
+                    .alternative_location(i)
+                    .is_some_and(|altloc| names.iter().any(|name| name == altloc))
+            }),
+            Selection::Insertion(codes) => each(&|i| {
+                context
+                    .insertion_code(i)
+                    .is_some_and(|insertion_code| codes.iter().any(|code| code == insertion_code))
+            }),
+            Selection::Hetero => each(&|i| context.is_hetero(i)),
+            Selection::Nucleic => each(&|i| context.is_nucleic(i)),
+            Selection::Lipid => each(&|i| context.is_lipid(i)),
+            Selection::Sugar => each(&|i| context.is_sugar(i)),
+            Selection::Cofactor => each(&|i| context.is_cofactor(i)),
+            Selection::Ligand => each(&|i| context.is_ligand(i)),
+            Selection::Hydrophobic => each(&|i| context.is_hydrophobic(i)),
+            Selection::Acidic => each(&|i| context.is_acidic(i)),
+            Selection::Basic => each(&|i| context.is_basic(i)),
+            Selection::Polar => each(&|i| context.is_polar(i)),
+            Selection::Aromatic => each(&|i| context.is_aromatic(i)),
+            Selection::Compare(property, comparison, value) => {
+                each(&|i| comparison.compare(property_value(context, *property, i), *value))
+            }
+            Selection::Not(selection) => selection.mask(context).into_iter().map(|b| !b).collect(),
+            Selection::And(selections) => selections.iter().fold(vec![true; n], |acc, s| {
+                acc.into_iter()
+                    .zip(s.mask(context))
+                    .map(|(a, b)| a && b)
+                    .collect()
+            }),
+            Selection::Or(selections) => selections.iter().fold(vec![false; n], |acc, s| {
+                acc.into_iter()
+                    .zip(s.mask(context))
+                    .map(|(a, b)| a || b)
+                    .collect()
+            }),
+            Selection::Bracket(selection) => selection.mask(context),
+            Selection::Within(distance, selection) => {
+                within(context, *distance, &selection.mask(context), false)
+            }
+            Selection::ExWithin(distance, selection) => {
+                within(context, *distance, &selection.mask(context), true)
+            }
+            Selection::SameResidue(selection) => {
+                let residues: std::collections::HashSet<usize> = selection
+                    .select(context)
+                    .into_iter()
+                    .map(|i| context.residue_index(i))
+                    .collect();
+                each(&|i| residues.contains(&context.residue_index(i)))
+            }
+            Selection::SameChain(selection) => {
+                let chains: std::collections::HashSet<&str> = selection
+                    .select(context)
+                    .into_iter()
+                    .map(|i| context.chain_name(i))
+                    .collect();
+                each(&|i| chains.contains(context.chain_name(i)))
+            }
+            Selection::SameMolecule(selection) => molecules(context, selection.mask(context)),
+            // to be replaced by Selection::expand
+            Selection::Macro(_) => vec![false; n],
+        }
+    }
+
+    /// `true` when the result depends on the positions, to be evaluated again for each frame
+    pub fn is_dynamic(&self) -> bool {
+        match self {
+            Selection::Within(..) | Selection::ExWithin(..) => true,
+            Selection::Compare(property, ..) => {
+                matches!(property, Property::X | Property::Y | Property::Z)
+            }
+            Selection::Not(selection)
+            | Selection::Bracket(selection)
+            | Selection::SameResidue(selection)
+            | Selection::SameChain(selection)
+            | Selection::SameMolecule(selection) => selection.is_dynamic(),
+            Selection::And(selections) | Selection::Or(selections) => {
+                selections.iter().any(|s| s.is_dynamic())
+            }
+            _ => false,
+        }
+    }
+
+    /// indices of the selected atoms in `context`, sorted
+    pub fn select<C: AtomContext + ?Sized>(&self, context: &C) -> Vec<usize> {
+        self.mask(context)
+            .into_iter()
+            .enumerate()
+            .filter(|(_, selected)| *selected)
+            .map(|(i, _)| i)
+            .collect()
+    }
+}
+
+// `*` for any characters and `?` for one
+fn glob_match(pattern: &str, text: &str) -> bool {
+    if !pattern.contains(['*', '?']) {
+        return pattern == text;
+    }
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // last `*` and the position in text it matched up to
+    let mut star: Option<(usize, usize)> = None;
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, t));
+            p += 1;
+        } else if let Some((star_p, star_t)) = star {
+            // let `*` take one more character
+            p = star_p + 1;
+            t = star_t + 1;
+            star = Some((star_p, star_t + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+fn full_match_regex(pattern: &str) -> Option<regex::Regex> {
+    regex::Regex::new(&format!("^(?:{})$", pattern)).ok()
+}
+
+fn property_value<C: AtomContext + ?Sized>(context: &C, property: Property, i: usize) -> f32 {
+    match property {
+        Property::X => context.position(i)[0],
+        Property::Y => context.position(i)[1],
+        Property::Z => context.position(i)[2],
+        Property::BFactor => context.b_factor(i),
+        Property::Occupancy => context.occupancy(i),
+        Property::Charge => context.charge(i),
+        Property::Mass => context.mass(i),
+        Property::ResId => context.residue_id(i) as f32,
+        Property::Index => context.atom_id(i) as f32,
+    }
+}
+
+fn within<C: AtomContext + ?Sized>(
+    context: &C,
+    distance: f32,
+    reference: &[bool],
+    exclusive: bool,
+) -> Vec<bool> {
+    let grid = Grid::new(
+        (0..context.n_atoms())
+            .filter(|&i| reference[i])
+            .map(|i| context.position(i)),
+        distance,
+    );
+    (0..context.n_atoms())
+        .map(|i| {
+            if reference[i] {
+                !exclusive
+            } else {
+                grid.any_within(&context.position(i), distance)
+            }
+        })
+        .collect()
+}
+
+// connected components of the bond graph containing the selected atoms
+fn molecules<C: AtomContext + ?Sized>(context: &C, mut mask: Vec<bool>) -> Vec<bool> {
+    let mut stack: Vec<usize> = (0..context.n_atoms()).filter(|&i| mask[i]).collect();
+    while let Some(i) = stack.pop() {
+        for j in context.neighbors(i) {
+            if !mask[j] {
+                mask[j] = true;
+                stack.push(j);
+            }
+        }
+    }
+    mask
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::eval::*;
+    use crate::parse_selection;
+
+    struct TestAtom {
+        name: &'static str,
+        resname: &'static str,
+        resid: isize,
+        position: [f32; 3],
+    }
+
+    impl AtomContext for [TestAtom] {
+        fn n_atoms(&self) -> usize {
+            self.len()
+        }
+        fn atom_name(&self, i: usize) -> &str {
+            self[i].name
+        }
+        fn residue_name(&self, i: usize) -> &str {
+            self[i].resname
+        }
+        fn residue_id(&self, i: usize) -> isize {
+            self[i].resid
+        }
+        fn residue_index(&self, i: usize) -> usize {
+            self[i].resid as usize
+        }
+        fn chain_name(&self, i: usize) -> &str {
+            if self.is_water(i) { "W" } else { "A" }
+        }
+        fn element(&self, i: usize) -> &str {
+            &self[i].name[..1]
+        }
+        fn is_hetero(&self, i: usize) -> bool {
+            self[i].resname != "ALA"
+        }
+        fn atom_id(&self, i: usize) -> usize {
+            i + 1
+        }
+        fn position(&self, i: usize) -> [f32; 3] {
+            self[i].position
+        }
+        fn neighbors(&self, i: usize) -> Vec<usize> {
+            (0..self.len())
+                .filter(|&j| {
+                    let d2: f32 = (0..3)
+                        .map(|k| (self[i].position[k] - self[j].position[k]).powi(2))
+                        .sum();
+                    j != i && d2 <= 1.6 * 1.6
+                })
+                .collect()
+        }
+        fn is_protein(&self, i: usize) -> bool {
+            self[i].resname == "ALA"
+        }
+        fn is_water(&self, i: usize) -> bool {
+            self[i].resname == "HOH"
+        }
+        fn is_ion(&self, _i: usize) -> bool {
+            false
+        }
+        fn is_backbone(&self, i: usize) -> bool {
+            self.is_protein(i) && self[i].name == "CA"
+        }
+        fn is_sidechain(&self, i: usize) -> bool {
+            self.is_protein(i) && !self.is_backbone(i)
+        }
+    }
+
+    // a ligand at the origin and waters along the x axis
+    fn atoms() -> Vec<TestAtom> {
+        let mut atoms = vec![
+            TestAtom {
+                name: "C1",
+                resname: "LIG",
+                resid: 1,
+                position: [0., 0., 0.],
+            },
+            TestAtom {
+                name: "C2",
+                resname: "LIG",
+                resid: 1,
+                position: [1.5, 0., 0.],
+            },
+        ];
+        for (k, x) in [3., 6., 6.6, 20., -4.].into_iter().enumerate() {
+            atoms.push(TestAtom {
+                name: "O",
+                resname: "HOH",
+                resid: k as isize + 2,
+                position: [x, 0., 0.],
+            });
+        }
+        atoms
+    }
+
+    #[test]
+    fn within() {
+        let atoms = atoms();
+        let select = |expr: &str| parse_selection(expr).unwrap().select(&atoms[..]);
+        assert_eq!(select("within 5 of resname LIG"), vec![0, 1, 2, 3, 6]);
+        assert_eq!(select("water within 5 of resname LIG"), vec![2, 3, 6]);
+        assert_eq!(select("exwithin 5 of resname LIG"), vec![2, 3, 6]);
+        assert_eq!(select("around 5.2 resname LIG"), vec![2, 3, 4, 6]);
+        assert_eq!(select("within 0 of resid 5"), vec![5]);
+        assert_eq!(select("not within 10 of water"), Vec::<usize>::new());
+    }
+
+    #[test]
+    fn dynamic() {
+        let atoms = atoms();
+        let selection = parse_selection("water within 5 of resname LIG").unwrap();
+        assert!(selection.is_dynamic());
+        assert!(!parse_selection("water").unwrap().is_dynamic());
+
+        // a frame where the ligand moved
+        let mut moved = atoms;
+        for atom in moved.iter_mut().take(2) {
+            atom.position[0] += 18.;
+        }
+        assert_eq!(selection.select(&moved[..]), vec![5]);
+    }
+
+    #[test]
+    fn fields() {
+        let atoms = atoms();
+        let select = |expr: &str| parse_selection(expr).unwrap().select(&atoms[..]);
+        assert_eq!(select("chain A"), vec![0, 1]);
+        assert_eq!(select("element o"), vec![2, 3, 4, 5, 6]);
+        assert_eq!(select("hetero and not water"), vec![0, 1]);
+        assert_eq!(select("model 0 and resid 2 3"), vec![2, 3]);
+        // not in the context
+        assert_eq!(
+            select("segname PROA or altloc A or insertion A"),
+            Vec::<usize>::new()
+        );
+    }
+
+    #[test]
+    fn compare() {
+        let atoms = atoms();
+        let select = |expr: &str| parse_selection(expr).unwrap().select(&atoms[..]);
+        assert_eq!(select("x > 5"), vec![3, 4, 5]);
+        assert_eq!(select("x >= 0 and x <= 3"), vec![0, 1, 2]);
+        assert_eq!(select("resid >= 5"), vec![5, 6]);
+        assert_eq!(select("index < 3 and occupancy == 1"), vec![0, 1]);
+        assert_eq!(select("mass != 0 or y > 0 or z < 0"), Vec::<usize>::new());
+        assert!(parse_selection("z < 0").unwrap().is_dynamic());
+        assert!(!parse_selection("bfactor < 0").unwrap().is_dynamic());
+    }
+
+    #[test]
+    fn glob() {
+        assert!(glob_match("CA", "CA"));
+        assert!(!glob_match("CA", "CB"));
+        assert!(glob_match("C*", "C"));
+        assert!(glob_match("C*", "CA"));
+        assert!(glob_match("H*1", "HB21"));
+        assert!(!glob_match("H*1", "HB2"));
+        assert!(glob_match("?G*", "OG1"));
+        assert!(!glob_match("?G", "G"));
+        assert!(glob_match("*", ""));
+        assert!(glob_match("**A", "BBA"));
+    }
+
+    #[test]
+    fn pattern() {
+        let atoms = molecules();
+        let select = |expr: &str| parse_selection(expr).unwrap().select(&atoms[..]);
+        assert_eq!(select("name C*"), vec![1, 2, 4]);
+        assert_eq!(select("name H?"), vec![6, 7]);
+        assert_eq!(select("name \"C[AB]\""), vec![1, 4]);
+        // the whole name
+        assert_eq!(select("name \"C\""), vec![2]);
+        assert_eq!(select("resname \"[A-Z]O.\" or name N"), vec![0, 3, 5, 6, 7]);
+    }
+
+    #[test]
+    fn resid() {
+        let atoms = atoms();
+        let select = |expr: &str| parse_selection(expr).unwrap().select(&atoms[..]);
+        assert_eq!(select("resid 2:6:2"), vec![2, 4, 6]);
+        assert_eq!(select("resid -5 to 1"), vec![0, 1]);
+        assert_eq!(select("resid 3 to 1"), Vec::<usize>::new());
+        assert_eq!(select("resid -1000000000 to 1000000000 by 3"), vec![2, 5]);
+        // no insertion codes in the context
+        assert_eq!(select("resid 1A"), Vec::<usize>::new());
+    }
+
+    // ALA-ALA and a water, each atom bonded to the next one in the chain
+    fn molecules() -> Vec<TestAtom> {
+        let atom = |name, resname, resid, x| TestAtom {
+            name,
+            resname,
+            resid,
+            position: [x, 0., 0.],
+        };
+        vec![
+            atom("N", "ALA", 1, 0.),
+            atom("CA", "ALA", 1, 1.5),
+            atom("C", "ALA", 1, 3.),
+            atom("N", "ALA", 2, 4.5),
+            atom("CA", "ALA", 2, 6.),
+            atom("O", "HOH", 3, 10.),
+            atom("H1", "HOH", 3, 11.),
+            atom("H2", "HOH", 3, 9.),
+        ]
+    }
+
+    #[test]
+    fn same() {
+        let atoms = molecules();
+        let select = |expr: &str| parse_selection(expr).unwrap().select(&atoms[..]);
+        assert_eq!(select("same residue as index 5"), vec![3, 4]);
+        assert_eq!(select("byres name O"), vec![5, 6, 7]);
+        assert_eq!(select("byres name CA and resid 1"), vec![0, 1, 2]);
+        assert_eq!(select("same chain as name CA"), vec![0, 1, 2, 3, 4]);
+        assert_eq!(select("bymolecule name H1"), vec![5, 6, 7]);
+        assert_eq!(select("bymolecule index 3"), vec![0, 1, 2, 3, 4]);
+        assert_eq!(
+            select("bymolecule within 5.5 of name H1"),
+            vec![0, 1, 2, 3, 4, 5, 6, 7]
+        );
+        assert!(
+            parse_selection("byres within 3 of water")
+                .unwrap()
+                .is_dynamic()
+        );
+    }
+}
@@ -0,0 +1,51 @@
+// uniform grid over the positions for distance queries, cells are not smaller than the distance
+// so that only the 27 cells around a point have to be searched
+const MIN_CELL_SIZE: f32 = 1.0;
+
+pub(crate) struct Grid {
+    cell_size: f32,
+    cells: std::collections::HashMap<[i32; 3], Vec<[f32; 3]>>,
+}
+
+impl Grid {
+    pub(crate) fn new(positions: impl Iterator<Item = [f32; 3]>, distance: f32) -> Self {
+        let mut grid = Self {
+            cell_size: distance.max(MIN_CELL_SIZE),
+            cells: std::collections::HashMap::new(),
+        };
+        for position in positions {
+            grid.cells
+                .entry(grid.cell(&position))
+                .or_default()
+                .push(position);
+        }
+        grid
+    }
+
+    fn cell(&self, position: &[f32; 3]) -> [i32; 3] {
+        position.map(|x| (x / self.cell_size).floor() as i32)
+    }
+
+    pub(crate) fn any_within(&self, position: &[f32; 3], distance: f32) -> bool {
+        let [cx, cy, cz] = self.cell(position);
+        let distance2 = distance * distance;
+        for dx in -1..=1 {
+            for dy in -1..=1 {
+                for dz in -1..=1 {
+                    let Some(points) = self.cells.get(&[cx + dx, cy + dy, cz + dz]) else {
+                        continue;
+                    };
+                    if points.iter().any(|point| {
+                        (0..3)
+                            .map(|k| (point[k] - position[k]).powi(2))
+                            .sum::<f32>()
+                            <= distance2
+                    }) {
+                        return true;
+                    }
+                }
+            }
+        }
+        false
+    }
+}
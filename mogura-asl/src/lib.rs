@@ -1,44 +1,191 @@
 use nom::Parser;
 
+mod error;
+mod eval;
+mod grid;
+mod macros;
+
+pub use error::ParseError;
+use error::{Error, IResult, expect, keyword};
+pub use eval::AtomContext;
+pub use macros::{Macros, default_macro_file};
+
 #[derive(Clone, PartialEq, Debug)]
 pub enum Selection {
     All,
+    /// glob patterns, `*` and `?`
     ResName(Vec<String>),
-    ResId(Vec<usize>),
+    /// regular expression matching the whole residue name, `resname "AL."`
+    ResNameRegex(String),
+    ResId(Vec<isize>),
+    /// `resid 10 to 20 by 2`, `10:20:2` and `10-20`, including `last`
+    ResIdRange {
+        first: isize,
+        last: isize,
+        step: usize,
+    },
+    /// residue id and insertion code, `resid 52A`
+    ResIdInsertion(Vec<(isize, String)>),
+    /// glob patterns, `*` and `?`
     Name(Vec<String>),
+    /// regular expression matching the whole atom name, `name "C[AB]"`
+    NameRegex(String),
     Index(Vec<usize>),
     Protein,
     Water,
     Ion,
     Backbone,
     Sidechain,
+    Chain(Vec<String>),
+    SegName(Vec<String>),
+    Model(Vec<usize>),
+    Element(Vec<String>),
+    AltLoc(Vec<String>),
+    Insertion(Vec<String>),
+    Hetero,
+    Nucleic,
+    Lipid,
+    Sugar,
+    Cofactor,
+    /// hetero atoms other than water
+    Ligand,
+    Hydrophobic,
+    Acidic,
+    Basic,
+    Polar,
+    Aromatic,
+    /// e.g. `x > 10`, `bfactor <= 30.5`
+    Compare(Property, Comparison, f32),
     Not(Box<Selection>),
     And(Vec<Box<Selection>>),
     Or(Vec<Box<Selection>>),
     Bracket(Box<Selection>),
+    /// atoms within the distance (angstrom) of the selection, including the selection
+    Within(f32, Box<Selection>),
+    /// as Within but excluding the selection, `exwithin` and `around`
+    ExWithin(f32, Box<Selection>),
+    /// whole residues of the selection, `same residue as` and `byres`
+    SameResidue(Box<Selection>),
+    /// `same chain as`
+    SameChain(Box<Selection>),
+    /// atoms connected by bonds to the selection, `same molecule as` and `bymolecule`
+    SameMolecule(Box<Selection>),
+    /// reference to a named selection, `$pocket` or `@pocket`
+    Macro(String),
+}
+
+/// A line of the selection language, either a selection or `define <name> as <selection>`.
+#[derive(Clone, PartialEq, Debug)]
+pub enum Statement {
+    Define(String, Selection),
+    Select(Selection),
+}
+
+/// Numeric atom properties for comparisons.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Property {
+    X,
+    Y,
+    Z,
+    /// `bfactor` or `beta`
+    BFactor,
+    /// `occupancy` or `occ`
+    Occupancy,
+    Charge,
+    Mass,
+    ResId,
+    Index,
 }
 
-pub fn parse_selection(selection: &str) -> Result<Selection, String> {
-    match nom::combinator::all_consuming(parse_expr).parse(selection) {
-        Ok((_, selection)) => Ok(selection),
-        Err(e) => Err(e.to_string()),
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Comparison {
+    Lt,
+    Le,
+    Eq,
+    Ne,
+    Ge,
+    Gt,
+}
+
+impl Comparison {
+    pub fn compare(&self, lhs: f32, rhs: f32) -> bool {
+        match self {
+            Comparison::Lt => lhs < rhs,
+            Comparison::Le => lhs <= rhs,
+            Comparison::Eq => lhs == rhs,
+            Comparison::Ne => lhs != rhs,
+            Comparison::Ge => lhs >= rhs,
+            Comparison::Gt => lhs > rhs,
+        }
     }
 }
 
-fn parse_expr(input: &str) -> nom::IResult<&str, Selection> {
+pub fn parse_selection(selection: &str) -> Result<Selection, ParseError> {
+    error::finish(selection, parse_expr.parse(selection))
+}
+
+pub fn parse_statement(statement: &str) -> Result<Statement, ParseError> {
+    error::finish(
+        statement,
+        expect(
+            "a selection",
+            nom::branch::alt((
+                parse_define,
+                nom::combinator::map(parse_expr, Statement::Select),
+            )),
+        )
+        .parse(statement),
+    )
+}
+
+fn parse_define(input: &str) -> IResult<'_, Statement> {
+    let (input, _) = nom::character::complete::space0(input)?;
+    let (input, _) = keyword("define").parse(input)?;
+    nom::combinator::map(
+        (
+            argument("a macro name", parse_macro_name),
+            argument("`as`", keyword("as")),
+            argument("a selection", parse_expr),
+        ),
+        |(name, _, selection)| Statement::Define(name.to_string(), selection),
+    )
+    .parse(input)
+}
+
+// what follows a keyword, required once the keyword is matched
+fn argument<'a, O>(
+    label: &'static str,
+    parser: impl Parser<&'a str, Output = O, Error = Error<'a>>,
+) -> impl Parser<&'a str, Output = O, Error = Error<'a>> {
+    nom::combinator::cut(expect(
+        label,
+        nom::sequence::preceded(nom::character::complete::space1, parser),
+    ))
+}
+
+fn parse_macro_name(input: &str) -> IResult<'_, &str> {
+    nom::bytes::complete::take_while1(error::is_ident_char).parse(input)
+}
+
+fn parse_macro(input: &str) -> IResult<'_, Selection> {
+    nom::combinator::map(
+        nom::sequence::preceded(nom::character::complete::one_of("$@"), parse_macro_name),
+        |name: &str| Selection::Macro(name.to_string()),
+    )
+    .parse(input)
+}
+
+fn parse_expr(input: &str) -> IResult<'_, Selection> {
     let (input, expr) = parse_or.parse(input)?;
     let (input, _) = nom::character::complete::space0(input)?;
     Ok((input, expr))
 }
 
-fn parse_or(input: &str) -> nom::IResult<&str, Selection> {
+fn parse_or(input: &str) -> IResult<'_, Selection> {
     let (input, init) = parse_and.parse(input)?;
     let (input, rest) = nom::multi::many0(nom::sequence::preceded(
         nom::character::complete::space1,
-        nom::sequence::preceded(
-            nom::bytes::complete::tag("or"),
-            nom::sequence::preceded(nom::character::complete::space1, parse_and),
-        ),
+        nom::sequence::preceded(keyword("or"), argument("a selection", parse_and)),
     ))
     .parse(input)?;
     if rest.is_empty() {
@@ -52,14 +199,15 @@ fn parse_or(input: &str) -> nom::IResult<&str, Selection> {
     }
 }
 
-fn parse_and(input: &str) -> nom::IResult<&str, Selection> {
+fn parse_and(input: &str) -> IResult<'_, Selection> {
     let (input, init) = parse_not.parse(input)?;
     let (input, rest) = nom::multi::many0(nom::sequence::preceded(
         nom::character::complete::space1,
-        nom::sequence::preceded(
-            nom::bytes::complete::tag("and"),
-            nom::sequence::preceded(nom::character::complete::space1, parse_not),
-        ),
+        nom::branch::alt((
+            nom::sequence::preceded(keyword("and"), argument("a selection", parse_not)),
+            // "water within 5 of ..." reads as "water and within 5 of ..."
+            parse_distance,
+        )),
     ))
     .parse(input)?;
     if rest.is_empty() {
@@ -73,10 +221,10 @@ fn parse_and(input: &str) -> nom::IResult<&str, Selection> {
     }
 }
 
-fn parse_not(input: &str) -> nom::IResult<&str, Selection> {
+fn parse_not(input: &str) -> IResult<'_, Selection> {
     let (input, nots) = nom::multi::many0(nom::sequence::preceded(
         nom::character::complete::space0,
-        nom::bytes::complete::tag("not"),
+        keyword("not"),
     ))
     .parse(input)?;
     let (input, primary) = parse_primary.parse(input)?;
@@ -86,19 +234,41 @@ fn parse_not(input: &str) -> nom::IResult<&str, Selection> {
     Ok((input, selection))
 }
 
-fn parse_primary(input: &str) -> nom::IResult<&str, Selection> {
+fn parse_primary(input: &str) -> IResult<'_, Selection> {
     let (input, _) = nom::character::complete::space0.parse(input)?;
-    nom::branch::alt((parse_bracket, parse_atom)).parse(input)
+    expect("a selection", nom::branch::alt((parse_bracket, parse_atom))).parse(input)
 }
 
-fn parse_bracket(input: &str) -> nom::IResult<&str, Selection> {
-    let (input, _) = nom::character::complete::char('(').parse(input)?;
-    let (input, expr) = parse_expr.parse(input)?;
-    let (input, _) = nom::character::complete::char(')').parse(input)?;
+fn parse_bracket(input: &str) -> IResult<'_, Selection> {
+    let (input, _) = keyword("(").parse(input)?;
+    let (input, expr) =
+        nom::combinator::cut(nom::sequence::terminated(parse_expr, keyword(")"))).parse(input)?;
     Ok((input, Selection::Bracket(Box::new(expr))))
 }
 
-fn parse_atom(input: &str) -> nom::IResult<&str, Selection> {
+fn parse_atom(input: &str) -> IResult<'_, Selection> {
+    nom::branch::alt((
+        parse_macro,
+        parse_class,
+        parse_chain,
+        parse_segname,
+        parse_model,
+        parse_element,
+        parse_altloc,
+        parse_insertion,
+        parse_distance,
+        parse_same,
+        parse_compare,
+        parse_resname,
+        parse_resid,
+        parse_index,
+        parse_name,
+    ))
+    .parse(input)
+}
+
+// keywords without arguments
+fn parse_class(input: &str) -> IResult<'_, Selection> {
     nom::branch::alt((
         parse_all,
         parse_protein,
@@ -106,77 +276,448 @@ fn parse_atom(input: &str) -> nom::IResult<&str, Selection> {
         parse_backbone,
         parse_water,
         parse_ion,
-        parse_resname,
-        parse_resid,
-        parse_index,
-        parse_name,
+        parse_hetero,
+        parse_residue_class,
     ))
     .parse(input)
 }
 
-fn parse_identifier(input: &str) -> nom::IResult<&str, &str> {
-    nom::combinator::verify(nom::character::complete::alphanumeric1, |s: &str| {
-        s != "and" && s != "or" && s != "not" && s != "to"
-    })
+fn parse_residue_class(input: &str) -> IResult<'_, Selection> {
+    nom::branch::alt((
+        nom::combinator::value(Selection::Nucleic, keyword("nucleic")),
+        nom::combinator::value(Selection::Lipid, keyword("lipid")),
+        nom::combinator::value(Selection::Sugar, keyword("sugar")),
+        nom::combinator::value(Selection::Cofactor, keyword("cofactor")),
+        nom::combinator::value(Selection::Ligand, keyword("ligand")),
+        nom::combinator::value(Selection::Hydrophobic, keyword("hydrophobic")),
+        nom::combinator::value(Selection::Acidic, keyword("acidic")),
+        nom::combinator::value(Selection::Basic, keyword("basic")),
+        nom::combinator::value(Selection::Polar, keyword("polar")),
+        nom::combinator::value(Selection::Aromatic, keyword("aromatic")),
+    ))
+    .parse(input)
+}
+
+fn parse_identifier(input: &str) -> IResult<'_, &str> {
+    nom::combinator::verify(
+        // C1', H2'', NA+, CL-, H*
+        nom::bytes::complete::take_while1(|c: char| c.is_alphanumeric() || "'+-_*?".contains(c)),
+        |s: &str| {
+            // opening quote
+            !s.starts_with('\'')
+                && ![
+                    "and",
+                    "or",
+                    "not",
+                    "to",
+                    "within",
+                    "exwithin",
+                    "around",
+                    "of",
+                    "same",
+                    "as",
+                    "byres",
+                    "bymolecule",
+                ]
+                .contains(&s)
+        },
+    )
     .parse(input)
 }
 
-fn parse_name(input: &str) -> nom::IResult<&str, Selection> {
+// items of name and resname
+enum Pattern<'a> {
+    Glob(&'a str),
+    Regex(&'a str),
+}
+
+fn parse_pattern(input: &str) -> IResult<'_, Pattern<'_>> {
+    nom::branch::alt((
+        // "regex"
+        nom::combinator::map(
+            nom::combinator::verify(
+                nom::sequence::delimited(
+                    keyword("\""),
+                    nom::bytes::complete::take_while1(|c| c != '"'),
+                    keyword("\""),
+                ),
+                |s: &str| regex::Regex::new(s).is_ok(),
+            ),
+            Pattern::Regex,
+        ),
+        // 'quoted', e.g. keywords or spaces
+        nom::combinator::map(
+            nom::sequence::delimited(
+                keyword("'"),
+                nom::bytes::complete::take_while1(|c| c != '\''),
+                keyword("'"),
+            ),
+            Pattern::Glob,
+        ),
+        nom::combinator::map(parse_identifier, Pattern::Glob),
+    ))
+    .parse(input)
+}
+
+// globs in one selection, or of regexes
+fn pattern_selection(
+    patterns: Vec<Pattern>,
+    glob: fn(Vec<String>) -> Selection,
+    regex: fn(String) -> Selection,
+) -> Selection {
+    let mut globs = Vec::new();
+    let mut selections = Vec::new();
+    for pattern in patterns {
+        match pattern {
+            Pattern::Glob(s) => globs.push(s.to_string()),
+            Pattern::Regex(s) => selections.push(Box::new(regex(s.to_string()))),
+        }
+    }
+    if !globs.is_empty() {
+        selections.insert(0, Box::new(glob(globs)));
+    }
+    if selections.len() == 1 {
+        *selections.pop().unwrap()
+    } else {
+        Selection::Or(selections)
+    }
+}
+
+fn parse_name(input: &str) -> IResult<'_, Selection> {
     nom::combinator::map(
         nom::sequence::preceded(
-            nom::bytes::complete::tag("name"),
-            nom::sequence::preceded(
-                nom::character::complete::space1,
-                nom::multi::separated_list1(nom::character::complete::space1, parse_identifier),
+            keyword("name"),
+            argument(
+                "an atom name",
+                nom::multi::separated_list1(nom::character::complete::space1, parse_pattern),
             ),
         ),
-        |vec: Vec<&str>| Selection::Name(vec.into_iter().map(|s| s.to_string()).collect()),
+        |patterns| pattern_selection(patterns, Selection::Name, Selection::NameRegex),
     )
     .parse(input)
 }
 
-fn parse_resname(input: &str) -> nom::IResult<&str, Selection> {
+fn parse_resname(input: &str) -> IResult<'_, Selection> {
     nom::combinator::map(
         nom::sequence::preceded(
-            nom::bytes::complete::tag("resname"),
-            nom::sequence::preceded(
-                nom::character::complete::space1,
-                nom::multi::separated_list1(nom::character::complete::space1, parse_identifier),
+            keyword("resname"),
+            argument(
+                "a residue name",
+                nom::multi::separated_list1(nom::character::complete::space1, parse_pattern),
             ),
         ),
-        |vec: Vec<&str>| Selection::ResName(vec.into_iter().map(|s| s.to_string()).collect()),
+        |patterns| pattern_selection(patterns, Selection::ResName, Selection::ResNameRegex),
     )
     .parse(input)
 }
 
-fn parse_resid(input: &str) -> nom::IResult<&str, Selection> {
+// `keyword` followed by identifiers
+fn parse_identifiers<'a>(
+    input: &'a str,
+    word: &'static str,
+    label: &'static str,
+) -> IResult<'a, Vec<String>> {
     nom::combinator::map(
         nom::sequence::preceded(
-            nom::bytes::complete::tag("resid"),
-            nom::sequence::preceded(nom::character::complete::space1, parse_numbers),
+            keyword(word),
+            argument(
+                label,
+                nom::multi::separated_list1(nom::character::complete::space1, parse_identifier),
+            ),
         ),
-        Selection::ResId,
+        |vec: Vec<&str>| vec.into_iter().map(|s| s.to_string()).collect(),
     )
     .parse(input)
 }
 
-fn parse_index(input: &str) -> nom::IResult<&str, Selection> {
+fn parse_chain(input: &str) -> IResult<'_, Selection> {
     nom::combinator::map(
-        nom::sequence::preceded(
-            nom::bytes::complete::tag("index"),
-            nom::sequence::preceded(nom::character::complete::space1, parse_numbers),
+        |input| parse_identifiers(input, "chain", "a chain name"),
+        Selection::Chain,
+    )
+    .parse(input)
+}
+
+fn parse_segname(input: &str) -> IResult<'_, Selection> {
+    nom::combinator::map(
+        |input| parse_identifiers(input, "segname", "a segment name"),
+        Selection::SegName,
+    )
+    .parse(input)
+}
+
+fn parse_element(input: &str) -> IResult<'_, Selection> {
+    nom::combinator::map(
+        |input| parse_identifiers(input, "element", "an element"),
+        Selection::Element,
+    )
+    .parse(input)
+}
+
+fn parse_altloc(input: &str) -> IResult<'_, Selection> {
+    nom::combinator::map(
+        |input| parse_identifiers(input, "altloc", "an alternate location"),
+        Selection::AltLoc,
+    )
+    .parse(input)
+}
+
+fn parse_insertion(input: &str) -> IResult<'_, Selection> {
+    nom::combinator::map(
+        |input| parse_identifiers(input, "insertion", "an insertion code"),
+        Selection::Insertion,
+    )
+    .parse(input)
+}
+
+fn parse_model(input: &str) -> IResult<'_, Selection> {
+    nom::combinator::map(
+        nom::sequence::preceded(keyword("model"), argument("a model number", parse_numbers)),
+        Selection::Model,
+    )
+    .parse(input)
+}
+
+fn parse_resid(input: &str) -> IResult<'_, Selection> {
+    let (input, items) = nom::sequence::preceded(
+        keyword("resid"),
+        argument(
+            "a residue id",
+            nom::multi::separated_list1(nom::character::complete::space1, parse_resid_item),
+        ),
+    )
+    .parse(input)?;
+    let mut ids = Vec::new();
+    let mut ranges = Vec::new();
+    let mut insertions = Vec::new();
+    for item in items {
+        match item {
+            ResIdItem::Id(id) => ids.push(id),
+            ResIdItem::Range(first, last, step) => {
+                ranges.push(Selection::ResIdRange { first, last, step })
+            }
+            ResIdItem::Insertion(id, code) => insertions.push((id, code.to_string())),
+        }
+    }
+    // ranges are kept as they are, `resid -1000000000 to 1000000000` is fine
+    let mut selections = Vec::new();
+    if !ids.is_empty() {
+        selections.push(Selection::ResId(ids));
+    }
+    selections.extend(ranges);
+    if !insertions.is_empty() {
+        selections.push(Selection::ResIdInsertion(insertions));
+    }
+    let selection = if selections.len() == 1 {
+        selections.pop().unwrap()
+    } else {
+        Selection::Or(selections.into_iter().map(Box::new).collect())
+    };
+    Ok((input, selection))
+}
+
+enum ResIdItem<'a> {
+    Id(isize),
+    Range(isize, isize, usize),
+    Insertion(isize, &'a str),
+}
+
+// -5, 52A, 10 to 20, 10 to 20 by 2, 10:20, 10:20:2, 10-20
+fn parse_resid_item(input: &str) -> IResult<'_, ResIdItem<'_>> {
+    let space1 = nom::character::complete::space1;
+    let range = |(first, last, step): (isize, isize, Option<usize>)| {
+        ResIdItem::Range(first, last, step.unwrap_or(1))
+    };
+    nom::branch::alt((
+        nom::combinator::map(
+            (
+                parse_isize,
+                nom::sequence::preceded(
+                    nom::sequence::delimited(space1, keyword("to"), space1),
+                    parse_isize,
+                ),
+                nom::combinator::opt(nom::sequence::preceded(
+                    nom::sequence::delimited(space1, keyword("by"), space1),
+                    parse_step,
+                )),
+            ),
+            range,
+        ),
+        nom::combinator::map(
+            (
+                parse_isize,
+                nom::sequence::preceded(keyword(":"), parse_isize),
+                nom::combinator::opt(nom::sequence::preceded(keyword(":"), parse_step)),
+            ),
+            range,
+        ),
+        nom::combinator::map(
+            nom::sequence::separated_pair(parse_isize, keyword("-"), parse_isize),
+            |(first, last)| range((first, last, None)),
         ),
+        nom::combinator::map(
+            nom::sequence::pair(parse_isize, nom::character::complete::alpha1),
+            |(id, code)| ResIdItem::Insertion(id, code),
+        ),
+        nom::combinator::map(parse_isize, ResIdItem::Id),
+    ))
+    .parse(input)
+}
+
+fn parse_isize(input: &str) -> IResult<'_, isize> {
+    nom::combinator::map_res(
+        nom::combinator::recognize(nom::sequence::pair(
+            nom::combinator::opt(keyword("-")),
+            nom::character::complete::digit1,
+        )),
+        |s: &str| s.parse::<isize>(),
+    )
+    .parse(input)
+}
+
+fn parse_step(input: &str) -> IResult<'_, usize> {
+    nom::combinator::verify(parse_usize, |step| *step > 0).parse(input)
+}
+
+fn parse_index(input: &str) -> IResult<'_, Selection> {
+    nom::combinator::map(
+        nom::sequence::preceded(keyword("index"), argument("an atom index", parse_numbers)),
         Selection::Index,
     )
     .parse(input)
 }
 
-fn parse_numbers(input: &str) -> nom::IResult<&str, Vec<usize>> {
+fn parse_distance(input: &str) -> IResult<'_, Selection> {
+    nom::branch::alt((
+        nom::combinator::map(
+            nom::sequence::preceded(
+                keyword("within"),
+                (
+                    argument("a distance", parse_float),
+                    argument("`of`", keyword("of")),
+                    argument("a selection", parse_not),
+                ),
+            ),
+            |(distance, _, selection)| Selection::Within(distance, Box::new(selection)),
+        ),
+        nom::combinator::map(
+            nom::sequence::preceded(
+                keyword("exwithin"),
+                (
+                    argument("a distance", parse_float),
+                    argument("`of`", keyword("of")),
+                    argument("a selection", parse_not),
+                ),
+            ),
+            |(distance, _, selection)| Selection::ExWithin(distance, Box::new(selection)),
+        ),
+        nom::combinator::map(
+            nom::sequence::preceded(
+                keyword("around"),
+                (
+                    argument("a distance", parse_float),
+                    argument("a selection", parse_not),
+                ),
+            ),
+            |(distance, selection)| Selection::ExWithin(distance, Box::new(selection)),
+        ),
+    ))
+    .parse(input)
+}
+
+fn parse_same(input: &str) -> IResult<'_, Selection> {
+    let (input, (expand, selection)) = nom::branch::alt((
+        nom::sequence::preceded(
+            keyword("same"),
+            nom::sequence::pair(
+                argument(
+                    "`residue`, `chain` or `molecule`",
+                    nom::branch::alt((keyword("residue"), keyword("chain"), keyword("molecule"))),
+                ),
+                nom::sequence::preceded(
+                    argument("`as`", keyword("as")),
+                    argument("a selection", parse_not),
+                ),
+            ),
+        ),
+        nom::sequence::pair(
+            nom::combinator::map(
+                nom::branch::alt((keyword("byres"), keyword("bymolecule"))),
+                |by: &str| if by == "byres" { "residue" } else { "molecule" },
+            ),
+            argument("a selection", parse_not),
+        ),
+    ))
+    .parse(input)?;
+    let selection = Box::new(selection);
+    let selection = match expand {
+        "residue" => Selection::SameResidue(selection),
+        "chain" => Selection::SameChain(selection),
+        _ => Selection::SameMolecule(selection),
+    };
+    Ok((input, selection))
+}
+
+fn parse_compare(input: &str) -> IResult<'_, Selection> {
+    nom::combinator::map(
+        (
+            parse_property,
+            nom::sequence::delimited(
+                nom::character::complete::space0,
+                parse_comparison,
+                nom::character::complete::space0,
+            ),
+            // the property may be resid or index otherwise
+            nom::combinator::cut(expect("a number", nom::number::complete::float)),
+        ),
+        |(property, comparison, value)| Selection::Compare(property, comparison, value),
+    )
+    .parse(input)
+}
+
+fn parse_property(input: &str) -> IResult<'_, Property> {
+    nom::branch::alt((
+        nom::combinator::value(Property::X, keyword("x")),
+        nom::combinator::value(Property::Y, keyword("y")),
+        nom::combinator::value(Property::Z, keyword("z")),
+        nom::combinator::value(Property::BFactor, keyword("bfactor")),
+        nom::combinator::value(Property::BFactor, keyword("beta")),
+        nom::combinator::value(Property::Occupancy, keyword("occupancy")),
+        nom::combinator::value(Property::Occupancy, keyword("occ")),
+        nom::combinator::value(Property::Charge, keyword("charge")),
+        nom::combinator::value(Property::Mass, keyword("mass")),
+        nom::combinator::value(Property::ResId, keyword("resid")),
+        nom::combinator::value(Property::Index, keyword("index")),
+    ))
+    .parse(input)
+}
+
+fn parse_comparison(input: &str) -> IResult<'_, Comparison> {
+    // longer operators first
+    nom::branch::alt((
+        nom::combinator::value(Comparison::Le, keyword("<=")),
+        nom::combinator::value(Comparison::Ge, keyword(">=")),
+        nom::combinator::value(Comparison::Eq, keyword("==")),
+        nom::combinator::value(Comparison::Ne, keyword("!=")),
+        nom::combinator::value(Comparison::Lt, keyword("<")),
+        nom::combinator::value(Comparison::Gt, keyword(">")),
+    ))
+    .parse(input)
+}
+
+fn parse_float(input: &str) -> IResult<'_, f32> {
+    nom::combinator::verify(nom::number::complete::float, |x: &f32| {
+        x.is_finite() && *x >= 0.
+    })
+    .parse(input)
+}
+
+fn parse_numbers(input: &str) -> IResult<'_, Vec<usize>> {
     let (input, first) = parse_usize.parse(input)?;
     if let Ok((input, last)) = nom::sequence::preceded(
         nom::sequence::delimited(
             nom::character::complete::space1,
-            nom::bytes::complete::tag("to"),
+            keyword("to"),
             nom::character::complete::space1,
         ),
         parse_usize,
@@ -197,36 +738,39 @@ fn parse_numbers(input: &str) -> nom::IResult<&str, Vec<usize>> {
     }
 }
 
-fn parse_usize(input: &str) -> nom::IResult<&str, usize> {
-    nom::combinator::map(nom::character::complete::digit1, |s: &str| {
-        s.parse::<usize>().unwrap()
+fn parse_usize(input: &str) -> IResult<'_, usize> {
+    nom::combinator::map_res(nom::character::complete::digit1, |s: &str| {
+        s.parse::<usize>()
     })
     .parse(input)
 }
 
-fn parse_all(input: &str) -> nom::IResult<&str, Selection> {
-    nom::combinator::value(Selection::All, nom::bytes::complete::tag("all")).parse(input)
+fn parse_all(input: &str) -> IResult<'_, Selection> {
+    nom::combinator::value(Selection::All, keyword("all")).parse(input)
+}
+
+fn parse_protein(input: &str) -> IResult<'_, Selection> {
+    nom::combinator::value(Selection::Protein, keyword("protein")).parse(input)
 }
 
-fn parse_protein(input: &str) -> nom::IResult<&str, Selection> {
-    nom::combinator::value(Selection::Protein, nom::bytes::complete::tag("protein")).parse(input)
+fn parse_sidechain(input: &str) -> IResult<'_, Selection> {
+    nom::combinator::value(Selection::Sidechain, keyword("sidechain")).parse(input)
 }
 
-fn parse_sidechain(input: &str) -> nom::IResult<&str, Selection> {
-    nom::combinator::value(Selection::Sidechain, nom::bytes::complete::tag("sidechain"))
-        .parse(input)
+fn parse_backbone(input: &str) -> IResult<'_, Selection> {
+    nom::combinator::value(Selection::Backbone, keyword("backbone")).parse(input)
 }
 
-fn parse_backbone(input: &str) -> nom::IResult<&str, Selection> {
-    nom::combinator::value(Selection::Backbone, nom::bytes::complete::tag("backbone")).parse(input)
+fn parse_water(input: &str) -> IResult<'_, Selection> {
+    nom::combinator::value(Selection::Water, keyword("water")).parse(input)
 }
 
-fn parse_water(input: &str) -> nom::IResult<&str, Selection> {
-    nom::combinator::value(Selection::Water, nom::bytes::complete::tag("water")).parse(input)
+fn parse_ion(input: &str) -> IResult<'_, Selection> {
+    nom::combinator::value(Selection::Ion, keyword("ion")).parse(input)
 }
 
-fn parse_ion(input: &str) -> nom::IResult<&str, Selection> {
-    nom::combinator::value(Selection::Ion, nom::bytes::complete::tag("ion")).parse(input)
+fn parse_hetero(input: &str) -> IResult<'_, Selection> {
+    nom::combinator::value(Selection::Hetero, keyword("hetero")).parse(input)
 }
 
 #[cfg(test)]
@@ -328,13 +872,95 @@ mod tests {
     fn resid_to() {
         let selection = "resid 10 to 20";
         let parsed = parse_selection(selection).unwrap();
-        assert_eq!(parsed, Selection::ResId((10..=20).collect()));
+        assert_eq!(
+            parsed,
+            Selection::ResIdRange {
+                first: 10,
+                last: 20,
+                step: 1
+            }
+        );
 
         let selection = "resid to 20";
         let parsed = parse_selection(selection);
         assert!(parsed.is_err());
     }
 
+    #[test]
+    fn resid_signed() {
+        let selection = "resid -5 to 2";
+        let parsed = parse_selection(selection).unwrap();
+        assert_eq!(
+            parsed,
+            Selection::ResIdRange {
+                first: -5,
+                last: 2,
+                step: 1
+            }
+        );
+
+        let selection = "resid -3 -1 0";
+        let parsed = parse_selection(selection).unwrap();
+        assert_eq!(parsed, Selection::ResId(vec![-3, -1, 0]));
+    }
+
+    #[test]
+    fn resid_range_syntax() {
+        let selection = "resid 10:13 20-22 -2--1";
+        let parsed = parse_selection(selection).unwrap();
+        let range = |first, last, step| Box::new(Selection::ResIdRange { first, last, step });
+        assert_eq!(
+            parsed,
+            Selection::Or(vec![range(10, 13, 1), range(20, 22, 1), range(-2, -1, 1)])
+        );
+
+        let selection = "resid 1 to 9 by 4 20:30:5 40";
+        let parsed = parse_selection(selection).unwrap();
+        assert_eq!(
+            parsed,
+            Selection::Or(vec![
+                Box::new(Selection::ResId(vec![40])),
+                range(1, 9, 4),
+                range(20, 30, 5)
+            ])
+        );
+
+        // not expanded
+        let selection = "resid -1000000000 to 1000000000";
+        let parsed = parse_selection(selection).unwrap();
+        assert_eq!(*range(-1000000000, 1000000000, 1), parsed);
+
+        let selection = "resid 1:10:0";
+        assert!(parse_selection(selection).is_err());
+        let selection = "resid 1:";
+        assert!(parse_selection(selection).is_err());
+        // overflow
+        let selection = "resid 1:10:99999999999999999999999";
+        assert!(parse_selection(selection).is_err());
+        let selection = "index 99999999999999999999999";
+        assert!(parse_selection(selection).is_err());
+    }
+
+    #[test]
+    fn resid_insertion() {
+        let selection = "resid 52A";
+        let parsed = parse_selection(selection).unwrap();
+        assert_eq!(
+            parsed,
+            Selection::ResIdInsertion(vec![(52, "A".to_string())])
+        );
+
+        let selection = "resid 50 52A 53";
+        let parsed = parse_selection(selection).unwrap();
+        assert_eq!(
+            parsed,
+            Selection::Or(vec![
+                Box::new(Selection::ResId(vec![50, 53])),
+                Box::new(Selection::ResIdInsertion(vec![(52, "A".to_string())]))
+            ])
+        );
+    }
+
     #[test]
     fn and() {
         let selection = "resname ALA and resname GLU";
@@ -450,30 +1076,374 @@ mod tests {
     }
 
     #[test]
-    fn sample_eval() {
-        #[allow(non_local_definitions)]
-        impl Selection {
-            fn eval(&self, state: &State) -> bool {
-                match self {
-                    Selection::All => true,
-                    Selection::ResName(names) => names.iter().any(|name| name == &state.resname),
-                    Selection::ResId(ids) => ids.iter().any(|id| id == &state.resid),
-                    Selection::Name(names) => names.iter().any(|name| name == &state.name),
-                    Selection::Index(indices) => indices.iter().any(|index| index == &state.index),
-                    Selection::Not(selection) => !selection.eval(state),
-                    Selection::And(selections) => selections.iter().all(|s| s.eval(state)),
-                    Selection::Or(selections) => selections.iter().any(|s| s.eval(state)),
-                    Selection::Bracket(selection) => selection.eval(state),
-                    _ => false,
-                    // Selection::Protein => state.is_protein(),
-                    // Selection::Water => state.is_water(),
-                    // Selection::Ion => state.is_ion(),
-                    // Selection::Backbone => state.is_backbone(),
-                    // Selection::Sidechain => state.is_sidechain(),
-                }
-            }
+    fn within() {
+        let selection = "within 5 of resname LIG";
+        let parsed = parse_selection(selection).unwrap();
+        assert_eq!(
+            parsed,
+            Selection::Within(5., Box::new(Selection::ResName(vec!["LIG".to_string()])))
+        );
+
+        let selection = "exwithin 3.5 of (protein and name CA)";
+        let parsed = parse_selection(selection).unwrap();
+        assert_eq!(
+            parsed,
+            Selection::ExWithin(
+                3.5,
+                Box::new(Selection::Bracket(Box::new(Selection::And(vec![
+                    Box::new(Selection::Protein),
+                    Box::new(Selection::Name(vec!["CA".to_string()]))
+                ]))))
+            )
+        );
+
+        let selection = "around 4 protein";
+        let parsed = parse_selection(selection).unwrap();
+        assert_eq!(
+            parsed,
+            Selection::ExWithin(4., Box::new(Selection::Protein))
+        );
+
+        let selection = "within -1 of protein";
+        assert!(parse_selection(selection).is_err());
+        let selection = "within of protein";
+        assert!(parse_selection(selection).is_err());
+    }
+
+    #[test]
+    fn within_implicit_and() {
+        let selection = "water within 5 of resname LIG and name O";
+        let parsed = parse_selection(selection).unwrap();
+        assert_eq!(
+            parsed,
+            Selection::And(vec![
+                Box::new(Selection::Water),
+                Box::new(Selection::Within(
+                    5.,
+                    Box::new(Selection::ResName(vec!["LIG".to_string()]))
+                )),
+                Box::new(Selection::Name(vec!["O".to_string()]))
+            ])
+        );
+
+        // keywords are not names
+        let selection = "resname HOH within 5 of protein";
+        let parsed = parse_selection(selection).unwrap();
+        assert_eq!(
+            parsed,
+            Selection::And(vec![
+                Box::new(Selection::ResName(vec!["HOH".to_string()])),
+                Box::new(Selection::Within(5., Box::new(Selection::Protein)))
+            ])
+        );
+    }
+
+    #[test]
+    fn same() {
+        let selection = "same residue as name CA";
+        let parsed = parse_selection(selection).unwrap();
+        assert_eq!(
+            parsed,
+            Selection::SameResidue(Box::new(Selection::Name(vec!["CA".to_string()])))
+        );
+
+        let selection = "byres within 4 of resname LIG";
+        let parsed = parse_selection(selection).unwrap();
+        assert_eq!(
+            parsed,
+            Selection::SameResidue(Box::new(Selection::Within(
+                4.,
+                Box::new(Selection::ResName(vec!["LIG".to_string()]))
+            )))
+        );
+
+        let selection = "same chain as resid 10 or bymolecule resname LIG";
+        let parsed = parse_selection(selection).unwrap();
+        assert_eq!(
+            parsed,
+            Selection::Or(vec![
+                Box::new(Selection::SameChain(Box::new(Selection::ResId(vec![10])))),
+                Box::new(Selection::SameMolecule(Box::new(Selection::ResName(vec![
+                    "LIG".to_string()
+                ]))))
+            ])
+        );
+
+        let selection = "same atom as name CA";
+        assert!(parse_selection(selection).is_err());
+    }
+
+    #[test]
+    fn fields() {
+        let selection = "chain A B and model 1";
+        let parsed = parse_selection(selection).unwrap();
+        assert_eq!(
+            parsed,
+            Selection::And(vec![
+                Box::new(Selection::Chain(vec!["A".to_string(), "B".to_string()])),
+                Box::new(Selection::Model(vec![1]))
+            ])
+        );
+
+        let selection = "segname PROA or element C N";
+        let parsed = parse_selection(selection).unwrap();
+        assert_eq!(
+            parsed,
+            Selection::Or(vec![
+                Box::new(Selection::SegName(vec!["PROA".to_string()])),
+                Box::new(Selection::Element(vec!["C".to_string(), "N".to_string()]))
+            ])
+        );
+
+        let selection = "altloc A and insertion B";
+        let parsed = parse_selection(selection).unwrap();
+        assert_eq!(
+            parsed,
+            Selection::And(vec![
+                Box::new(Selection::AltLoc(vec!["A".to_string()])),
+                Box::new(Selection::Insertion(vec!["B".to_string()]))
+            ])
+        );
+
+        let selection = "hetero and not water";
+        let parsed = parse_selection(selection).unwrap();
+        assert_eq!(
+            parsed,
+            Selection::And(vec![
+                Box::new(Selection::Hetero),
+                Box::new(Selection::Not(Box::new(Selection::Water)))
+            ])
+        );
+
+        let selection = "model A";
+        assert!(parse_selection(selection).is_err());
+    }
+
+    #[test]
+    fn residue_class() {
+        let parsed = parse_selection("ligand or nucleic and not lipid").unwrap();
+        assert_eq!(
+            parsed,
+            Selection::Or(vec![
+                Box::new(Selection::Ligand),
+                Box::new(Selection::And(vec![
+                    Box::new(Selection::Nucleic),
+                    Box::new(Selection::Not(Box::new(Selection::Lipid)))
+                ]))
+            ])
+        );
+
+        let parsed = parse_selection("protein and (acidic or basic) and not aromatic").unwrap();
+        assert_eq!(
+            parsed,
+            Selection::And(vec![
+                Box::new(Selection::Protein),
+                Box::new(Selection::Bracket(Box::new(Selection::Or(vec![
+                    Box::new(Selection::Acidic),
+                    Box::new(Selection::Basic)
+                ])))),
+                Box::new(Selection::Not(Box::new(Selection::Aromatic)))
+            ])
+        );
+
+        for (keyword, selection) in [
+            ("sugar", Selection::Sugar),
+            ("cofactor", Selection::Cofactor),
+            ("hydrophobic", Selection::Hydrophobic),
+            ("polar", Selection::Polar),
+        ] {
+            assert_eq!(parse_selection(keyword).unwrap(), selection);
         }
+    }
+
+    #[test]
+    fn compare() {
+        let selection = "x > 10";
+        let parsed = parse_selection(selection).unwrap();
+        assert_eq!(parsed, Selection::Compare(Property::X, Comparison::Gt, 10.));
+
+        let selection = "bfactor<30.5 and charge != -1";
+        let parsed = parse_selection(selection).unwrap();
+        assert_eq!(
+            parsed,
+            Selection::And(vec![
+                Box::new(Selection::Compare(Property::BFactor, Comparison::Lt, 30.5)),
+                Box::new(Selection::Compare(Property::Charge, Comparison::Ne, -1.))
+            ])
+        );
+
+        let selection = "mass >= 12 or occ == 1.0e0 or resid <= 5 or index < 3";
+        let parsed = parse_selection(selection).unwrap();
+        assert_eq!(
+            parsed,
+            Selection::Or(vec![
+                Box::new(Selection::Compare(Property::Mass, Comparison::Ge, 12.)),
+                Box::new(Selection::Compare(Property::Occupancy, Comparison::Eq, 1.)),
+                Box::new(Selection::Compare(Property::ResId, Comparison::Le, 5.)),
+                Box::new(Selection::Compare(Property::Index, Comparison::Lt, 3.))
+            ])
+        );
+
+        // resid and index lists are not comparisons
+        let selection = "resid 5 and index 3";
+        let parsed = parse_selection(selection).unwrap();
+        assert_eq!(
+            parsed,
+            Selection::And(vec![
+                Box::new(Selection::ResId(vec![5])),
+                Box::new(Selection::Index(vec![3]))
+            ])
+        );
+
+        let selection = "x => 10";
+        assert!(parse_selection(selection).is_err());
+        let selection = "x > ten";
+        assert!(parse_selection(selection).is_err());
+    }
+
+    #[test]
+    fn pattern() {
+        let selection = "name C1' H2'' C*";
+        let parsed = parse_selection(selection).unwrap();
+        assert_eq!(
+            parsed,
+            Selection::Name(vec![
+                "C1'".to_string(),
+                "H2''".to_string(),
+                "C*".to_string()
+            ])
+        );
+
+        let selection = "resname NA+ CL- 'and' C?";
+        let parsed = parse_selection(selection).unwrap();
+        assert_eq!(
+            parsed,
+            Selection::ResName(vec![
+                "NA+".to_string(),
+                "CL-".to_string(),
+                "and".to_string(),
+                "C?".to_string()
+            ])
+        );
+
+        let selection = "name \"C[AB]\"";
+        let parsed = parse_selection(selection).unwrap();
+        assert_eq!(parsed, Selection::NameRegex("C[AB]".to_string()));
+
+        let selection = "resname ALA \"GL.\" and name CA";
+        let parsed = parse_selection(selection).unwrap();
+        assert_eq!(
+            parsed,
+            Selection::And(vec![
+                Box::new(Selection::Or(vec![
+                    Box::new(Selection::ResName(vec!["ALA".to_string()])),
+                    Box::new(Selection::ResNameRegex("GL.".to_string()))
+                ])),
+                Box::new(Selection::Name(vec!["CA".to_string()]))
+            ])
+        );
+
+        // invalid regex and unclosed quotes
+        let selection = "name \"C[AB\"";
+        assert!(parse_selection(selection).is_err());
+        let selection = "name 'CA";
+        assert!(parse_selection(selection).is_err());
+    }
 
+    #[test]
+    fn define() {
+        let parsed = parse_statement("define pocket as byres within 5 of resname LIG").unwrap();
+        assert_eq!(
+            parsed,
+            Statement::Define(
+                "pocket".to_string(),
+                Selection::SameResidue(Box::new(Selection::Within(
+                    5.,
+                    Box::new(Selection::ResName(vec!["LIG".to_string()]))
+                )))
+            )
+        );
+
+        let parsed = parse_statement("protein").unwrap();
+        assert_eq!(parsed, Statement::Select(Selection::Protein));
+
+        assert!(parse_statement("define pocket protein").is_err());
+    }
+
+    #[test]
+    fn macro_reference() {
+        let parsed = parse_selection("$pocket and not @heavy_atoms").unwrap();
+        assert_eq!(
+            parsed,
+            Selection::And(vec![
+                Box::new(Selection::Macro("pocket".to_string())),
+                Box::new(Selection::Not(Box::new(Selection::Macro(
+                    "heavy_atoms".to_string()
+                ))))
+            ])
+        );
+
+        assert!(parse_selection("$").is_err());
+    }
+
+    #[test]
+    fn parse_error() {
+        let error = parse_selection("protein and resnmae ALA").unwrap_err();
+        assert_eq!(error.offset, 12);
+        assert_eq!(error.unexpected, "resnmae");
+        assert_eq!(error.expected, vec!["a selection"]);
+        assert_eq!(error.suggestion.as_deref(), Some("resname"));
+        assert_eq!(
+            error.to_string(),
+            "\
+unexpected `resnmae` at offset 12, expected a selection
+protein and resnmae ALA
+            ^^^^^^^
+did you mean `resname`?"
+        );
+
+        let error = parse_selection("(protein or water").unwrap_err();
+        assert_eq!(error.offset, 17);
+        assert_eq!(error.unexpected, "");
+        assert_eq!(error.expected, vec!["`)`"]);
+
+        let error = parse_selection("within 5 water").unwrap_err();
+        assert_eq!(error.offset, 9);
+        assert_eq!(error.expected, vec!["`of`"]);
+        assert_eq!(error.suggestion, None);
+
+        let error = parse_selection("protein xyz").unwrap_err();
+        assert_eq!(error.offset, 8);
+        assert_eq!(error.expected, vec!["`and`", "`or`"]);
+
+        let error = parse_selection("same atom as water").unwrap_err();
+        assert_eq!(error.offset, 5);
+        assert_eq!(
+            error.message(),
+            "unexpected `atom` at offset 5, expected `residue`, `chain` or `molecule`"
+        );
+
+        // whole words
+        let error = parse_selection("waters").unwrap_err();
+        assert_eq!(error.offset, 0);
+        assert_eq!(error.unexpected, "waters");
+        assert_eq!(error.suggestion.as_deref(), Some("water"));
+
+        let error = parse_selection("resnameALA").unwrap_err();
+        assert_eq!(error.offset, 0);
+        assert_eq!(error.unexpected, "resnameALA");
+        assert_eq!(error.expected, vec!["a selection"]);
+
+        let error = parse_selection("protein andwater").unwrap_err();
+        assert_eq!(error.unexpected, "andwater");
+
+        // character offset
+        let error = parse_selection("name 'Å' or").unwrap_err();
+        assert_eq!(error.offset, 11);
+    }
+
+    #[test]
+    fn sample_eval() {
         struct State {
             index: usize,
             resname: String,
@@ -481,6 +1451,51 @@ mod tests {
             name: String,
         }
 
+        impl AtomContext for [State] {
+            fn n_atoms(&self) -> usize {
+                self.len()
+            }
+            fn atom_name(&self, i: usize) -> &str {
+                &self[i].name
+            }
+            fn residue_name(&self, i: usize) -> &str {
+                &self[i].resname
+            }
+            fn residue_id(&self, i: usize) -> isize {
+                self[i].resid as isize
+            }
+            fn residue_index(&self, i: usize) -> usize {
+                self[i].resid
+            }
+            fn chain_name(&self, _i: usize) -> &str {
+                "A"
+            }
+            fn atom_id(&self, i: usize) -> usize {
+                self[i].index
+            }
+            fn position(&self, _i: usize) -> [f32; 3] {
+                [0., 0., 0.]
+            }
+            fn neighbors(&self, _i: usize) -> Vec<usize> {
+                Vec::new()
+            }
+            fn is_protein(&self, _i: usize) -> bool {
+                true
+            }
+            fn is_water(&self, _i: usize) -> bool {
+                false
+            }
+            fn is_ion(&self, _i: usize) -> bool {
+                false
+            }
+            fn is_backbone(&self, i: usize) -> bool {
+                self[i].name == "CA"
+            }
+            fn is_sidechain(&self, i: usize) -> bool {
+                !self.is_backbone(i)
+            }
+        }
+
         let expr = "(index 10 to 20) or resname ALA";
         let selection = parse_selection(expr).unwrap();
 
@@ -498,7 +1513,18 @@ mod tests {
             name: "CA".to_string(),
         };
 
-        assert!(selection.eval(&state1));
-        assert!(!selection.eval(&state2));
+        let state3 = State {
+            index: 30,
+            resname: "GLU".to_string(),
+            resid: 20,
+            name: "CB".to_string(),
+        };
+
+        let states = [state1, state2, state3];
+        assert_eq!(selection.mask(&states[..]), vec![true, false, false]);
+        assert_eq!(selection.select(&states[..]), vec![0]);
+
+        let selection = parse_selection("resid 20 and not backbone").unwrap();
+        assert_eq!(selection.select(&states[..]), vec![2]);
     }
 }
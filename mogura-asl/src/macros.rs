@@ -0,0 +1,183 @@
+use crate::{Selection, Statement, parse_statement};
+
+/// Named selections referenced by `$name` or `@name`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Macros {
+    definitions: std::collections::BTreeMap<String, Selection>,
+}
+
+impl Macros {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// replaces the definition with the same name
+    pub fn define(&mut self, name: &str, selection: Selection) {
+        self.definitions.insert(name.to_string(), selection);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Selection> {
+        self.definitions.get(name)
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.definitions.keys().map(|name| name.as_str())
+    }
+
+    /// definitions of `other` replace those with the same name
+    pub fn extend(&mut self, other: &Macros) {
+        self.definitions.extend(
+            other
+                .definitions
+                .iter()
+                .map(|(name, selection)| (name.clone(), selection.clone())),
+        );
+    }
+
+    /// One `define <name> as <selection>` per line, `#` starts a comment.
+    pub fn from_config(content: &str) -> Result<Self, String> {
+        let mut macros = Self::new();
+        for (n, line) in content.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            match parse_statement(line) {
+                Ok(Statement::Define(name, selection)) => macros.define(&name, selection),
+                Ok(Statement::Select(_)) => {
+                    return Err(format!("line {}: expected `define <name> as ...`", n + 1));
+                }
+                Err(e) => return Err(format!("line {}: {}", n + 1, e.message())),
+            }
+        }
+        Ok(macros)
+    }
+
+    pub fn load(path: &std::path::Path) -> Result<Self, String> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| format!("failed to read {}: {}", path.display(), e))?;
+        Self::from_config(&content).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+}
+
+/// `$MOGURA_CONFIG_DIR/macros.asl`, `$XDG_CONFIG_HOME/mogura/macros.asl` or
+/// `~/.config/mogura/macros.asl`
+pub fn default_macro_file() -> Option<std::path::PathBuf> {
+    if cfg!(target_arch = "wasm32") {
+        return None;
+    }
+    let non_empty = |key: &str| std::env::var_os(key).filter(|value| !value.is_empty());
+    let dir = if let Some(dir) = non_empty("MOGURA_CONFIG_DIR") {
+        std::path::PathBuf::from(dir)
+    } else if let Some(dir) = non_empty("XDG_CONFIG_HOME") {
+        std::path::PathBuf::from(dir).join("mogura")
+    } else {
+        std::path::PathBuf::from(non_empty("HOME")?)
+            .join(".config")
+            .join("mogura")
+    };
+    Some(dir.join("macros.asl"))
+}
+
+impl Selection {
+    /// Replaces the macro references with their definitions.
+    pub fn expand(&self, macros: &Macros) -> Result<Selection, String> {
+        self.expand_with(macros, &mut Vec::new())
+    }
+
+    // `stack` of the macros being expanded, to find recursive definitions
+    fn expand_with(&self, macros: &Macros, stack: &mut Vec<String>) -> Result<Selection, String> {
+        let mut expand = |selection: &Selection| -> Result<Box<Selection>, String> {
+            Ok(Box::new(selection.expand_with(macros, stack)?))
+        };
+        let expanded = match self {
+            Selection::Macro(name) => {
+                if stack.contains(name) {
+                    return Err(format!("recursive macro: ${}", name));
+                }
+                let definition = macros
+                    .get(name)
+                    .ok_or_else(|| format!("undefined macro: ${}", name))?;
+                stack.push(name.clone());
+                let expanded = definition.expand_with(macros, stack)?;
+                stack.pop();
+                expanded
+            }
+            Selection::Not(selection) => Selection::Not(expand(selection)?),
+            Selection::Bracket(selection) => Selection::Bracket(expand(selection)?),
+            Selection::SameResidue(selection) => Selection::SameResidue(expand(selection)?),
+            Selection::SameChain(selection) => Selection::SameChain(expand(selection)?),
+            Selection::SameMolecule(selection) => Selection::SameMolecule(expand(selection)?),
+            Selection::Within(distance, selection) => {
+                Selection::Within(*distance, expand(selection)?)
+            }
+            Selection::ExWithin(distance, selection) => {
+                Selection::ExWithin(*distance, expand(selection)?)
+            }
+            Selection::And(selections) => Selection::And(
+                selections
+                    .iter()
+                    .map(|s| expand(s))
+                    .collect::<Result<_, _>>()?,
+            ),
+            Selection::Or(selections) => Selection::Or(
+                selections
+                    .iter()
+                    .map(|s| expand(s))
+                    .collect::<Result<_, _>>()?,
+            ),
+            selection => selection.clone(),
+        };
+        Ok(expanded)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::macros::*;
+    use crate::parse_selection;
+
+    #[test]
+    fn expand() {
+        let mut macros = Macros::new();
+        macros.define("ligand", parse_selection("resname LIG").unwrap());
+        macros.define(
+            "site",
+            parse_selection("byres within 5 of $ligand").unwrap(),
+        );
+
+        let selection = parse_selection("protein and @site").unwrap();
+        assert_eq!(
+            selection.expand(&macros).unwrap(),
+            parse_selection("protein and byres within 5 of resname LIG").unwrap()
+        );
+
+        let selection = parse_selection("$pocket").unwrap();
+        assert!(selection.expand(&macros).is_err());
+
+        macros.define("a", parse_selection("name CA or $b").unwrap());
+        macros.define("b", parse_selection("not $a").unwrap());
+        let selection = parse_selection("$a").unwrap();
+        assert!(selection.expand(&macros).is_err());
+    }
+
+    #[test]
+    fn config() {
+        let content = "\
+# binding site
+define ligand as resname LIG
+
+define heavy as not name H*  # no hydrogens
+";
+        let macros = Macros::from_config(content).unwrap();
+        assert_eq!(macros.names().collect::<Vec<_>>(), vec!["heavy", "ligand"]);
+        assert_eq!(
+            macros.get("ligand"),
+            Some(&Selection::ResName(vec!["LIG".to_string()]))
+        );
+
+        let content = "define ligand as resname LIG\nprotein\n";
+        let error = Macros::from_config(content).unwrap_err();
+        assert!(error.starts_with("line 2"), "{}", error);
+    }
+}
@@ -0,0 +1,214 @@
+/// Where a problem was found. Every field is optional because readers
+/// working on in-memory content do not know the file name,
+/// and some formats only report the line.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Location {
+    pub file: Option<String>,
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+}
+
+impl Location {
+    pub fn file(file: &str) -> Self {
+        Self {
+            file: Some(file.to_string()),
+            ..Default::default()
+        }
+    }
+
+    pub fn line(line: usize, column: Option<usize>) -> Self {
+        Self {
+            file: None,
+            line: Some(line),
+            column,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.file.is_none() && self.line.is_none()
+    }
+
+    // "file:line:column: ", or "" when nothing is known
+    fn prefix(&self) -> String {
+        if self.is_empty() {
+            String::new()
+        } else {
+            format!("{}: ", self)
+        }
+    }
+}
+
+impl std::fmt::Display for Location {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut parts = Vec::new();
+        if let Some(file) = &self.file {
+            parts.push(file.clone());
+        }
+        match (self.line, self.column) {
+            (Some(line), Some(column)) if self.file.is_some() => {
+                parts.push(format!("{}:{}", line, column))
+            }
+            (Some(line), None) if self.file.is_some() => parts.push(line.to_string()),
+            (Some(line), Some(column)) => parts.push(format!("line {}, column {}", line, column)),
+            (Some(line), None) => parts.push(format!("line {}", line)),
+            _ => {}
+        }
+        write!(f, "{}", parts.join(":"))
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum MoguraIoError {
+    #[error("{}{source}", location.prefix())]
+    Io {
+        location: Location,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("{}unsupported format", location.prefix())]
+    UnsupportedFormat { location: Location },
+    #[error("{}failed to parse {format}: {message}", location.prefix())]
+    Parse {
+        format: String,
+        location: Location,
+        message: String,
+    },
+    #[error("failed to download {url}: {message}")]
+    Download { url: String, message: String },
+    #[error("{}{source}", location.prefix())]
+    Other {
+        location: Location,
+        #[source]
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+}
+
+impl MoguraIoError {
+    pub fn io(file: &str, source: std::io::Error) -> Self {
+        MoguraIoError::Io {
+            location: Location::file(file),
+            source,
+        }
+    }
+
+    pub fn parse(format: &str, location: Location, message: &str) -> Self {
+        MoguraIoError::Parse {
+            format: format.to_string(),
+            location,
+            message: message.to_string(),
+        }
+    }
+
+    /// For errors from user defined readers.
+    pub fn other<E: Into<Box<dyn std::error::Error + Send + Sync>>>(source: E) -> Self {
+        MoguraIoError::Other {
+            location: Location::default(),
+            source: source.into(),
+        }
+    }
+
+    pub fn location(&self) -> Option<&Location> {
+        match self {
+            MoguraIoError::Io { location, .. }
+            | MoguraIoError::UnsupportedFormat { location }
+            | MoguraIoError::Parse { location, .. }
+            | MoguraIoError::Other { location, .. } => Some(location),
+            MoguraIoError::Download { .. } => None,
+        }
+    }
+
+    /// Fills in the file name if the reader did not know it.
+    pub fn with_file(mut self, file: Option<&str>) -> Self {
+        let location = match &mut self {
+            MoguraIoError::Io { location, .. }
+            | MoguraIoError::UnsupportedFormat { location }
+            | MoguraIoError::Parse { location, .. }
+            | MoguraIoError::Other { location, .. } => location,
+            MoguraIoError::Download { .. } => return self,
+        };
+        if location.file.is_none() {
+            location.file = file.map(|file| file.to_string());
+        }
+        self
+    }
+}
+
+impl From<std::io::Error> for MoguraIoError {
+    fn from(source: std::io::Error) -> Self {
+        MoguraIoError::Io {
+            location: Location::default(),
+            source,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl std::fmt::Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+/// Non-fatal problem found while reading, e.g. pdbtbx warnings on a loosely formatted PDB.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub location: Location,
+    pub message: String,
+}
+
+impl std::fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: {}{}",
+            self.severity,
+            self.location.prefix(),
+            self.message
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::*;
+
+    #[test]
+    fn display() {
+        let error = MoguraIoError::parse("PDB", Location::line(12, Some(31)), "invalid coordinate");
+        assert_eq!(
+            error.to_string(),
+            "line 12, column 31: failed to parse PDB: invalid coordinate"
+        );
+        let error = error.with_file(Some("1abc.pdb"));
+        assert_eq!(
+            error.to_string(),
+            "1abc.pdb:12:31: failed to parse PDB: invalid coordinate"
+        );
+        let error = error.with_file(Some("other.pdb"));
+        assert_eq!(
+            error
+                .location()
+                .and_then(|location| location.file.as_deref()),
+            Some("1abc.pdb")
+        );
+
+        let diagnostic = Diagnostic {
+            severity: Severity::Warning,
+            location: Location::line(3, None),
+            message: "atom serial number is not unique".to_string(),
+        };
+        assert_eq!(
+            diagnostic.to_string(),
+            "warning: line 3: atom serial number is not unique"
+        );
+    }
+}
@@ -0,0 +1,310 @@
+use std::io::{BufReader, Read};
+
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+const BZIP2_MAGIC: &[u8] = b"BZh";
+const XZ_MAGIC: &[u8] = &[0xfd, b'7', b'z', b'X', b'Z', 0x00];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    None,
+    Gzip,
+    Bzip2,
+    Xz,
+}
+
+impl Compression {
+    pub fn from_extension(extension: &str) -> Self {
+        match extension.to_ascii_lowercase().as_str() {
+            "gz" | "gzip" => Compression::Gzip,
+            "bz2" | "bzip2" => Compression::Bzip2,
+            "xz" => Compression::Xz,
+            _ => Compression::None,
+        }
+    }
+
+    pub fn from_magic(bytes: &[u8]) -> Self {
+        if bytes.starts_with(GZIP_MAGIC) {
+            Compression::Gzip
+        } else if bytes.starts_with(BZIP2_MAGIC) {
+            Compression::Bzip2
+        } else if bytes.starts_with(XZ_MAGIC) {
+            Compression::Xz
+        } else {
+            Compression::None
+        }
+    }
+
+    pub fn is_compressed(&self) -> bool {
+        *self != Compression::None
+    }
+}
+
+/// Splits a file name such as `1abc.pdb.gz` into its format extension (`pdb`)
+/// and the compression guessed from the outer extension.
+pub fn split_extension(file_name: &str) -> (Option<String>, Compression) {
+    let path = std::path::Path::new(file_name);
+    let outer = path.extension().and_then(|ext| ext.to_str());
+    let compression = outer
+        .map(Compression::from_extension)
+        .unwrap_or(Compression::None);
+    let extension = if compression.is_compressed() {
+        path.file_stem()
+            .and_then(|stem| std::path::Path::new(stem).extension())
+            .and_then(|ext| ext.to_str())
+    } else {
+        outer
+    };
+    (extension.map(|ext| ext.to_ascii_lowercase()), compression)
+}
+
+/// Reads up to the longest magic number, retrying short reads until EOF.
+fn read_magic<R: Read>(reader: &mut R) -> Result<Vec<u8>, std::io::Error> {
+    let mut magic = vec![0; XZ_MAGIC.len()];
+    let mut filled = 0;
+    while filled < magic.len() {
+        match reader.read(&mut magic[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    magic.truncate(filled);
+    Ok(magic)
+}
+
+/// Wraps `reader` in a streaming decoder chosen from its leading magic bytes.
+/// Uncompressed input is passed through as is.
+pub fn decompress<'a, R: Read + 'a>(mut reader: R) -> Result<Box<dyn Read + 'a>, std::io::Error> {
+    let magic = read_magic(&mut reader)?;
+    let compression = Compression::from_magic(&magic);
+    let reader = BufReader::new(std::io::Cursor::new(magic).chain(reader));
+    match compression {
+        Compression::None => Ok(Box::new(reader)),
+        Compression::Gzip => Ok(Box::new(flate2::bufread::MultiGzDecoder::new(reader))),
+        Compression::Bzip2 => Ok(Box::new(bzip2::bufread::MultiBzDecoder::new(reader))),
+        #[cfg(not(target_arch = "wasm32"))]
+        Compression::Xz => Ok(Box::new(xz2::bufread::XzDecoder::new_multi_decoder(reader))),
+        #[cfg(target_arch = "wasm32")]
+        Compression::Xz => Err(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "xz is not supported on WASM",
+        )),
+    }
+}
+
+/// Readers such as groan_rs only accept paths, so compressed inputs are
+/// decompressed into a temporary file which is removed on drop.
+#[cfg_attr(not(feature = "groan_rs"), allow(dead_code))]
+pub(crate) enum LocalFile {
+    Original(String),
+    Decompressed(TempFile),
+}
+
+#[cfg_attr(not(feature = "groan_rs"), allow(dead_code))]
+impl LocalFile {
+    pub(crate) fn new(path: &str, extension: &str) -> Result<Self, std::io::Error> {
+        let mut file = std::fs::File::open(path)?;
+        let magic = read_magic(&mut file)?;
+        if Compression::from_magic(&magic).is_compressed() {
+            Ok(LocalFile::Decompressed(TempFile::new(
+                decompress(std::io::Cursor::new(magic).chain(file))?,
+                extension,
+            )?))
+        } else {
+            Ok(LocalFile::Original(path.to_string()))
+        }
+    }
+
+    pub(crate) fn path(&self) -> &str {
+        match self {
+            LocalFile::Original(path) => path,
+            LocalFile::Decompressed(temp_file) => temp_file.path(),
+        }
+    }
+}
+
+#[cfg_attr(not(feature = "groan_rs"), allow(dead_code))]
+pub(crate) struct TempFile {
+    path: String,
+}
+
+#[cfg_attr(not(feature = "groan_rs"), allow(dead_code))]
+impl TempFile {
+    pub(crate) fn new<R: Read>(mut reader: R, extension: &str) -> Result<Self, std::io::Error> {
+        static COUNTER: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+        // never open a file someone else already placed at the predictable name
+        let (path, mut file) = loop {
+            let count = COUNTER.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+            let path = std::env::temp_dir()
+                .join(format!(
+                    "mogura-{}-{}.{}",
+                    std::process::id(),
+                    count,
+                    extension
+                ))
+                .display()
+                .to_string();
+            match std::fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&path)
+            {
+                Ok(file) => break (path, file),
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+                Err(e) => return Err(e),
+            }
+        };
+        // from here on the file is ours and dropping it cleans up
+        let temp_file = Self { path };
+        std::io::copy(&mut reader, &mut file)?;
+        Ok(temp_file)
+    }
+
+    pub(crate) fn path(&self) -> &str {
+        &self.path
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::input::compression::*;
+    use std::io::Write;
+
+    fn read_to_string(reader: &[u8]) -> String {
+        let mut content = String::new();
+        decompress(reader)
+            .unwrap()
+            .read_to_string(&mut content)
+            .unwrap();
+        content
+    }
+
+    #[test]
+    fn extension() {
+        assert_eq!(
+            split_extension("1abc.pdb.gz"),
+            (Some("pdb".to_string()), Compression::Gzip)
+        );
+        assert_eq!(
+            split_extension("dir.v1/init.GRO"),
+            (Some("gro".to_string()), Compression::None)
+        );
+        assert_eq!(
+            split_extension("traj.xtc.xz"),
+            (Some("xtc".to_string()), Compression::Xz)
+        );
+        assert_eq!(split_extension("1abc.gz"), (None, Compression::Gzip));
+        assert_eq!(split_extension("1abc"), (None, Compression::None));
+    }
+
+    #[test]
+    fn gzip() {
+        let content = "ATOM      1  N   ALA A   1      11.104   6.134  -6.504\n";
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(content.as_bytes()).unwrap();
+        let compressed = encoder.finish().unwrap();
+
+        assert_eq!(Compression::from_magic(&compressed), Compression::Gzip);
+        assert_eq!(read_to_string(&compressed), content);
+    }
+
+    #[test]
+    fn bzip2() {
+        let content = "ATOM      1  N   ALA A   1      11.104   6.134  -6.504\n";
+        let mut encoder = bzip2::write::BzEncoder::new(Vec::new(), bzip2::Compression::default());
+        encoder.write_all(content.as_bytes()).unwrap();
+        let compressed = encoder.finish().unwrap();
+
+        assert_eq!(Compression::from_magic(&compressed), Compression::Bzip2);
+        assert_eq!(read_to_string(&compressed), content);
+    }
+
+    #[test]
+    fn xz() {
+        let content = "ATOM      1  N   ALA A   1      11.104   6.134  -6.504\n";
+        let mut encoder = xz2::write::XzEncoder::new(Vec::new(), 6);
+        encoder.write_all(content.as_bytes()).unwrap();
+        let compressed = encoder.finish().unwrap();
+
+        assert_eq!(Compression::from_magic(&compressed), Compression::Xz);
+        assert_eq!(read_to_string(&compressed), content);
+    }
+
+    #[test]
+    fn short_reads() {
+        // e.g. a network stream handing out one byte at a time
+        struct Trickle<'a>(&'a [u8]);
+        impl Read for Trickle<'_> {
+            fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+                let n = self.0.len().min(buf.len()).min(1);
+                buf[..n].copy_from_slice(&self.0[..n]);
+                self.0 = &self.0[n..];
+                Ok(n)
+            }
+        }
+        let content = "ATOM      1  N   ALA A   1      11.104   6.134  -6.504\n";
+        let mut encoder = xz2::write::XzEncoder::new(Vec::new(), 6);
+        encoder.write_all(content.as_bytes()).unwrap();
+        let compressed = encoder.finish().unwrap();
+
+        let mut decompressed = String::new();
+        decompress(Trickle(&compressed))
+            .unwrap()
+            .read_to_string(&mut decompressed)
+            .unwrap();
+        assert_eq!(decompressed, content);
+
+        let mut passed = String::new();
+        decompress(Trickle(b"HE"))
+            .unwrap()
+            .read_to_string(&mut passed)
+            .unwrap();
+        assert_eq!(passed, "HE");
+    }
+
+    #[test]
+    fn temp_file() {
+        let temp_file = TempFile::new("HEADER\n".as_bytes(), "pdb").unwrap();
+        let path = temp_file.path().to_string();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "HEADER\n");
+        drop(temp_file);
+        assert!(!std::path::Path::new(&path).exists());
+    }
+
+    #[test]
+    fn temp_file_removed_on_error() {
+        struct Failing;
+        impl Read for Failing {
+            fn read(&mut self, _: &mut [u8]) -> std::io::Result<usize> {
+                Err(std::io::Error::other("broken"))
+            }
+        }
+        let count = || {
+            std::fs::read_dir(std::env::temp_dir())
+                .unwrap()
+                .filter_map(Result::ok)
+                .filter(|entry| entry.file_name().to_string_lossy().ends_with(".failing"))
+                .count()
+        };
+        let before = count();
+        assert!(TempFile::new(Failing, "failing").is_err());
+        assert_eq!(count(), before);
+    }
+
+    #[test]
+    fn uncompressed() {
+        let content = "HEADER    PLAIN TEXT\n";
+        assert_eq!(
+            Compression::from_magic(content.as_bytes()),
+            Compression::None
+        );
+        assert_eq!(read_to_string(content.as_bytes()), content);
+    }
+}
@@ -0,0 +1,328 @@
+use crate::error::MoguraIoError;
+use crate::structure_data::StructureData;
+
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::prelude::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum FetchSource {
+    /// legacy PDB format, not available for large entries
+    RcsbPdb,
+    #[default]
+    RcsbMmcif,
+    Pdbe,
+    /// id is a UniProt accession, e.g. P69905
+    AlphaFold,
+}
+
+impl FetchSource {
+    pub const ALL: [FetchSource; 4] = [
+        FetchSource::RcsbPdb,
+        FetchSource::RcsbMmcif,
+        FetchSource::Pdbe,
+        FetchSource::AlphaFold,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            FetchSource::RcsbPdb => "RCSB (PDB)",
+            FetchSource::RcsbMmcif => "RCSB (mmCIF)",
+            FetchSource::Pdbe => "PDBe",
+            FetchSource::AlphaFold => "AlphaFold DB",
+        }
+    }
+
+    pub fn default_base_url(&self) -> &'static str {
+        match self {
+            FetchSource::RcsbPdb | FetchSource::RcsbMmcif => "https://files.rcsb.org/download",
+            FetchSource::Pdbe => "https://www.ebi.ac.uk/pdbe/entry-files/download",
+            FetchSource::AlphaFold => "https://alphafold.ebi.ac.uk/files",
+        }
+    }
+
+    /// File name on the server, also used as the cache key.
+    pub fn file_name(&self, id: &str) -> String {
+        match self {
+            FetchSource::RcsbPdb => format!("{}.pdb", id.to_ascii_uppercase()),
+            FetchSource::RcsbMmcif => format!("{}.cif", id.to_ascii_uppercase()),
+            FetchSource::Pdbe => format!("{}_updated.cif", id.to_ascii_lowercase()),
+            FetchSource::AlphaFold => {
+                format!("AF-{}-F1-model_v4.cif", id.to_ascii_uppercase())
+            }
+        }
+    }
+
+    fn cache_dir_name(&self) -> &'static str {
+        match self {
+            FetchSource::RcsbPdb | FetchSource::RcsbMmcif => "rcsb",
+            FetchSource::Pdbe => "pdbe",
+            FetchSource::AlphaFold => "alphafold",
+        }
+    }
+}
+
+/// Downloads structures from a [`FetchSource`].
+/// Downloaded files are kept in the cache directory, so the same id can be loaded offline.
+#[derive(Debug, Clone)]
+pub struct Fetcher {
+    source: FetchSource,
+    base_url: String,
+    cache_dir: Option<std::path::PathBuf>,
+}
+
+impl Fetcher {
+    pub fn new(source: FetchSource) -> Self {
+        Self {
+            source,
+            base_url: source.default_base_url().to_string(),
+            cache_dir: default_cache_dir(),
+        }
+    }
+
+    /// e.g. a local mirror or a test server
+    pub fn with_base_url(mut self, base_url: &str) -> Self {
+        self.base_url = base_url.trim_end_matches('/').to_string();
+        self
+    }
+
+    /// `None` disables the cache.
+    pub fn with_cache_dir(mut self, cache_dir: Option<std::path::PathBuf>) -> Self {
+        self.cache_dir = cache_dir;
+        self
+    }
+
+    pub fn source(&self) -> FetchSource {
+        self.source
+    }
+
+    pub fn url(&self, id: &str) -> String {
+        format!("{}/{}", self.base_url, self.source.file_name(id))
+    }
+
+    pub fn cache_path(&self, id: &str) -> Option<std::path::PathBuf> {
+        self.cache_dir.as_ref().map(|cache_dir| {
+            cache_dir
+                .join(self.source.cache_dir_name())
+                .join(self.source.file_name(id))
+        })
+    }
+
+    pub async fn fetch(&self, id: &str) -> Result<Box<dyn StructureData>, MoguraIoError> {
+        let content = self.fetch_bytes(id).await?;
+        crate::structure_data::structure_loader_from_content(
+            &content,
+            Some(&self.source.file_name(id)),
+        )
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub async fn fetch_bytes(&self, id: &str) -> Result<Vec<u8>, MoguraIoError> {
+        let id = validate_id(id)?;
+        let cache_path = self.cache_path(id);
+        if let Some(cache_path) = &cache_path {
+            if let Ok(content) = std::fs::read(cache_path) {
+                return Ok(content);
+            }
+        }
+
+        let url = self.url(id);
+        let download_error = |message: String| MoguraIoError::Download {
+            url: url.clone(),
+            message,
+        };
+        let response = reqwest::blocking::Client::new()
+            .get(&url)
+            .send()
+            .map_err(|e| download_error(e.to_string()))?;
+        let status_code = response.status().as_u16();
+        if status_code != 200 {
+            return Err(download_error(format!("status code {}", status_code)));
+        }
+        let content = response
+            .bytes()
+            .map_err(|e| download_error(e.to_string()))?
+            .to_vec();
+
+        // failing to cache should not fail the download
+        if let Some(cache_path) = &cache_path {
+            let _ = write_cache(cache_path, &content);
+        }
+        Ok(content)
+    }
+
+    // no disk cache on WASM, the browser caches responses
+    #[cfg(target_arch = "wasm32")]
+    pub async fn fetch_bytes(&self, id: &str) -> Result<Vec<u8>, MoguraIoError> {
+        let id = validate_id(id)?;
+        let url = self.url(id);
+        let download_error = |message: &str| MoguraIoError::Download {
+            url: url.clone(),
+            message: message.to_string(),
+        };
+        let mut opts = web_sys::RequestInit::new();
+        opts.method("GET");
+        opts.mode(web_sys::RequestMode::Cors);
+        let request = web_sys::Request::new_with_str_and_init(&url, &opts)
+            .map_err(|_| download_error("failed to create request"))?;
+        let window = gloo::utils::window();
+        let resp_value = wasm_bindgen_futures::JsFuture::from(window.fetch_with_request(&request))
+            .await
+            .map_err(|_| download_error("failed to fetch"))?;
+        let resp: web_sys::Response = resp_value
+            .dyn_into()
+            .map_err(|_| download_error("failed to get response"))?;
+        if resp.status() != 200 {
+            return Err(download_error(&format!("status code {}", resp.status())));
+        }
+        let text = wasm_bindgen_futures::JsFuture::from(
+            resp.text()
+                .map_err(|_| download_error("failed to get text"))?,
+        )
+        .await
+        .map_err(|_| download_error("failed to get text"))?;
+
+        match text.as_string() {
+            Some(content) => Ok(content.into_bytes()),
+            None => Err(download_error("failed to get content")),
+        }
+    }
+}
+
+// ids become file names in the cache, so only allow plain identifiers
+fn validate_id(id: &str) -> Result<&str, MoguraIoError> {
+    let id = id.trim();
+    if !id.is_empty() && id.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        Ok(id)
+    } else {
+        Err(MoguraIoError::Download {
+            url: id.to_string(),
+            message: "invalid id".to_string(),
+        })
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn write_cache(cache_path: &std::path::Path, content: &[u8]) -> Result<(), std::io::Error> {
+    if let Some(parent) = cache_path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    // write then rename, so a half written file is never read as a cache hit
+    let temp_path = cache_path.with_extension(format!("tmp{}", std::process::id()));
+    std::fs::write(&temp_path, content)?;
+    std::fs::rename(&temp_path, cache_path)
+}
+
+/// `$MOGURA_CACHE_DIR`, `$XDG_CACHE_HOME/mogura` or `~/.cache/mogura`
+pub fn default_cache_dir() -> Option<std::path::PathBuf> {
+    if cfg!(target_arch = "wasm32") {
+        return None;
+    }
+    let non_empty = |key: &str| std::env::var_os(key).filter(|value| !value.is_empty());
+    if let Some(dir) = non_empty("MOGURA_CACHE_DIR") {
+        return Some(dir.into());
+    }
+    if let Some(dir) = non_empty("XDG_CACHE_HOME") {
+        return Some(std::path::PathBuf::from(dir).join("mogura"));
+    }
+    non_empty("HOME").map(|home| std::path::PathBuf::from(home).join(".cache").join("mogura"))
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use crate::input::fetch::*;
+    use std::io::{BufRead, Write};
+
+    // serves `body` for `n` requests and returns the requested paths
+    fn serve(body: &'static str, n: usize) -> (String, std::thread::JoinHandle<Vec<String>>) {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let handle = std::thread::spawn(move || {
+            let mut paths = Vec::new();
+            for stream in listener.incoming().take(n) {
+                let mut stream = stream.unwrap();
+                let mut reader = std::io::BufReader::new(stream.try_clone().unwrap());
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).unwrap();
+                // skip headers
+                let mut line = String::new();
+                while reader.read_line(&mut line).unwrap() > 2 {
+                    line.clear();
+                }
+                paths.push(request_line.split_whitespace().nth(1).unwrap().to_string());
+                write!(
+                    stream,
+                    "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                )
+                .unwrap();
+            }
+            paths
+        });
+        (base_url, handle)
+    }
+
+    fn temp_cache_dir(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("mogura-test-{}-{}", std::process::id(), name))
+    }
+
+    #[test]
+    fn file_name() {
+        assert_eq!(FetchSource::RcsbPdb.file_name("8gng"), "8GNG.pdb");
+        assert_eq!(FetchSource::RcsbMmcif.file_name("8gng"), "8GNG.cif");
+        assert_eq!(FetchSource::Pdbe.file_name("8GNG"), "8gng_updated.cif");
+        assert_eq!(
+            FetchSource::AlphaFold.file_name("p69905"),
+            "AF-P69905-F1-model_v4.cif"
+        );
+        assert!(validate_id("../etc/passwd").is_err());
+        assert!(validate_id(" 8GNG ").is_ok());
+    }
+
+    #[test]
+    fn fetch_and_cache() {
+        let body = "data_8GNG\n";
+        let (base_url, handle) = serve(body, 1);
+        let cache_dir = temp_cache_dir("fetch");
+        let fetcher = Fetcher::new(FetchSource::RcsbMmcif)
+            .with_base_url(&base_url)
+            .with_cache_dir(Some(cache_dir.clone()));
+
+        let content = block_on(fetcher.fetch_bytes("8gng")).unwrap();
+        assert_eq!(content, body.as_bytes());
+        assert_eq!(handle.join().unwrap(), vec!["/8GNG.cif".to_string()]);
+
+        // the server is gone, so this must come from the cache
+        let content = block_on(fetcher.fetch_bytes("8GNG")).unwrap();
+        assert_eq!(content, body.as_bytes());
+        assert!(fetcher.cache_path("8GNG").unwrap().exists());
+
+        let _ = std::fs::remove_dir_all(cache_dir);
+    }
+
+    #[test]
+    fn fetch_without_cache() {
+        let (base_url, handle) = serve("data_P69905\n", 2);
+        let fetcher = Fetcher::new(FetchSource::AlphaFold)
+            .with_base_url(&base_url)
+            .with_cache_dir(None);
+        for _ in 0..2 {
+            assert!(block_on(fetcher.fetch_bytes("P69905")).is_ok());
+        }
+        assert_eq!(
+            handle.join().unwrap(),
+            vec!["/AF-P69905-F1-model_v4.cif".to_string(); 2]
+        );
+    }
+
+    // fetch_bytes never awaits on native, so polling once is enough
+    fn block_on<F: std::future::Future>(future: F) -> F::Output {
+        let waker = std::task::Waker::noop();
+        let mut context = std::task::Context::from_waker(waker);
+        let mut future = std::pin::pin!(future);
+        match future.as_mut().poll(&mut context) {
+            std::task::Poll::Ready(output) => output,
+            std::task::Poll::Pending => panic!("fetch_bytes should not be pending on native"),
+        }
+    }
+}
@@ -1 +1,3 @@
-
+pub mod compression;
+pub mod fetch;
+pub mod registry;
@@ -0,0 +1,510 @@
+use crate::error::{Location, MoguraIoError};
+use crate::input::compression::{decompress, split_extension};
+use crate::structure_data::StructureData;
+use crate::structure_data::pdb::PDBData;
+use crate::trajectory_data::TrajectoryData;
+use std::io::{BufRead, Read};
+
+#[cfg(feature = "groan_rs")]
+use crate::structure_data::gro::GroData;
+#[cfg(feature = "groan_rs")]
+use crate::trajectory_data::xtc::XtcData;
+
+pub type Sniffer = fn(&[u8]) -> bool;
+pub type StructureLoader =
+    Box<dyn Fn(&[u8]) -> Result<Box<dyn StructureData>, MoguraIoError> + Send + Sync>;
+pub type TrajectoryLoader = Box<
+    dyn Fn(&dyn StructureData, &mut dyn Read) -> Result<Box<dyn TrajectoryData>, MoguraIoError>
+        + Send
+        + Sync,
+>;
+pub type TrajectoryPathLoader =
+    Box<dyn Fn(&str, &str) -> Result<Box<dyn TrajectoryData>, MoguraIoError> + Send + Sync>;
+
+// number of leading bytes handed to sniffers
+const SNIFF_LENGTH: usize = 4096;
+
+pub struct StructureFormat {
+    name: String,
+    extensions: Vec<String>,
+    sniffer: Option<Sniffer>,
+    loader: StructureLoader,
+}
+
+impl StructureFormat {
+    pub fn new<F>(name: &str, extensions: &[&str], loader: F) -> Self
+    where
+        F: Fn(&[u8]) -> Result<Box<dyn StructureData>, MoguraIoError> + Send + Sync + 'static,
+    {
+        Self {
+            name: name.to_string(),
+            extensions: extensions
+                .iter()
+                .map(|ext| ext.to_ascii_lowercase())
+                .collect(),
+            sniffer: None,
+            loader: Box::new(loader),
+        }
+    }
+
+    pub fn with_sniffer(mut self, sniffer: Sniffer) -> Self {
+        self.sniffer = Some(sniffer);
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn extensions(&self) -> &[String] {
+        &self.extensions
+    }
+
+    pub fn sniff(&self, content: &[u8]) -> bool {
+        self.sniffer
+            .is_some_and(|sniffer| sniffer(&content[..content.len().min(SNIFF_LENGTH)]))
+    }
+
+    pub fn load(&self, content: &[u8]) -> Result<Box<dyn StructureData>, MoguraIoError> {
+        (self.loader)(content)
+    }
+}
+
+pub struct TrajectoryFormat {
+    name: String,
+    extensions: Vec<String>,
+    sniffer: Option<Sniffer>,
+    loader: TrajectoryLoader,
+    path_loader: Option<TrajectoryPathLoader>,
+}
+
+impl TrajectoryFormat {
+    pub fn new<F>(name: &str, extensions: &[&str], loader: F) -> Self
+    where
+        F: Fn(&dyn StructureData, &mut dyn Read) -> Result<Box<dyn TrajectoryData>, MoguraIoError>
+            + Send
+            + Sync
+            + 'static,
+    {
+        Self {
+            name: name.to_string(),
+            extensions: extensions
+                .iter()
+                .map(|ext| ext.to_ascii_lowercase())
+                .collect(),
+            sniffer: None,
+            loader: Box::new(loader),
+            path_loader: None,
+        }
+    }
+
+    pub fn with_sniffer(mut self, sniffer: Sniffer) -> Self {
+        self.sniffer = Some(sniffer);
+        self
+    }
+
+    /// Used instead of the reader when both files are on disk,
+    /// e.g. to avoid copying large trajectories.
+    pub fn with_path_loader<F>(mut self, path_loader: F) -> Self
+    where
+        F: Fn(&str, &str) -> Result<Box<dyn TrajectoryData>, MoguraIoError> + Send + Sync + 'static,
+    {
+        self.path_loader = Some(Box::new(path_loader));
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn extensions(&self) -> &[String] {
+        &self.extensions
+    }
+
+    pub fn sniff(&self, content: &[u8]) -> bool {
+        self.sniffer
+            .is_some_and(|sniffer| sniffer(&content[..content.len().min(SNIFF_LENGTH)]))
+    }
+
+    pub fn load(
+        &self,
+        topology: &dyn StructureData,
+        reader: &mut dyn Read,
+    ) -> Result<Box<dyn TrajectoryData>, MoguraIoError> {
+        (self.loader)(topology, reader)
+    }
+}
+
+/// Maps extensions and content sniffers to readers.
+/// Formats registered later take precedence over earlier ones,
+/// so downstream crates can override the built-in readers.
+#[derive(Default)]
+pub struct FormatRegistry {
+    structure_formats: Vec<StructureFormat>,
+    trajectory_formats: Vec<TrajectoryFormat>,
+}
+
+impl FormatRegistry {
+    /// Registry with the readers shipped in mogura-io.
+    pub fn builtin() -> Self {
+        let mut registry = Self::default();
+
+        registry.register_structure(
+            StructureFormat::new("PDB", &["pdb", "ent"], |content| {
+                Ok(Box::new(PDBData::load_from_content(to_str(
+                    content, "PDB",
+                )?)?))
+            })
+            .with_sniffer(PDBData::sniff),
+        );
+        registry.register_structure(
+            StructureFormat::new("mmCIF", &["cif", "mmcif"], |content| {
+                Ok(Box::new(PDBData::load_mmcif_from_content(to_str(
+                    content, "mmCIF",
+                )?)?))
+            })
+            .with_sniffer(PDBData::sniff_mmcif),
+        );
+
+        #[cfg(feature = "groan_rs")]
+        {
+            registry.register_structure(
+                StructureFormat::new("GRO", &["gro"], |content| {
+                    Ok(Box::new(GroData::from_bytes(content)?))
+                })
+                .with_sniffer(GroData::sniff),
+            );
+            registry.register_trajectory(
+                TrajectoryFormat::new("XTC", &["xtc"], |topology, reader| {
+                    Ok(Box::new(XtcData::from_reader(topology, reader)?))
+                })
+                .with_path_loader(|topology_file, trajectory_file| {
+                    Ok(Box::new(XtcData::load(topology_file, trajectory_file)?))
+                })
+                .with_sniffer(XtcData::sniff),
+            );
+        }
+
+        registry
+    }
+
+    /// Process-wide registry used by `structure_loader` and `trajectory_loader`.
+    pub fn global() -> &'static std::sync::RwLock<FormatRegistry> {
+        static REGISTRY: std::sync::OnceLock<std::sync::RwLock<FormatRegistry>> =
+            std::sync::OnceLock::new();
+        REGISTRY.get_or_init(|| std::sync::RwLock::new(FormatRegistry::builtin()))
+    }
+
+    pub fn register_structure(&mut self, format: StructureFormat) {
+        self.structure_formats.push(format);
+    }
+
+    pub fn register_trajectory(&mut self, format: TrajectoryFormat) {
+        self.trajectory_formats.push(format);
+    }
+
+    pub fn structure_formats(&self) -> impl Iterator<Item = &StructureFormat> {
+        self.structure_formats.iter().rev()
+    }
+
+    pub fn trajectory_formats(&self) -> impl Iterator<Item = &TrajectoryFormat> {
+        self.trajectory_formats.iter().rev()
+    }
+
+    /// Finds a format by extension first, then by sniffing the content.
+    pub fn find_structure_format(
+        &self,
+        extension: Option<&str>,
+        content: &[u8],
+    ) -> Option<&StructureFormat> {
+        extension
+            .and_then(|extension| {
+                self.structure_formats()
+                    .find(|format| format.extensions.iter().any(|ext| ext == extension))
+            })
+            .or_else(|| {
+                self.structure_formats()
+                    .find(|format| format.sniff(content))
+            })
+    }
+
+    pub fn find_trajectory_format(
+        &self,
+        extension: Option<&str>,
+        content: &[u8],
+    ) -> Option<&TrajectoryFormat> {
+        extension
+            .and_then(|extension| {
+                self.trajectory_formats()
+                    .find(|format| format.extensions.iter().any(|ext| ext == extension))
+            })
+            .or_else(|| {
+                self.trajectory_formats()
+                    .find(|format| format.sniff(content))
+            })
+    }
+
+    pub fn load_structure(
+        &self,
+        structure_file: &str,
+    ) -> Result<Box<dyn StructureData>, MoguraIoError> {
+        let file = std::fs::File::open(structure_file)
+            .map_err(|e| MoguraIoError::io(structure_file, e))?;
+        self.load_structure_from_reader(file, Some(structure_file))
+    }
+
+    pub fn load_structure_from_reader<R: Read>(
+        &self,
+        reader: R,
+        file_name: Option<&str>,
+    ) -> Result<Box<dyn StructureData>, MoguraIoError> {
+        let mut content = Vec::new();
+        decompress(reader)
+            .and_then(|mut reader| reader.read_to_end(&mut content))
+            .map_err(|e| MoguraIoError::from(e).with_file(file_name))?;
+        self.load_decompressed_structure(&content, file_name)
+    }
+
+    /// `file_name` is optional and only used for its extension.
+    /// Without it, the format is detected from the content.
+    pub fn load_structure_from_content(
+        &self,
+        content: &[u8],
+        file_name: Option<&str>,
+    ) -> Result<Box<dyn StructureData>, MoguraIoError> {
+        self.load_structure_from_reader(content, file_name)
+    }
+
+    // the formats only see decompressed content
+    fn load_decompressed_structure(
+        &self,
+        content: &[u8],
+        file_name: Option<&str>,
+    ) -> Result<Box<dyn StructureData>, MoguraIoError> {
+        let extension = file_name.and_then(|file_name| split_extension(file_name).0);
+        match self.find_structure_format(extension.as_deref(), content) {
+            Some(format) => format.load(content).map_err(|e| e.with_file(file_name)),
+            None => Err(unsupported(file_name)),
+        }
+    }
+
+    pub fn load_trajectory(
+        &self,
+        topology_file: &str,
+        trajectory_file: &str,
+    ) -> Result<Box<dyn TrajectoryData>, MoguraIoError> {
+        let io_error = |e| MoguraIoError::io(trajectory_file, e);
+        let file = std::fs::File::open(trajectory_file).map_err(io_error)?;
+        let mut reader =
+            std::io::BufReader::with_capacity(SNIFF_LENGTH, decompress(file).map_err(io_error)?);
+        let extension = split_extension(trajectory_file).0;
+        let format = self
+            .find_trajectory_format(extension.as_deref(), reader.fill_buf().map_err(io_error)?)
+            .ok_or_else(|| unsupported(Some(trajectory_file)))?;
+        match &format.path_loader {
+            Some(path_loader) => path_loader(topology_file, trajectory_file),
+            None => {
+                let topology = self.load_structure(topology_file)?;
+                format.load(topology.as_ref(), &mut reader)
+            }
+        }
+        .map_err(|e| e.with_file(Some(trajectory_file)))
+    }
+
+    /// `file_name` is optional and only used for its extension.
+    /// Without it, the format is detected from the content.
+    pub fn load_trajectory_from_reader<R: Read>(
+        &self,
+        topology: &dyn StructureData,
+        reader: R,
+        file_name: Option<&str>,
+    ) -> Result<Box<dyn TrajectoryData>, MoguraIoError> {
+        let io_error = |e| MoguraIoError::from(e).with_file(file_name);
+        let mut reader =
+            std::io::BufReader::with_capacity(SNIFF_LENGTH, decompress(reader).map_err(io_error)?);
+        let extension = file_name.and_then(|file_name| split_extension(file_name).0);
+        match self
+            .find_trajectory_format(extension.as_deref(), reader.fill_buf().map_err(io_error)?)
+        {
+            Some(format) => format
+                .load(topology, &mut reader)
+                .map_err(|e| e.with_file(file_name)),
+            None => Err(unsupported(file_name)),
+        }
+    }
+}
+
+fn to_str<'a>(content: &'a [u8], format: &str) -> Result<&'a str, MoguraIoError> {
+    std::str::from_utf8(content).map_err(|e| {
+        let valid = &content[..e.valid_up_to()];
+        let line = valid.iter().filter(|&&byte| byte == b'\n').count() + 1;
+        let column = valid.len()
+            - valid
+                .iter()
+                .rposition(|&byte| byte == b'\n')
+                .map_or(0, |i| i + 1)
+            + 1;
+        MoguraIoError::parse(format, Location::line(line, Some(column)), "invalid UTF-8")
+    })
+}
+
+fn unsupported(file_name: Option<&str>) -> MoguraIoError {
+    MoguraIoError::UnsupportedFormat {
+        location: Location {
+            file: file_name.map(|file_name| file_name.to_string()),
+            ..Default::default()
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::input::registry::*;
+
+    const PDB: &str = "\
+HEADER    TEST
+ATOM      1  N   ALA A   1      11.104   6.134  -6.504  1.00  0.00           N
+END
+";
+
+    const MMCIF: &str = "\
+# comment
+data_1ABC
+_entry.id 1ABC
+";
+
+    const GRO: &str = "\
+test
+    1
+    1ALA      N    1   1.110   0.613  -0.650
+   1.00000   1.00000   1.00000
+";
+
+    #[test]
+    fn sniff() {
+        assert!(PDBData::sniff(PDB.as_bytes()));
+        assert!(!PDBData::sniff(MMCIF.as_bytes()));
+        assert!(PDBData::sniff_mmcif(MMCIF.as_bytes()));
+        assert!(!PDBData::sniff_mmcif(PDB.as_bytes()));
+        assert!(crate::structure_data::gro::GroData::sniff(GRO.as_bytes()));
+        assert!(!crate::structure_data::gro::GroData::sniff(PDB.as_bytes()));
+        assert!(crate::trajectory_data::xtc::XtcData::sniff(&[
+            0, 0, 0x07, 0xcb, 0, 0, 0, 1
+        ]));
+    }
+
+    #[test]
+    fn find_format() {
+        let registry = FormatRegistry::builtin();
+        let format = registry.find_structure_format(Some("pdb"), MMCIF.as_bytes());
+        assert_eq!(format.map(|format| format.name()), Some("PDB"));
+        let format = registry.find_structure_format(None, MMCIF.as_bytes());
+        assert_eq!(format.map(|format| format.name()), Some("mmCIF"));
+        let format = registry.find_structure_format(Some("txt"), PDB.as_bytes());
+        assert_eq!(format.map(|format| format.name()), Some("PDB"));
+        assert!(registry.find_structure_format(None, b"hello").is_none());
+    }
+
+    struct Empty(
+        Vec<crate::structure_data::Atom>,
+        Vec<crate::structure_data::Residue>,
+    );
+
+    impl StructureData for Empty {
+        fn from_reader<R: Read>(_reader: R) -> Result<Self, MoguraIoError> {
+            Ok(Self(Vec::new(), Vec::new()))
+        }
+
+        fn atoms(&self) -> &Vec<crate::structure_data::Atom> {
+            &self.0
+        }
+
+        fn residues(&self) -> &Vec<crate::structure_data::Residue> {
+            &self.1
+        }
+    }
+
+    struct Positions(Vec<crate::trajectory_data::Frame>);
+
+    impl TrajectoryData for Positions {
+        fn frames(&self) -> &Vec<crate::trajectory_data::Frame> {
+            &self.0
+        }
+
+        // one frame per line, "x y z" for each atom
+        fn from_reader<R: Read>(
+            topology: &dyn StructureData,
+            reader: R,
+        ) -> Result<Self, MoguraIoError> {
+            let mut frames = Vec::new();
+            for (frame_id, line) in std::io::BufReader::new(reader).lines().enumerate() {
+                let values = line?
+                    .split_whitespace()
+                    .map(|value| value.parse::<f32>())
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(MoguraIoError::other)?;
+                let positions: Vec<[f32; 3]> = values
+                    .chunks_exact(3)
+                    .map(|xyz| [xyz[0], xyz[1], xyz[2]])
+                    .collect();
+                if positions.len() != topology.atoms().len() {
+                    return Err(MoguraIoError::other("number of atoms does not match"));
+                }
+                frames.push(crate::trajectory_data::Frame::new(frame_id, positions));
+            }
+            Ok(Self(frames))
+        }
+    }
+
+    #[test]
+    fn trajectory_from_reader() {
+        let mut registry = FormatRegistry::builtin();
+        registry.register_trajectory(TrajectoryFormat::new(
+            "positions",
+            &["pos"],
+            |topology, reader| Ok(Box::new(Positions::from_reader(topology, reader)?)),
+        ));
+
+        let topology = Empty::from_bytes(&[]).unwrap();
+        let trajectory = registry
+            .load_trajectory_from_reader(&topology, "\n\n".as_bytes(), Some("traj.pos"))
+            .unwrap();
+        assert_eq!(trajectory.n_frame(), 2);
+        assert!(
+            registry
+                .load_trajectory_from_reader(&topology, "\n".as_bytes(), None)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn error_location() {
+        let registry = FormatRegistry::builtin();
+        let error = registry
+            .load_structure_from_content(b"HEADER\nATOM \xff", Some("broken.pdb"))
+            .err()
+            .unwrap();
+        assert_eq!(
+            error.location(),
+            Some(&Location {
+                file: Some("broken.pdb".to_string()),
+                line: Some(2),
+                column: Some(6),
+            })
+        );
+        assert!(matches!(
+            registry.load_structure_from_content(b"hello", Some("hello.txt")),
+            Err(MoguraIoError::UnsupportedFormat { .. })
+        ));
+    }
+
+    #[test]
+    fn override_format() {
+        let mut registry = FormatRegistry::builtin();
+        registry.register_structure(StructureFormat::new("custom", &["pdb"], |_| {
+            Err(MoguraIoError::other("custom"))
+        }));
+        let format = registry.find_structure_format(Some("pdb"), PDB.as_bytes());
+        assert_eq!(format.map(|format| format.name()), Some("custom"));
+    }
+}
@@ -1,13 +1,26 @@
+mod error;
 mod input;
+mod selection;
 mod structure_data;
 mod trajectory_data;
 
 pub mod prelude {
+    pub use crate::error::{Diagnostic, Location, MoguraIoError, Severity};
+    pub use crate::input::compression::{Compression, decompress, split_extension};
+    pub use crate::input::fetch::{FetchSource, Fetcher, default_cache_dir};
+    pub use crate::input::registry::{FormatRegistry, StructureFormat, TrajectoryFormat};
+    pub use crate::selection::SelectionContext;
     pub use crate::structure_data::gro::GroData;
     pub use crate::structure_data::pdb::PDBData;
+    pub use crate::structure_data::residue_class::ResidueClass;
+    pub use crate::structure_data::secondary_structure::SecondaryStructure;
     pub use crate::structure_data::{
-        Atom, Element, GENERAL_BOND_CUTOFF, StructureData, structure_loader,
-        structure_loader_from_content,
+        Atom, Element, GENERAL_BOND_CUTOFF, Residue, StructureData, structure_loader,
+        structure_loader_from_content, structure_loader_from_reader,
+    };
+    pub use crate::trajectory_data::xtc::XtcData;
+    pub use crate::trajectory_data::{
+        Frame, TrajectoryData, trajectory_loader, trajectory_loader_from_content,
+        trajectory_loader_from_reader,
     };
-    pub use crate::trajectory_data::{TrajectoryData, trajectory_loader};
 }
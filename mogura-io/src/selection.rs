@@ -0,0 +1,192 @@
+use crate::structure_data::{Atom, GENERAL_BOND_CUTOFF, pairs_within};
+
+/// Atoms of a structure for evaluating mogura_asl selections without a viewer.
+/// Positions of a trajectory frame and bonds can be given, otherwise those of the
+/// structure file and bonds by distance are used.
+pub struct SelectionContext<'a> {
+    atoms: &'a [Atom],
+    residue_indices: Vec<usize>,
+    positions: Option<&'a [[f32; 3]]>,
+    neighbors: std::cell::OnceCell<Vec<Vec<usize>>>,
+}
+
+impl<'a> SelectionContext<'a> {
+    pub fn new(atoms: &'a [Atom]) -> Self {
+        // consecutive atoms with the same model, chain, residue id and insertion code
+        let key = |atom: &'a Atom| {
+            (
+                atom.model_id(),
+                atom.chain_name(),
+                atom.residue_id(),
+                atom.insertion_code(),
+            )
+        };
+        let mut residue_indices = Vec::with_capacity(atoms.len());
+        for (i, atom) in atoms.iter().enumerate() {
+            let residue_index = match residue_indices.last() {
+                Some(&last) if key(&atoms[i - 1]) != key(atom) => last + 1,
+                Some(&last) => last,
+                None => 0,
+            };
+            residue_indices.push(residue_index);
+        }
+        Self {
+            atoms,
+            residue_indices,
+            positions: None,
+            neighbors: std::cell::OnceCell::new(),
+        }
+    }
+
+    /// indexed by atom id, e.g. Frame::positions
+    pub fn with_positions(mut self, positions: &'a [[f32; 3]]) -> Self {
+        self.positions = Some(positions);
+        self
+    }
+
+    /// pairs of atom indices, those out of range are skipped
+    pub fn with_bonds(self, bonds: &[(usize, usize)]) -> Self {
+        let n = self.atoms.len();
+        let mut neighbors = vec![Vec::new(); n];
+        for &(i, j) in bonds.iter().filter(|&&(i, j)| i < n && j < n) {
+            neighbors[i].push(j);
+            neighbors[j].push(i);
+        }
+        let _ = self.neighbors.set(neighbors);
+        self
+    }
+
+    fn bonds_by_distance(&self) -> Vec<Vec<usize>> {
+        let n = self.atoms.len();
+        let positions: Vec<[f32; 3]> = (0..n)
+            .map(|i| mogura_asl::AtomContext::position(self, i))
+            .collect();
+        let mut neighbors = vec![Vec::new(); n];
+        for (i, j) in pairs_within(&positions, GENERAL_BOND_CUTOFF) {
+            neighbors[i].push(j);
+            neighbors[j].push(i);
+        }
+        neighbors
+    }
+}
+
+impl mogura_asl::AtomContext for SelectionContext<'_> {
+    fn n_atoms(&self) -> usize {
+        self.atoms.len()
+    }
+    fn atom_name(&self, i: usize) -> &str {
+        self.atoms[i].atom_name()
+    }
+    fn residue_name(&self, i: usize) -> &str {
+        self.atoms[i].residue_name()
+    }
+    fn residue_id(&self, i: usize) -> isize {
+        self.atoms[i].residue_id()
+    }
+    fn residue_index(&self, i: usize) -> usize {
+        self.residue_indices[i]
+    }
+    fn chain_name(&self, i: usize) -> &str {
+        self.atoms[i].chain_name()
+    }
+    fn model_id(&self, i: usize) -> usize {
+        self.atoms[i].model_id()
+    }
+    fn segment_name(&self, i: usize) -> Option<&str> {
+        self.atoms[i].segment_name()
+    }
+    fn insertion_code(&self, i: usize) -> Option<&str> {
+        self.atoms[i].insertion_code()
+    }
+    fn alternative_location(&self, i: usize) -> Option<&str> {
+        self.atoms[i].alternative_location()
+    }
+    fn element(&self, i: usize) -> &str {
+        self.atoms[i].element_symbol()
+    }
+    fn is_hetero(&self, i: usize) -> bool {
+        self.atoms[i].is_hetero()
+    }
+    fn b_factor(&self, i: usize) -> f32 {
+        self.atoms[i].b_factor()
+    }
+    fn occupancy(&self, i: usize) -> f32 {
+        self.atoms[i].occupancy()
+    }
+    fn charge(&self, i: usize) -> f32 {
+        self.atoms[i].charge()
+    }
+    fn mass(&self, i: usize) -> f32 {
+        self.atoms[i].mass()
+    }
+    fn atom_id(&self, i: usize) -> usize {
+        self.atoms[i].atom_id()
+    }
+    fn position(&self, i: usize) -> [f32; 3] {
+        match self.positions {
+            Some(positions) => positions[self.atoms[i].id()],
+            None => self.atoms[i].xyz(),
+        }
+    }
+    fn neighbors(&self, i: usize) -> Vec<usize> {
+        self.neighbors.get_or_init(|| self.bonds_by_distance())[i].clone()
+    }
+    fn is_protein(&self, i: usize) -> bool {
+        self.atoms[i].is_protein()
+    }
+    fn is_water(&self, i: usize) -> bool {
+        self.atoms[i].is_water()
+    }
+    fn is_ion(&self, i: usize) -> bool {
+        self.atoms[i].is_ion()
+    }
+    fn is_backbone(&self, i: usize) -> bool {
+        self.atoms[i].is_backbone()
+    }
+    fn is_sidechain(&self, i: usize) -> bool {
+        self.atoms[i].is_sidechain()
+    }
+    fn is_nucleic(&self, i: usize) -> bool {
+        self.atoms[i].is_nucleic()
+    }
+    fn is_lipid(&self, i: usize) -> bool {
+        self.atoms[i].is_lipid()
+    }
+    fn is_sugar(&self, i: usize) -> bool {
+        self.atoms[i].is_sugar()
+    }
+    fn is_cofactor(&self, i: usize) -> bool {
+        self.atoms[i].is_cofactor()
+    }
+    fn is_ligand(&self, i: usize) -> bool {
+        self.atoms[i].is_ligand()
+    }
+    fn is_hydrophobic(&self, i: usize) -> bool {
+        self.atoms[i].is_hydrophobic()
+    }
+    fn is_acidic(&self, i: usize) -> bool {
+        self.atoms[i].is_acidic()
+    }
+    fn is_basic(&self, i: usize) -> bool {
+        self.atoms[i].is_basic()
+    }
+    fn is_polar(&self, i: usize) -> bool {
+        self.atoms[i].is_polar()
+    }
+    fn is_aromatic(&self, i: usize) -> bool {
+        self.atoms[i].is_aromatic()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::selection::*;
+    use mogura_asl::AtomContext;
+
+    #[test]
+    fn bonds_out_of_range() {
+        // e.g. bonds of a larger topology
+        let context = SelectionContext::new(&[]).with_bonds(&[(0, 1), (2, 3)]);
+        assert_eq!(context.n_atoms(), 0);
+    }
+}
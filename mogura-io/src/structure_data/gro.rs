@@ -1,6 +1,11 @@
 use crate::structure_data::*;
 use itertools::Itertools;
 
+#[cfg(feature = "groan_rs")]
+use crate::error::{Location, MoguraIoError};
+#[cfg(feature = "groan_rs")]
+use crate::input::compression::{LocalFile, TempFile};
+
 #[cfg(feature = "groan_rs")]
 use groan_rs::prelude::*;
 
@@ -10,10 +15,55 @@ pub struct GroData {
     residues: Vec<Residue>,
 }
 
+impl GroData {
+    /// title line, number of atoms, then fixed-width atom lines
+    pub fn sniff(content: &[u8]) -> bool {
+        let content = String::from_utf8_lossy(content);
+        let mut lines = content.lines().skip(1);
+        let n_atoms = match lines.next().map(|line| line.trim().parse::<usize>()) {
+            Some(Ok(n_atoms)) => n_atoms,
+            _ => return false,
+        };
+        if n_atoms == 0 {
+            return true;
+        }
+        lines.next().is_some_and(|line| {
+            (20..44).step_by(8).all(|start| {
+                line.get(start..start + 8)
+                    .is_some_and(|coord| coord.trim().parse::<f32>().is_ok())
+            })
+        })
+    }
+}
+
 #[cfg(feature = "groan_rs")]
 impl StructureData for GroData {
-    fn load(structure_file: &str) -> Result<Self, anyhow::Error> {
-        let system = System::from_file(structure_file).map_err(anyhow::Error::msg)?;
+    fn from_reader<R: std::io::Read>(reader: R) -> Result<Self, MoguraIoError> {
+        // groan_rs only reads from paths
+        let temp_file = TempFile::new(reader, "gro")?;
+        Self::load_system(temp_file.path())
+    }
+
+    fn load(structure_file: &str) -> Result<Self, MoguraIoError> {
+        let local_file = LocalFile::new(structure_file, "gro")
+            .map_err(|e| MoguraIoError::io(structure_file, e))?;
+        Self::load_system(local_file.path()).map_err(|e| e.with_file(Some(structure_file)))
+    }
+
+    fn atoms(&self) -> &Vec<crate::structure_data::Atom> {
+        &self.atoms
+    }
+
+    fn residues(&self) -> &Vec<Residue> {
+        &self.residues
+    }
+}
+
+#[cfg(feature = "groan_rs")]
+impl GroData {
+    fn load_system(gro_file: &str) -> Result<Self, MoguraIoError> {
+        let system = System::from_file(gro_file)
+            .map_err(|e| MoguraIoError::parse("GRO", Location::default(), &e.to_string()))?;
 
         let system_atoms = system.get_atoms_copy();
         let mut atoms = Vec::with_capacity(system_atoms.len());
@@ -28,6 +78,10 @@ impl StructureData for GroData {
                 },
                 residue_id: atom.get_residue_number() as isize,
                 residue_name: atom.get_residue_name().to_string(),
+                insertion_code: None,
+                alternative_location: None,
+                segment_name: None,
+                hetero: None,
                 atom_id: atom.get_atom_number(),
                 atom_name: atom.get_atom_name().to_string(),
                 element: Element::from_atom_name(atom.get_atom_name()),
@@ -35,6 +89,9 @@ impl StructureData for GroData {
                 x: atom.get_position().unwrap().x * 10.0,
                 y: atom.get_position().unwrap().y * 10.0,
                 z: atom.get_position().unwrap().z * 10.0,
+                b_factor: 0.,
+                occupancy: 1.,
+                charge: 0.,
             });
         }
 
@@ -52,17 +109,31 @@ impl StructureData for GroData {
                 residue_id: first_atom.residue_id,
                 residue_name: first_atom.residue_name.clone(),
                 atoms: group_atoms,
+                secondary_structure: None,
             });
         }
 
         Ok(Self { atoms, residues })
     }
+}
 
-    fn atoms(&self) -> &Vec<crate::structure_data::Atom> {
-        &self.atoms
-    }
+#[cfg(all(test, feature = "groan_rs"))]
+mod tests {
+    use crate::structure_data::gro::*;
 
-    fn residues(&self) -> &Vec<Residue> {
-        &self.residues
+    #[test]
+    fn ligand() {
+        // GRO has no HETATM records
+        let content = "\
+ligand in water
+    3
+    1ALA     CA    1   0.000   0.000   0.000
+    2LIG     C1    2   0.500   0.000   0.000
+    3SOL     OW    3   1.000   0.000   0.000
+   2.00000   2.00000   2.00000
+";
+        let gro = GroData::from_reader(content.as_bytes()).unwrap();
+        let ligand: Vec<_> = gro.atoms().iter().map(|atom| atom.is_ligand()).collect();
+        assert_eq!(ligand, vec![false, true, false]);
     }
 }
@@ -1,80 +1,105 @@
 pub mod gro;
 pub mod pdb;
-use crate::structure_data::gro::GroData;
-use crate::structure_data::pdb::PDBData;
+pub mod residue_class;
+pub mod secondary_structure;
+use crate::error::{Diagnostic, MoguraIoError};
+use crate::input::registry::FormatRegistry;
+use residue_class::ResidueClass;
+use secondary_structure::SecondaryStructure;
 
 pub const GENERAL_BOND_CUTOFF: f32 = 1.6; // angstrom
-pub(crate) const PROTEIN_RESNAME: [&str; 24] = [
-    "ALA", "ARG", "ASN", "ASP", "CYS", "GLN", "GLU", "GLY", "HIS", "ILE", "LEU", "LYS", "MET",
-    "PHE", "PRO", "SER", "THR", "TRP", "TYR", "VAL", "CYX", "HID", "HIE", "HIP",
-];
-// ACE
-// NME
-
-pub fn structure_loader(structure_file: &str) -> Result<Box<dyn StructureData>, anyhow::Error> {
-    let extension = std::path::Path::new(structure_file)
-        .extension()
-        .and_then(|ext| ext.to_str());
-    if let Some(extension) = extension {
-        match extension {
-            "pdb" => Ok(Box::new(PDBData::load(structure_file)?)),
-            "gro" => {
-                #[cfg(feature = "groan_rs")]
-                {
-                    Ok(Box::new(GroData::load(structure_file)?))
-                }
 
-                #[cfg(not(feature = "groan_rs"))]
-                {
-                    // unimplemented!("This extension is not supported.");
-                    // Err("This extension is not supported.".to_string())
-                    Err(anyhow::anyhow!("This extension is not supported."))
+/// `(i, j)` with `j < i` and the distance at most `cutoff`, ordered by `i` then `j`.
+// cell list of `cutoff` wide cells, only the 27 cells around an atom are searched
+pub(crate) fn pairs_within(positions: &[[f32; 3]], cutoff: f32) -> Vec<(usize, usize)> {
+    let cell = |position: &[f32; 3]| position.map(|x| (x / cutoff).floor() as i32);
+    let mut cells: std::collections::HashMap<[i32; 3], Vec<usize>> =
+        std::collections::HashMap::new();
+    for (i, position) in positions.iter().enumerate() {
+        cells.entry(cell(position)).or_default().push(i);
+    }
+    let distance = |a: &[f32; 3], b: &[f32; 3]| {
+        ((a[0] - b[0]).powi(2) + (a[1] - b[1]).powi(2) + (a[2] - b[2]).powi(2)).sqrt()
+    };
+    let mut pairs = Vec::new();
+    let mut neighbors = Vec::new();
+    for (i, position) in positions.iter().enumerate() {
+        let [cx, cy, cz] = cell(position);
+        neighbors.clear();
+        for dx in -1..=1 {
+            for dy in -1..=1 {
+                for dz in -1..=1 {
+                    let Some(others) = cells.get(&[cx + dx, cy + dy, cz + dz]) else {
+                        continue;
+                    };
+                    neighbors.extend(
+                        others
+                            .iter()
+                            .copied()
+                            .filter(|&j| j < i && distance(position, &positions[j]) <= cutoff),
+                    );
                 }
             }
-            _ => {
-                // unimplemented!("This extension is not supported.")
-                // Err("This extension is not supported.".to_string())
-                Err(anyhow::anyhow!("This extension is not supported."))
-            }
         }
-    } else {
-        // panic!("structure_file: {} has no extension.", structure_file);
-        // Err(format!(
-        //     "structure_file: {} has no extension.",
-        //     structure_file
-        // ))
-        Err(anyhow::anyhow!(format!(
-            "structure_file: {} has no extension.",
-            structure_file
-        )))
+        neighbors.sort_unstable();
+        pairs.extend(neighbors.iter().map(|&j| (i, j)));
     }
+    pairs
 }
 
+pub fn structure_loader(structure_file: &str) -> Result<Box<dyn StructureData>, MoguraIoError> {
+    FormatRegistry::global()
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .load_structure(structure_file)
+}
+
+/// `file_name` is only used for its extension, e.g. `1abc.pdb.gz`.
+/// When it is `None`, the format is detected from the content.
+/// Compressed content is detected by its magic bytes and decompressed.
 pub fn structure_loader_from_content(
-    content: &str,
-    extension: &str,
-) -> Result<Box<dyn StructureData>, anyhow::Error> {
-    match extension {
-        "pdb" => Ok(Box::new(PDBData::load_from_content(content)?)),
-        "gro" => {
-            // unimplemented!("gro is not supported for loading from content")
-            // Err("gro is not supported for loading from content".to_string())
-            Err(anyhow::anyhow!(
-                "gro is not supported for loading from content"
-            ))
-        }
-        _ => {
-            // unimplemented!("This extension is not supported.")
-            // Err("This extension is not supported.".to_string())
-            Err(anyhow::anyhow!("This extension is not supported."))
-        }
-    }
+    content: &[u8],
+    file_name: Option<&str>,
+) -> Result<Box<dyn StructureData>, MoguraIoError> {
+    FormatRegistry::global()
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .load_structure_from_content(content, file_name)
+}
+
+pub fn structure_loader_from_reader<R: std::io::Read>(
+    reader: R,
+    file_name: Option<&str>,
+) -> Result<Box<dyn StructureData>, MoguraIoError> {
+    FormatRegistry::global()
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .load_structure_from_reader(reader, file_name)
 }
 
 pub trait StructureData: Sync + Send {
-    fn load(structure_file: &str) -> Result<Self, anyhow::Error>
+    /// Uncompressed input, `structure_loader_from_reader` also takes compressed one.
+    fn from_reader<R: std::io::Read>(reader: R) -> Result<Self, MoguraIoError>
     where
         Self: Sized;
+    fn from_bytes(content: &[u8]) -> Result<Self, MoguraIoError>
+    where
+        Self: Sized,
+    {
+        Self::from_reader(content)
+    }
+    fn load(structure_file: &str) -> Result<Self, MoguraIoError>
+    where
+        Self: Sized,
+    {
+        let file = std::fs::File::open(structure_file)
+            .map_err(|e| MoguraIoError::io(structure_file, e))?;
+        Self::from_reader(file).map_err(|e| e.with_file(Some(structure_file)))
+    }
+    /// Non-fatal problems found while reading, e.g. pdbtbx warnings.
+    fn diagnostics(&self) -> &[Diagnostic] {
+        &[]
+    }
     // fn export(output_path: &str);
     fn atoms(&self) -> &Vec<Atom>;
     fn center(&self) -> [f32; 3] {
@@ -90,53 +115,20 @@ pub trait StructureData: Sync + Send {
         center
     }
     fn bonds_indirected(&self) -> Vec<(usize, usize)> {
-        let n = self.atoms().len();
-        let mut bonds = Vec::with_capacity(n * n);
-        let atoms = self.atoms();
-        for i in 0..n {
-            for j in 0..i {
-                if i == j {
-                    continue;
-                }
-                // TODO
-                // too heavy
-                // if (!atoms[i].is_protein() || !atoms[j].is_protein()) &&
-                //     (atoms[i].residue_name() != atoms[j].residue_name()) {
-                //     continue;
-                // }
-                if atoms[i].distance(&atoms[j]) <= GENERAL_BOND_CUTOFF {
-                    bonds.push((i, j));
-                }
-            }
-        }
-        bonds
+        let positions: Vec<[f32; 3]> = self.atoms().iter().map(Atom::xyz).collect();
+        pairs_within(&positions, GENERAL_BOND_CUTOFF)
     }
     fn bonds_directed(&self) -> Vec<(usize, usize)> {
-        let n = self.atoms().len();
-        let mut bonds = Vec::with_capacity(n * n);
-        let atoms = self.atoms();
-        for i in 0..n {
-            for j in 0..i {
-                if i == j {
-                    continue;
-                }
-                // if (!atoms[i].is_protein() || !atoms[j].is_protein()) &&
-                //     (atoms[i].residue_name() != atoms[j].residue_name()) {
-                //     continue;
-                // }
-                if atoms[i].distance(&atoms[j]) <= GENERAL_BOND_CUTOFF {
-                    bonds.push((i, j));
-                    bonds.push((j, i));
-                }
-            }
-        }
-        bonds
+        self.bonds_indirected()
+            .into_iter()
+            .flat_map(|(i, j)| [(i, j), (j, i)])
+            .collect()
     }
     fn protein(&self) -> Vec<Atom> {
         let atoms = self.atoms();
         let mut atoms_in_protein = Vec::with_capacity(atoms.len());
         for atom in atoms {
-            if PROTEIN_RESNAME.contains(&atom.residue_name()) {
+            if atom.is_protein() {
                 atoms_in_protein.push(atom.clone());
             }
         }
@@ -164,6 +156,7 @@ pub struct Residue {
     residue_id: isize,
     residue_name: String,
     atoms: Vec<Atom>,
+    secondary_structure: Option<SecondaryStructure>,
 }
 
 impl Residue {
@@ -173,6 +166,13 @@ impl Residue {
     pub fn residue_name(&self) -> &str {
         &self.residue_name
     }
+    pub fn chain_name(&self) -> &str {
+        &self.chain_name
+    }
+    /// from HELIX/SHEET records, `None` when the file has none
+    pub fn secondary_structure(&self) -> Option<SecondaryStructure> {
+        self.secondary_structure
+    }
 
     pub fn center(&self) -> [f32; 3] {
         let mut center = [0., 0., 0.];
@@ -201,18 +201,17 @@ impl Residue {
             atom_ha.clone(),
         ))
     }
+    pub fn residue_class(&self) -> ResidueClass {
+        ResidueClass::of(&self.residue_name)
+    }
     pub fn is_water(&self) -> bool {
-        self.residue_name == "HOH"
-            || self.residue_name == "WAT"
-            || self.residue_name.contains("TIP")
+        self.residue_class() == ResidueClass::Water
     }
     pub fn is_protein(&self) -> bool {
-        PROTEIN_RESNAME
-            .iter()
-            .any(|resname| self.residue_name == *resname)
+        self.residue_class() == ResidueClass::Protein
     }
     pub fn is_ion(&self) -> bool {
-        self.residue_name.contains("+") || self.residue_name.contains("-")
+        self.residue_class() == ResidueClass::Ion
     }
 }
 
@@ -223,13 +222,22 @@ pub struct Atom {
     chain_name: String,
     residue_id: isize,
     residue_name: String,
+    insertion_code: Option<String>,
+    alternative_location: Option<String>,
+    // segid of CHARMM style PDB, columns 73-76
+    segment_name: Option<String>,
+    // HETATM record, None for formats without one such as GRO
+    hetero: Option<bool>,
     atom_id: usize,
     atom_name: String,
     element: Option<Element>,
     x: f32, // angstrom
     y: f32, // angstrom
     z: f32, // angstrom
-            // charge: usize,
+    b_factor: f32,
+    occupancy: f32,
+    // formal charge
+    charge: f32,
 }
 
 impl Atom {
@@ -260,9 +268,40 @@ impl Atom {
     pub fn residue_id(&self) -> isize {
         self.residue_id
     }
+    pub fn insertion_code(&self) -> Option<&str> {
+        self.insertion_code.as_deref()
+    }
+    pub fn chain_name(&self) -> &str {
+        &self.chain_name
+    }
+    pub fn alternative_location(&self) -> Option<&str> {
+        self.alternative_location.as_deref()
+    }
+    pub fn segment_name(&self) -> Option<&str> {
+        self.segment_name.as_deref()
+    }
+    pub fn is_hetero(&self) -> bool {
+        self.hetero.unwrap_or(false)
+    }
+    pub fn model_id(&self) -> usize {
+        self.model_id
+    }
     pub fn element(&self) -> &Option<Element> {
         &self.element
     }
+    pub fn b_factor(&self) -> f32 {
+        self.b_factor
+    }
+    pub fn occupancy(&self) -> f32 {
+        self.occupancy
+    }
+    pub fn charge(&self) -> f32 {
+        self.charge
+    }
+    /// dalton, 0 when the element is unknown
+    pub fn mass(&self) -> f32 {
+        self.element.map(|element| element.mass()).unwrap_or(0.)
+    }
     pub fn distance2(&self, other: &Atom) -> f32 {
         let dx = self.x - other.x;
         let dy = self.y - other.y;
@@ -278,18 +317,59 @@ impl Atom {
             .map(|element| element.to_symbol())
             .unwrap_or("")
     }
+    pub fn residue_class(&self) -> ResidueClass {
+        ResidueClass::of(&self.residue_name)
+    }
     pub fn is_water(&self) -> bool {
-        self.residue_name == "HOH"
-            || self.residue_name == "WAT"
-            || self.residue_name.contains("TIP")
+        self.residue_class() == ResidueClass::Water
     }
     pub fn is_protein(&self) -> bool {
-        PROTEIN_RESNAME
-            .iter()
-            .any(|resname| self.residue_name == *resname)
+        self.residue_class() == ResidueClass::Protein
     }
     pub fn is_ion(&self) -> bool {
-        self.residue_name.contains("+") || self.residue_name.contains("-")
+        self.residue_class() == ResidueClass::Ion
+    }
+    pub fn is_nucleic(&self) -> bool {
+        self.residue_class() == ResidueClass::Nucleic
+    }
+    pub fn is_lipid(&self) -> bool {
+        self.residue_class() == ResidueClass::Lipid
+    }
+    pub fn is_sugar(&self) -> bool {
+        self.residue_class() == ResidueClass::Sugar
+    }
+    pub fn is_cofactor(&self) -> bool {
+        self.residue_class() == ResidueClass::Cofactor
+    }
+    /// HETATM other than water. Without HETATM records, anything that is
+    /// not protein, nucleic acid, lipid, water or ion.
+    pub fn is_ligand(&self) -> bool {
+        match self.hetero {
+            Some(hetero) => hetero && !self.is_water(),
+            None => !matches!(
+                self.residue_class(),
+                ResidueClass::Protein
+                    | ResidueClass::Nucleic
+                    | ResidueClass::Lipid
+                    | ResidueClass::Water
+                    | ResidueClass::Ion
+            ),
+        }
+    }
+    pub fn is_hydrophobic(&self) -> bool {
+        residue_class::is_hydrophobic(&self.residue_name)
+    }
+    pub fn is_acidic(&self) -> bool {
+        residue_class::is_acidic(&self.residue_name)
+    }
+    pub fn is_basic(&self) -> bool {
+        residue_class::is_basic(&self.residue_name)
+    }
+    pub fn is_polar(&self) -> bool {
+        residue_class::is_polar(&self.residue_name)
+    }
+    pub fn is_aromatic(&self) -> bool {
+        residue_class::is_aromatic(&self.residue_name)
     }
     pub fn is_backbone(&self) -> bool {
         if self.is_protein() {
@@ -688,10 +768,30 @@ impl Element {
     }
 
     pub fn to_symbol(self) -> &'static str {
-        ELEMENT_SYMBOLS[self as usize]
+        // atomic numbers start from 1
+        ELEMENT_SYMBOLS[self as usize - 1]
+    }
+
+    /// standard atomic weight in dalton, mass number of the most stable isotope for
+    /// elements without one
+    pub fn mass(self) -> f32 {
+        ATOMIC_MASSES[self as usize - 1]
     }
 }
 
+const ATOMIC_MASSES: [f32; 118] = [
+    1.008, 4.0026, 6.94, 9.0122, 10.81, 12.011, 14.007, 15.999, 18.998, 20.180, 22.990, 24.305,
+    26.982, 28.085, 30.974, 32.06, 35.45, 39.948, 39.098, 40.078, 44.956, 47.867, 50.942, 51.996,
+    54.938, 55.845, 58.933, 58.693, 63.546, 65.38, 69.723, 72.630, 74.922, 78.971, 79.904, 83.798,
+    85.468, 87.62, 88.906, 91.224, 92.906, 95.95, 98., 101.07, 102.91, 106.42, 107.87, 112.41,
+    114.82, 118.71, 121.76, 127.60, 126.90, 131.29, 132.91, 137.33, 138.91, 140.12, 140.91, 144.24,
+    145., 150.36, 151.96, 157.25, 158.93, 162.50, 164.93, 167.26, 168.93, 173.05, 174.97, 178.49,
+    180.95, 183.84, 186.21, 190.23, 192.22, 195.08, 196.97, 200.59, 204.38, 207.2, 208.98, 209.,
+    210., 222., 223., 226., 227., 232.04, 231.04, 238.03, 237., 244., 243., 247., 247., 251., 252.,
+    257., 258., 259., 266., 267., 268., 269., 270., 277., 278., 281., 282., 285., 286., 289., 290.,
+    293., 294., 294.,
+];
+
 const ELEMENT_SYMBOLS: [&str; 118] = [
     "H", "HE", "LI", "BE", "B", "C", "N", "O", "F", "NE", "NA", "MG", "AL", "SI", "P", "S", "CL",
     "AR", "K", "CA", "SC", "TI", "V", "CR", "MN", "FE", "CO", "NI", "CU", "ZN", "GA", "GE", "AS",
@@ -702,3 +802,74 @@ const ELEMENT_SYMBOLS: [&str; 118] = [
     "CF", "ES", "FM", "MD", "NO", "LR", "RF", "DB", "SG", "BH", "HS", "MT", "DS", "RG", "CN", "NH",
     "FL", "MC", "LV", "TS", "OG",
 ];
+
+#[cfg(test)]
+mod tests {
+    use crate::structure_data::*;
+
+    #[test]
+    fn pairs_within_cutoff() {
+        // a helix-like walk with negative coordinates, and atoms on the cell borders
+        let mut positions: Vec<[f32; 3]> = (0..200)
+            .map(|k| {
+                let t = k as f32 * 0.7;
+                [3. * t.cos() - 5., 3. * t.sin(), 0.3 * k as f32 - 20.]
+            })
+            .collect();
+        positions.extend([[0., 0., 0.], [1.6, 0., 0.], [3.2, 0., 0.], [-1.6, 0., 0.]]);
+        let mut expected = Vec::new();
+        for i in 0..positions.len() {
+            for j in 0..i {
+                let d2: f32 = (0..3)
+                    .map(|k| (positions[i][k] - positions[j][k]).powi(2))
+                    .sum();
+                if d2.sqrt() <= GENERAL_BOND_CUTOFF {
+                    expected.push((i, j));
+                }
+            }
+        }
+        assert!(!expected.is_empty());
+        assert_eq!(pairs_within(&positions, GENERAL_BOND_CUTOFF), expected);
+    }
+
+    fn atom(residue_name: &str, hetero: Option<bool>) -> Atom {
+        Atom {
+            id: 0,
+            model_id: 0,
+            chain_name: "A".to_string(),
+            residue_id: 1,
+            residue_name: residue_name.to_string(),
+            insertion_code: None,
+            alternative_location: None,
+            segment_name: None,
+            hetero,
+            atom_id: 1,
+            atom_name: "C1".to_string(),
+            element: None,
+            x: 0.,
+            y: 0.,
+            z: 0.,
+            b_factor: 0.,
+            occupancy: 1.,
+            charge: 0.,
+        }
+    }
+
+    #[test]
+    fn ligand() {
+        // PDB: by the HETATM records
+        assert!(atom("LIG", Some(true)).is_ligand());
+        assert!(atom("MSE", Some(true)).is_ligand());
+        assert!(!atom("HOH", Some(true)).is_ligand());
+        assert!(!atom("LIG", Some(false)).is_ligand());
+
+        // GRO: by the residue names
+        for residue_name in ["LIG", "UNL", "HEM", "NAG"] {
+            assert!(atom(residue_name, None).is_ligand(), "{residue_name}");
+        }
+        for residue_name in ["ALA", "DA", "POPC", "SOL", "NA", "CLA"] {
+            assert!(!atom(residue_name, None).is_ligand(), "{residue_name}");
+        }
+        assert!(!atom("LIG", None).is_hetero());
+    }
+}
@@ -1,18 +1,27 @@
+use crate::error::{Diagnostic, Location, MoguraIoError, Severity};
+use crate::input::fetch::{FetchSource, Fetcher};
+use crate::structure_data::secondary_structure::{
+    find_secondary_structure, parse_mmcif_records, parse_pdb_records,
+};
 use crate::structure_data::*;
-
-#[cfg(target_arch = "wasm32")]
-use wasm_bindgen::prelude::*;
+use std::collections::HashMap;
 
 #[derive(Clone, Debug)]
 pub struct PDBData {
     atoms: Vec<Atom>,
     residues: Vec<Residue>,
+    diagnostics: Vec<Diagnostic>,
 }
 
 impl StructureData for PDBData {
-    fn load(structure_file: &str) -> Result<Self, anyhow::Error> {
-        let content = std::fs::read_to_string(structure_file)?;
-        Self::load_from_content(&content)
+    fn from_reader<R: std::io::Read>(mut reader: R) -> Result<Self, MoguraIoError> {
+        let mut content = String::new();
+        reader.read_to_string(&mut content)?;
+        if Self::sniff_mmcif(content.as_bytes()) {
+            Self::load_mmcif_from_content(&content)
+        } else {
+            Self::load_from_content(&content)
+        }
     }
 
     fn atoms(&self) -> &Vec<Atom> {
@@ -22,10 +31,45 @@ impl StructureData for PDBData {
     fn residues(&self) -> &Vec<Residue> {
         &self.residues
     }
+
+    fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
 }
 
+// record names which only appear in PDB format
+const PDB_RECORDS: [&str; 9] = [
+    "ATOM  ", "HETATM", "HEADER", "CRYST1", "MODEL ", "REMARK", "COMPND", "TITLE ", "SEQRES",
+];
+
 impl PDBData {
-    pub fn load_from_content(content: &str) -> Result<Self, anyhow::Error> {
+    pub fn load_from_content(content: &str) -> Result<Self, MoguraIoError> {
+        Self::load_from_content_with_format(content, pdbtbx::Format::Pdb)
+    }
+
+    pub fn load_mmcif_from_content(content: &str) -> Result<Self, MoguraIoError> {
+        Self::load_from_content_with_format(content, pdbtbx::Format::Mmcif)
+    }
+
+    pub fn sniff(content: &[u8]) -> bool {
+        String::from_utf8_lossy(content)
+            .lines()
+            .take(64)
+            .any(|line| PDB_RECORDS.iter().any(|record| line.starts_with(record)))
+    }
+
+    pub fn sniff_mmcif(content: &[u8]) -> bool {
+        String::from_utf8_lossy(content)
+            .lines()
+            .map(|line| line.trim())
+            .find(|line| !line.is_empty() && !line.starts_with('#'))
+            .is_some_and(|line| line.starts_with("data_"))
+    }
+
+    fn load_from_content_with_format(
+        content: &str,
+        format: pdbtbx::Format,
+    ) -> Result<Self, MoguraIoError> {
         let reader = std::io::BufReader::new(std::io::Cursor::new(content));
 
         // let (input_pdb, _errors) = pdbtbx::open_pdb_raw(
@@ -35,11 +79,40 @@ impl PDBData {
         // )
         // .unwrap();
 
-        let (input_pdb, _errors) = pdbtbx::ReadOptions::new()
-            .set_format(pdbtbx::Format::Pdb)
+        let format_name = match format {
+            pdbtbx::Format::Mmcif => "mmCIF",
+            _ => "PDB",
+        };
+        let (input_pdb, errors) = pdbtbx::ReadOptions::new()
+            .set_format(format)
             .set_level(pdbtbx::StrictnessLevel::Loose)
             .read_raw(reader)
-            .map_err(|_| anyhow::anyhow!("Failed to read PDB"))?;
+            .map_err(|errors| {
+                // report the first error which actually stopped reading
+                let error = errors
+                    .iter()
+                    .find(|error| error.level().fails(pdbtbx::StrictnessLevel::Loose))
+                    .or(errors.first());
+                match error {
+                    Some(error) => {
+                        let diagnostic = diagnostic(error);
+                        MoguraIoError::parse(format_name, diagnostic.location, &diagnostic.message)
+                    }
+                    None => MoguraIoError::parse(format_name, Location::default(), "unknown error"),
+                }
+            })?;
+        let diagnostics = errors.iter().map(diagnostic).collect();
+        // pdbtbx does not keep HELIX/SHEET records
+        let ss_ranges = match format {
+            pdbtbx::Format::Mmcif => parse_mmcif_records(content),
+            _ => parse_pdb_records(content),
+        };
+
+        // pdbtbx does not keep the segment id either
+        let segment_names = match format {
+            pdbtbx::Format::Mmcif => HashMap::new(),
+            _ => parse_segment_names(content),
+        };
 
         let mut id = 0;
         let mut atoms = Vec::new();
@@ -52,31 +125,45 @@ impl PDBData {
                 for k in 0..chain.residue_count() {
                     let residue = chain.residue(k).unwrap();
                     let mut tmp_atoms = Vec::new();
-                    for l in 0..residue.atom_count() {
-                        let atom = residue.atom(l).unwrap();
-                        let persed_atom = Atom {
-                            id,
-                            model_id: model.serial_number(),
-                            chain_name: chain.id().to_string(),
-                            residue_id: residue.serial_number(),
-                            residue_name: match residue.name() {
-                                Some(name) => name.to_string(),
-                                None => "None".to_string(),
-                            },
-                            atom_id: atom.serial_number(),
-                            atom_name: atom.name().to_string(),
-                            element: if let Some(element) = atom.element() {
-                                Element::from_symbol(element.symbol())
-                            } else {
-                                None
-                            },
-                            x: atom.x() as f32,
-                            y: atom.y() as f32,
-                            z: atom.z() as f32,
-                        };
-                        atoms.push(persed_atom.clone());
-                        tmp_atoms.push(persed_atom.clone());
-                        id += 1;
+                    for conformer in residue.conformers() {
+                        for atom in conformer.atoms() {
+                            let persed_atom = Atom {
+                                id,
+                                model_id: model.serial_number(),
+                                chain_name: chain.id().to_string(),
+                                residue_id: residue.serial_number(),
+                                residue_name: match residue.name() {
+                                    Some(name) => name.to_string(),
+                                    None => "None".to_string(),
+                                },
+                                insertion_code: residue
+                                    .insertion_code()
+                                    .map(|code| code.to_string()),
+                                alternative_location: conformer
+                                    .alternative_location()
+                                    .map(|altloc| altloc.to_string()),
+                                segment_name: segment_names
+                                    .get(&(i, atom.serial_number()))
+                                    .cloned(),
+                                hetero: Some(atom.hetero()),
+                                atom_id: atom.serial_number(),
+                                atom_name: atom.name().to_string(),
+                                element: if let Some(element) = atom.element() {
+                                    Element::from_symbol(element.symbol())
+                                } else {
+                                    None
+                                },
+                                x: atom.x() as f32,
+                                y: atom.y() as f32,
+                                z: atom.z() as f32,
+                                b_factor: atom.b_factor() as f32,
+                                occupancy: atom.occupancy() as f32,
+                                charge: atom.charge() as f32,
+                            };
+                            atoms.push(persed_atom.clone());
+                            tmp_atoms.push(persed_atom.clone());
+                            id += 1;
+                        }
                     }
 
                     residues.push(Residue {
@@ -89,57 +176,131 @@ impl PDBData {
                             None => "None".to_string(),
                         },
                         atoms: tmp_atoms,
+                        secondary_structure: find_secondary_structure(
+                            &ss_ranges,
+                            chain.id(),
+                            residue.serial_number(),
+                            residue.insertion_code(),
+                        ),
                     });
                 }
             }
         }
 
-        Ok(Self { atoms, residues })
+        Ok(Self {
+            atoms,
+            residues,
+            diagnostics,
+        })
     }
 
-    #[cfg(not(target_arch = "wasm32"))]
-    pub async fn download(pdbid: &str) -> anyhow::Result<Self, anyhow::Error> {
-        let response = reqwest::blocking::Client::new()
-            .get(format!("https://files.rcsb.org/view/{}.pdb", pdbid))
-            .send()?;
-        let status_code = response.status().as_u16();
-        let content = response.text()?;
+    pub async fn download(pdbid: &str) -> Result<Self, MoguraIoError> {
+        let content = Fetcher::new(FetchSource::RcsbPdb)
+            .fetch_bytes(pdbid)
+            .await?;
+        Self::from_bytes(&content)
+    }
+}
 
-        if status_code == 200 {
-            Ok(Self::load_from_content(&content)?)
-        } else {
-            Err(anyhow::anyhow!("Failed to download PDB file for {}", pdbid))
+fn diagnostic(error: &pdbtbx::PDBError) -> Diagnostic {
+    let severity = match error.level() {
+        pdbtbx::ErrorLevel::BreakingError | pdbtbx::ErrorLevel::InvalidatingError => {
+            Severity::Error
         }
+        _ => Severity::Warning,
+    };
+    let message = if error.long_description().is_empty() {
+        error.short_description().to_string()
+    } else {
+        format!(
+            "{}: {}",
+            error.short_description(),
+            error.long_description()
+        )
+    };
+    Diagnostic {
+        severity,
+        location: context_location(error.context()),
+        message,
     }
+}
 
-    #[cfg(target_arch = "wasm32")]
-    pub async fn download(pdbid: &str) -> anyhow::Result<Self, anyhow::Error> {
-        let mut opts = web_sys::RequestInit::new();
-        opts.method("GET");
-        opts.mode(web_sys::RequestMode::Cors);
-        let url = format!("https://files.rcsb.org/view/{}.pdb", pdbid);
-        let request = web_sys::Request::new_with_str_and_init(&url, &opts)
-            .map_err(|_| anyhow::anyhow!("Failed to create request"))?;
-        let window = gloo::utils::window();
-        let resp_value = wasm_bindgen_futures::JsFuture::from(window.fetch_with_request(&request))
-            .await
-            .map_err(|_| anyhow::anyhow!("Failed to fetch"))?;
-        let resp: web_sys::Response = resp_value
-            .dyn_into()
-            .map_err(|_| anyhow::anyhow!("Failed to get response"))?;
-        let text = wasm_bindgen_futures::JsFuture::from(
-            resp.text()
-                .map_err(|_| anyhow::anyhow!("Failed to get text"))?,
-        )
-        .await
-        .map_err(|_| anyhow::anyhow!("Failed to get text"))?;
+// pdbtbx columns are 0-based offsets
+fn context_location(context: &pdbtbx::Context) -> Location {
+    match context {
+        pdbtbx::Context::FullLine { linenumber, .. } => Location::line(*linenumber, None),
+        pdbtbx::Context::Line {
+            linenumber, offset, ..
+        } => Location::line(*linenumber, Some(offset + 1)),
+        pdbtbx::Context::Range {
+            start_linenumber,
+            offset,
+            ..
+        } => Location::line(*start_linenumber, Some(offset + 1)),
+        pdbtbx::Context::Multiple { contexts } => contexts
+            .iter()
+            .map(|(_, context)| context_location(context))
+            .find(|location| !location.is_empty())
+            .unwrap_or_default(),
+        _ => Location::default(),
+    }
+}
 
-        let content = match text.as_string() {
-            Some(content) => Ok(content.to_string()),
-            None => Err(()),
+// segid by model index and atom serial number, from ATOM/HETATM records,
+// serial numbers start over in each MODEL
+fn parse_segment_names(content: &str) -> HashMap<(usize, usize), String> {
+    let mut segment_names = HashMap::new();
+    let mut n_models: usize = 0;
+    for line in content.lines() {
+        if line.starts_with("MODEL") {
+            n_models += 1;
+            continue;
+        }
+        if !(line.starts_with("ATOM") || line.starts_with("HETATM")) {
+            continue;
         }
-        .map_err(|_| anyhow::anyhow!("Failed to get content"))?;
+        let Some(serial) = line
+            .get(6..11)
+            .and_then(|serial| serial.trim().parse().ok())
+        else {
+            continue;
+        };
+        let segment_name = line.get(72..76.min(line.len())).unwrap_or("").trim();
+        if !segment_name.is_empty() {
+            let model = n_models.saturating_sub(1);
+            segment_names.insert((model, serial), segment_name.to_string());
+        }
+    }
+    segment_names
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::structure_data::pdb::*;
+
+    #[test]
+    fn segment_names() {
+        let content = "\
+ATOM      1  N   ALA A   1      11.104   6.134  -6.504  1.00  0.00      PROA N
+ATOM      2  CA  ALA A   1      11.639   6.071  -5.147  1.00  0.00
+HETATM    3  O   HOH W   2       1.000   2.000   3.000  1.00  0.00      SOLV
+";
+        let segment_names = parse_segment_names(content);
+        assert_eq!(segment_names.len(), 2);
+        assert_eq!(segment_names[&(0, 1)], "PROA");
+        assert_eq!(segment_names[&(0, 3)], "SOLV");
 
-        Ok(Self::load_from_content(&content)?)
+        let content = "\
+MODEL        1
+ATOM      1  N   ALA A   1      11.104   6.134  -6.504  1.00  0.00      PROA N
+ENDMDL
+MODEL        2
+ATOM      1  N   ALA A   1      11.204   6.034  -6.404  1.00  0.00      PROB N
+ENDMDL
+";
+        let segment_names = parse_segment_names(content);
+        assert_eq!(segment_names.len(), 2);
+        assert_eq!(segment_names[&(0, 1)], "PROA");
+        assert_eq!(segment_names[&(1, 1)], "PROB");
     }
 }
@@ -0,0 +1,172 @@
+// residue names of PDB, Amber, CHARMM and GROMACS force fields
+
+const PROTEIN_RESNAME: [&str; 43] = [
+    "ALA", "ARG", "ASN", "ASP", "CYS", "GLN", "GLU", "GLY", "HIS", "ILE", "LEU", "LYS", "MET",
+    "PHE", "PRO", "SER", "THR", "TRP", "TYR", "VAL", // protonation states
+    "CYX", "CYM", "HID", "HIE", "HIP", "HSD", "HSE", "HSP", "LYN", "ASH", "GLH", "ARN", "TYM",
+    // caps
+    "ACE", "NME", "NMA", "NH2", // modified and rare amino acids
+    "MSE", "SEP", "TPO", "PTR", "SEC", "PYL",
+];
+
+const NUCLEIC_RESNAME: [&str; 45] = [
+    "A", "C", "G", "U", "I", "DA", "DC", "DG", "DT", "DU", "DI", "RA", "RC", "RG", "RU",
+    // Amber terminal residues
+    "A5", "A3", "C5", "C3", "G5", "G3", "U5", "U3", "DA5", "DA3", "DC5", "DC3", "DG5", "DG3", "DT5",
+    "DT3", "RA5", "RA3", "RC5", "RC3", "RG5", "RG3", "RU5", "RU3", // CHARMM
+    "ADE", "CYT", "GUA", "THY", "URA", // modified
+    "PSU",
+];
+
+const LIPID_RESNAME: [&str; 24] = [
+    "POPC", "POPE", "POPG", "POPS", "POPA", "DOPC", "DOPE", "DOPS", "DOPG", "DPPC", "DPPE", "DPPG",
+    "DMPC", "DMPE", "DMPG", "DLPC", "DSPC", "PLPC", "SOPC", "CHOL", "CHL1", "PSM", "SSM", "CLR",
+];
+
+const SUGAR_RESNAME: [&str; 26] = [
+    "GLC", "BGC", "NAG", "NDG", "MAN", "BMA", "GAL", "GLA", "FUC", "FUL", "SIA", "XYS", "XYP",
+    "FRU", "SUC", "TRE", "MAL", "LAT", "GCS", "A2G", // CHARMM
+    "AGLC", "BGLC", "AMAN", "BMAN", "AGAL", "BGAL",
+];
+
+const ION_RESNAME: [&str; 34] = [
+    "NA", "CL", "K", "MG", "CA", "ZN", "FE", "FE2", "MN", "CU", "CU1", "CO", "NI", "CD", "HG",
+    "SR", "BA", "CS", "LI", "RB", "BR", "IOD", "F", "AL", "AG", "PT", "AU", // CHARMM
+    "SOD", "CLA", "POT", "CAL", "CES", "LIT", "ZN2",
+];
+
+const WATER_RESNAME: [&str; 12] = [
+    "HOH", "WAT", "H2O", "DOD", "SOL", "SPC", "T3P", "T4P", "T5P", "TIP3", "TIP4", "TIP5",
+];
+
+const COFACTOR_RESNAME: [&str; 31] = [
+    "HEM", "HEC", "HEA", "HEB", "ATP", "ADP", "AMP", "ANP", "GTP", "GDP", "GNP", "NAD", "NAP",
+    "NDP", "NAI", "FAD", "FMN", "COA", "ACO", "SAM", "SAH", "PLP", "TPP", "BTN", "SF4", "FES",
+    "F3S", "MGD", "B12", "CHL", "BCL",
+];
+
+// amino acids, by the side chain
+const HYDROPHOBIC_RESNAME: [&str; 9] = [
+    "ALA", "VAL", "LEU", "ILE", "MET", "PHE", "TRP", "PRO", "MSE",
+];
+const ACIDIC_RESNAME: [&str; 2] = ["ASP", "GLU"];
+const BASIC_RESNAME: [&str; 5] = ["ARG", "LYS", "HIS", "HIP", "HSP"];
+const POLAR_RESNAME: [&str; 21] = [
+    "SER", "THR", "ASN", "GLN", "TYR", "CYS", "CYX", "CYM", "HID", "HIE", "HSD", "HSE", "ASH",
+    "GLH", "LYN", "ARN", "TYM", "SEC", "SEP", "TPO", "PTR",
+];
+const AROMATIC_RESNAME: [&str; 11] = [
+    "PHE", "TYR", "TRP", "HIS", "HID", "HIE", "HIP", "HSD", "HSE", "HSP", "PTR",
+];
+
+/// Class of a residue by its name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ResidueClass {
+    Protein,
+    Nucleic,
+    Lipid,
+    Sugar,
+    Ion,
+    Water,
+    Cofactor,
+    Other,
+}
+
+impl ResidueClass {
+    pub fn of(residue_name: &str) -> Self {
+        if PROTEIN_RESNAME.contains(&residue_name) {
+            ResidueClass::Protein
+        } else if WATER_RESNAME.contains(&residue_name) || residue_name.starts_with("TIP") {
+            ResidueClass::Water
+        } else if NUCLEIC_RESNAME.contains(&residue_name) {
+            ResidueClass::Nucleic
+        } else if ION_RESNAME.contains(&residue_name)
+            // e.g. Na+, Cl-, MG2+
+            || (residue_name.ends_with(['+', '-']) && residue_name.len() <= 4)
+        {
+            ResidueClass::Ion
+        } else if LIPID_RESNAME.contains(&residue_name) {
+            ResidueClass::Lipid
+        } else if SUGAR_RESNAME.contains(&residue_name) {
+            ResidueClass::Sugar
+        } else if COFACTOR_RESNAME.contains(&residue_name) {
+            ResidueClass::Cofactor
+        } else {
+            ResidueClass::Other
+        }
+    }
+}
+
+pub(crate) fn is_hydrophobic(residue_name: &str) -> bool {
+    HYDROPHOBIC_RESNAME.contains(&residue_name)
+}
+
+pub(crate) fn is_acidic(residue_name: &str) -> bool {
+    ACIDIC_RESNAME.contains(&residue_name)
+}
+
+pub(crate) fn is_basic(residue_name: &str) -> bool {
+    BASIC_RESNAME.contains(&residue_name)
+}
+
+pub(crate) fn is_polar(residue_name: &str) -> bool {
+    POLAR_RESNAME.contains(&residue_name)
+}
+
+pub(crate) fn is_aromatic(residue_name: &str) -> bool {
+    AROMATIC_RESNAME.contains(&residue_name)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::structure_data::residue_class::*;
+
+    #[test]
+    fn classes() {
+        for (residue_name, class) in [
+            ("ALA", ResidueClass::Protein),
+            ("HSD", ResidueClass::Protein),
+            ("ACE", ResidueClass::Protein),
+            ("NME", ResidueClass::Protein),
+            ("DA", ResidueClass::Nucleic),
+            ("U", ResidueClass::Nucleic),
+            ("RG5", ResidueClass::Nucleic),
+            ("POPC", ResidueClass::Lipid),
+            ("CHL1", ResidueClass::Lipid),
+            ("NAG", ResidueClass::Sugar),
+            ("NA", ResidueClass::Ion),
+            ("SOD", ResidueClass::Ion),
+            ("Cl-", ResidueClass::Ion),
+            ("MG2+", ResidueClass::Ion),
+            ("SOL", ResidueClass::Water),
+            ("TIP3", ResidueClass::Water),
+            ("HEM", ResidueClass::Cofactor),
+            ("LIG", ResidueClass::Other),
+        ] {
+            assert_eq!(ResidueClass::of(residue_name), class, "{}", residue_name);
+        }
+    }
+
+    #[test]
+    fn amino_acids() {
+        assert!(is_hydrophobic("LEU") && !is_hydrophobic("SER"));
+        assert!(is_acidic("GLU") && !is_acidic("GLH"));
+        assert!(is_basic("LYS") && is_basic("HIP") && !is_basic("LYN"));
+        assert!(is_polar("GLH") && is_polar("HSE") && !is_polar("GLY"));
+        assert!(is_aromatic("TRP") && is_aromatic("HSD") && !is_aromatic("LEU"));
+        for residue_name in HYDROPHOBIC_RESNAME
+            .iter()
+            .chain(&ACIDIC_RESNAME)
+            .chain(&BASIC_RESNAME)
+            .chain(&POLAR_RESNAME)
+            .chain(&AROMATIC_RESNAME)
+        {
+            assert_eq!(
+                ResidueClass::of(residue_name),
+                ResidueClass::Protein,
+                "{}",
+                residue_name
+            );
+        }
+    }
+}
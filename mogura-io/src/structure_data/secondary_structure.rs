@@ -0,0 +1,316 @@
+use std::collections::HashMap;
+
+/// Secondary structure assigned by the authors,
+/// from HELIX/SHEET records of PDB or struct_conf/struct_sheet_range of mmCIF.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SecondaryStructure {
+    AlphaHelix,
+    Helix310,
+    PiHelix,
+    PolyProline,
+    /// other helix classes, e.g. gamma or left-handed alpha
+    OtherHelix,
+    Strand,
+    Turn,
+}
+
+impl SecondaryStructure {
+    // helixClass of the HELIX record, also used by mmCIF pdbx_PDB_helix_class
+    fn from_helix_class(class: &str) -> Self {
+        match class.trim() {
+            "" | "1" => SecondaryStructure::AlphaHelix,
+            "3" => SecondaryStructure::PiHelix,
+            "5" => SecondaryStructure::Helix310,
+            "10" => SecondaryStructure::PolyProline,
+            _ => SecondaryStructure::OtherHelix,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct SSRange {
+    ss: SecondaryStructure,
+    chain: String,
+    // residue number and insertion code
+    start: (isize, String),
+    end: (isize, String),
+}
+
+impl SSRange {
+    fn new(
+        ss: SecondaryStructure,
+        chain: &str,
+        start: (&str, &str),
+        end: (&str, &str),
+    ) -> Option<Self> {
+        Some(Self {
+            ss,
+            chain: chain.to_string(),
+            start: (start.0.trim().parse().ok()?, insertion_code(start.1)),
+            end: (end.0.trim().parse().ok()?, insertion_code(end.1)),
+        })
+    }
+}
+
+fn insertion_code(code: &str) -> String {
+    match code.trim() {
+        "?" | "." => String::new(),
+        code => code.to_string(),
+    }
+}
+
+pub(crate) fn find_secondary_structure(
+    ranges: &[SSRange],
+    chain: &str,
+    residue_id: isize,
+    insertion: Option<&str>,
+) -> Option<SecondaryStructure> {
+    let residue = (residue_id, insertion_code(insertion.unwrap_or("")));
+    ranges
+        .iter()
+        .find(|range| range.chain == chain && range.start <= residue && residue <= range.end)
+        .map(|range| range.ss)
+}
+
+// fixed columns, 0-based and end exclusive
+fn column(line: &str, range: std::ops::Range<usize>) -> &str {
+    line.get(range.start..range.end.min(line.len()))
+        .unwrap_or("")
+        .trim()
+}
+
+pub(crate) fn parse_pdb_records(content: &str) -> Vec<SSRange> {
+    content
+        .lines()
+        .filter_map(|line| {
+            if line.starts_with("HELIX ") {
+                SSRange::new(
+                    SecondaryStructure::from_helix_class(column(line, 38..40)),
+                    column(line, 19..20),
+                    (column(line, 21..25), column(line, 25..26)),
+                    (column(line, 33..37), column(line, 37..38)),
+                )
+            } else if line.starts_with("SHEET ") {
+                SSRange::new(
+                    SecondaryStructure::Strand,
+                    column(line, 21..22),
+                    (column(line, 22..26), column(line, 26..27)),
+                    (column(line, 33..37), column(line, 37..38)),
+                )
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+pub(crate) fn parse_mmcif_records(content: &str) -> Vec<SSRange> {
+    // auth_* are the numbering of the PDB format, which pdbtbx reads
+    let range = |row: &HashMap<String, String>, ss: SecondaryStructure| {
+        let get = |auth: &str, label: &str| {
+            row.get(auth)
+                .filter(|value| *value != "?" && *value != ".")
+                .or(row.get(label))
+                .map(|value| value.as_str())
+                .unwrap_or("")
+        };
+        SSRange::new(
+            ss,
+            get("beg_auth_asym_id", "beg_label_asym_id"),
+            (
+                get("beg_auth_seq_id", "beg_label_seq_id"),
+                get("pdbx_beg_PDB_ins_code", ""),
+            ),
+            (
+                get("end_auth_seq_id", "end_label_seq_id"),
+                get("pdbx_end_PDB_ins_code", ""),
+            ),
+        )
+    };
+
+    let mut ranges = Vec::new();
+    for row in mmcif_category(content, "_struct_conf") {
+        let conf_type = row.get("conf_type_id").map(|t| t.as_str()).unwrap_or("");
+        let ss = match conf_type {
+            "HELX_RH_AL_P" => SecondaryStructure::AlphaHelix,
+            "HELX_RH_3T_P" => SecondaryStructure::Helix310,
+            "HELX_RH_PI_P" => SecondaryStructure::PiHelix,
+            "HELX_LH_PP_P" => SecondaryStructure::PolyProline,
+            "HELX_P" => SecondaryStructure::from_helix_class(
+                row.get("pdbx_PDB_helix_class")
+                    .map(|class| class.as_str())
+                    .unwrap_or(""),
+            ),
+            "STRN" => SecondaryStructure::Strand,
+            _ if conf_type.starts_with("HELX") => SecondaryStructure::OtherHelix,
+            _ if conf_type.starts_with("TURN") => SecondaryStructure::Turn,
+            _ => continue,
+        };
+        ranges.extend(range(&row, ss));
+    }
+    for row in mmcif_category(content, "_struct_sheet_range") {
+        ranges.extend(range(&row, SecondaryStructure::Strand));
+    }
+    ranges
+}
+
+// rows of a category, either in a loop_ or as key-value pairs
+fn mmcif_category(content: &str, category: &str) -> Vec<HashMap<String, String>> {
+    let prefix = format!("{}.", category);
+    let mut rows = Vec::new();
+    let mut lines = content.lines().peekable();
+    let mut single = HashMap::new();
+
+    while let Some(line) = lines.next() {
+        let line = line.trim();
+        if line == "loop_" {
+            let mut keys = Vec::new();
+            while let Some(key) = lines
+                .peek()
+                .and_then(|line| line.trim().strip_prefix(&prefix))
+            {
+                keys.push(key.to_string());
+                lines.next();
+            }
+            if keys.is_empty() {
+                continue;
+            }
+            let mut values = Vec::new();
+            while let Some(line) = lines.peek() {
+                let trimmed = line.trim();
+                if trimmed.starts_with('_')
+                    || trimmed.starts_with('#')
+                    || trimmed == "loop_"
+                    || trimmed.starts_with("data_")
+                {
+                    break;
+                }
+                let line = lines.next().unwrap();
+                if let Some(text) = line.strip_prefix(';') {
+                    // multi-line text field
+                    let mut text = text.to_string();
+                    for line in lines.by_ref() {
+                        if line.starts_with(';') {
+                            break;
+                        }
+                        text.push_str(line);
+                    }
+                    values.push(text);
+                } else {
+                    values.extend(mmcif_tokens(line));
+                }
+            }
+            for chunk in values.chunks(keys.len()) {
+                if chunk.len() == keys.len() {
+                    rows.push(keys.iter().cloned().zip(chunk.iter().cloned()).collect());
+                }
+            }
+        } else if let Some(item) = line.strip_prefix(&prefix) {
+            let mut tokens = mmcif_tokens(item);
+            if tokens.len() == 2 {
+                let value = tokens.pop().unwrap();
+                single.insert(tokens.pop().unwrap(), value);
+            }
+        }
+    }
+    if !single.is_empty() {
+        rows.push(single);
+    }
+    rows
+}
+
+// whitespace separated, '...' and "..." are quoted
+fn mmcif_tokens(line: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut chars = line.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c == '\'' || c == '"' {
+            chars.next();
+            let mut token = String::new();
+            while let Some(next) = chars.next() {
+                // a quote only closes the token when followed by whitespace
+                if next == c && chars.peek().is_none_or(|after| after.is_whitespace()) {
+                    break;
+                }
+                token.push(next);
+            }
+            tokens.push(token);
+        } else {
+            let mut token = String::new();
+            while let Some(&next) = chars.peek() {
+                if next.is_whitespace() {
+                    break;
+                }
+                token.push(next);
+                chars.next();
+            }
+            tokens.push(token);
+        }
+    }
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::structure_data::secondary_structure::*;
+
+    #[test]
+    fn pdb_records() {
+        let content = "\
+HELIX    1   1 ALA A   10  GLY A   20  1                                  11
+HELIX    2   2 PRO B   30A LEU B   35  5                                   6
+SHEET    1   A 2 VAL A  40  THR A  44  0
+ATOM      1  N   ALA A  10      11.104   6.134  -6.504  1.00  0.00           N
+";
+        let ranges = parse_pdb_records(content);
+        assert_eq!(ranges.len(), 3);
+        let find = |chain, id, icode| find_secondary_structure(&ranges, chain, id, icode);
+        assert_eq!(find("A", 10, None), Some(SecondaryStructure::AlphaHelix));
+        assert_eq!(find("A", 20, None), Some(SecondaryStructure::AlphaHelix));
+        assert_eq!(find("A", 21, None), None);
+        assert_eq!(find("B", 30, None), None);
+        assert_eq!(find("B", 30, Some("A")), Some(SecondaryStructure::Helix310));
+        assert_eq!(find("A", 42, Some(" ")), Some(SecondaryStructure::Strand));
+        assert_eq!(find("B", 42, None), None);
+    }
+
+    #[test]
+    fn mmcif_records() {
+        let content = "\
+data_TEST
+#
+loop_
+_struct_conf.conf_type_id
+_struct_conf.id
+_struct_conf.beg_label_asym_id
+_struct_conf.beg_label_seq_id
+_struct_conf.pdbx_beg_PDB_ins_code
+_struct_conf.end_label_seq_id
+_struct_conf.pdbx_end_PDB_ins_code
+_struct_conf.beg_auth_asym_id
+_struct_conf.beg_auth_seq_id
+_struct_conf.end_auth_seq_id
+_struct_conf.pdbx_PDB_helix_class
+_struct_conf.details
+HELX_P HELX_P1 A 1 ? 11 ? B 10 20 1 ?
+HELX_P HELX_P2 A 21 ? 26 ? B 30 35 5 'one 3-10 helix'
+#
+_struct_sheet_range.sheet_id A
+_struct_sheet_range.id 1
+_struct_sheet_range.beg_auth_asym_id B
+_struct_sheet_range.beg_auth_seq_id 40
+_struct_sheet_range.end_auth_seq_id 44
+#
+";
+        let ranges = parse_mmcif_records(content);
+        assert_eq!(ranges.len(), 3);
+        let find = |id| find_secondary_structure(&ranges, "B", id, None);
+        assert_eq!(find(15), Some(SecondaryStructure::AlphaHelix));
+        assert_eq!(find(33), Some(SecondaryStructure::Helix310));
+        assert_eq!(find(44), Some(SecondaryStructure::Strand));
+        assert_eq!(find(25), None);
+        assert_eq!(find_secondary_structure(&ranges, "A", 15, None), None);
+    }
+}
@@ -1,45 +1,37 @@
 pub mod xtc;
-use crate::trajectory_data::xtc::XtcData;
+use crate::error::MoguraIoError;
+use crate::input::registry::FormatRegistry;
+use crate::structure_data::{StructureData, structure_loader};
 
 pub fn trajectory_loader(
     topology_file: &str,
     trajectory_file: &str,
-) -> Result<Box<dyn TrajectoryData>, anyhow::Error> {
-    let extension = std::path::Path::new(trajectory_file)
-        .extension()
-        .and_then(|ext| ext.to_str());
-    if let Some(extension) = extension {
-        match extension {
-            "xtc" => {
-                #[cfg(feature = "groan_rs")]
-                {
-                    Ok(Box::new(XtcData::load(topology_file, trajectory_file)?))
-                }
+) -> Result<Box<dyn TrajectoryData>, MoguraIoError> {
+    FormatRegistry::global()
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .load_trajectory(topology_file, trajectory_file)
+}
 
-                #[cfg(not(feature = "groan_rs"))]
-                {
-                    // unimplemented!("This extension is not supported.");
-                    // Err("This extension is not supported.".to_string())
-                    Err(anyhow::anyhow!("This extension is not supported."))
-                }
-            }
-            _ => {
-                // unimplemented!("This extension is not supported.");
-                // Err("This extension is not supported.".to_string())
-                Err(anyhow::anyhow!("This extension is not supported."))
-            }
-        }
-    } else {
-        // panic!("trajectory_file: {} has no extension.", trajectory_file);
-        // Err(format!(
-        //     "trajectory_file: {} has no extension.",
-        //     trajectory_file
-        // ))
-        Err(anyhow::anyhow!(format!(
-            "trajectory_file: {} has no extension.",
-            trajectory_file
-        )))
-    }
+/// `file_name` is only used for its extension, e.g. `traj.xtc.gz`.
+/// When it is `None`, the format is detected from the content.
+pub fn trajectory_loader_from_reader<R: std::io::Read>(
+    topology: &dyn StructureData,
+    reader: R,
+    file_name: Option<&str>,
+) -> Result<Box<dyn TrajectoryData>, MoguraIoError> {
+    FormatRegistry::global()
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .load_trajectory_from_reader(topology, reader, file_name)
+}
+
+pub fn trajectory_loader_from_content(
+    topology: &dyn StructureData,
+    content: &[u8],
+    file_name: Option<&str>,
+) -> Result<Box<dyn TrajectoryData>, MoguraIoError> {
+    trajectory_loader_from_reader(topology, content, file_name)
 }
 
 pub trait TrajectoryData: Sync + Send {
@@ -49,10 +41,31 @@ pub trait TrajectoryData: Sync + Send {
         &self.frames()[frame_id]
     }
 
-    fn load(topology_file: &str, trajectory_file: &str) -> Result<Self, anyhow::Error>
+    /// Uncompressed input, `trajectory_loader_from_reader` also takes compressed one.
+    fn from_reader<R: std::io::Read>(
+        topology: &dyn StructureData,
+        reader: R,
+    ) -> Result<Self, MoguraIoError>
     where
         Self: Sized;
 
+    fn from_bytes(topology: &dyn StructureData, content: &[u8]) -> Result<Self, MoguraIoError>
+    where
+        Self: Sized,
+    {
+        Self::from_reader(topology, content)
+    }
+
+    fn load(topology_file: &str, trajectory_file: &str) -> Result<Self, MoguraIoError>
+    where
+        Self: Sized,
+    {
+        let topology = structure_loader(topology_file)?;
+        let file = std::fs::File::open(trajectory_file)
+            .map_err(|e| MoguraIoError::io(trajectory_file, e))?;
+        Self::from_reader(topology.as_ref(), file).map_err(|e| e.with_file(Some(trajectory_file)))
+    }
+
     fn n_frame(&self) -> usize {
         self.frames().len()
     }
@@ -1,5 +1,11 @@
 use crate::trajectory_data::*;
 
+#[cfg(feature = "groan_rs")]
+use crate::error::{Location, MoguraIoError};
+#[cfg(feature = "groan_rs")]
+use crate::input::compression::{LocalFile, TempFile};
+#[cfg(feature = "groan_rs")]
+use crate::structure_data::{StructureData, structure_loader};
 #[cfg(feature = "groan_rs")]
 use groan_rs::prelude::*;
 
@@ -8,16 +14,60 @@ pub struct XtcData {
     frames: Vec<Frame>,
 }
 
+// every xtc frame starts with this magic number (big endian)
+const XTC_MAGIC: i32 = 1995;
+
+impl XtcData {
+    pub fn sniff(content: &[u8]) -> bool {
+        content.starts_with(&XTC_MAGIC.to_be_bytes())
+    }
+}
+
 #[cfg(feature = "groan_rs")]
 impl TrajectoryData for XtcData {
     fn frames(&self) -> &Vec<Frame> {
         &self.frames
     }
 
-    fn load(topology_file: &str, trajectory_file: &str) -> Result<Self, anyhow::Error> {
-        let mut topology = System::from_file(topology_file).map_err(anyhow::Error::msg)?;
+    fn from_reader<R: std::io::Read>(
+        topology: &dyn StructureData,
+        reader: R,
+    ) -> Result<Self, MoguraIoError> {
+        // groan_rs only reads from paths
+        let temp_file = TempFile::new(reader, "xtc")?;
+        Self::load_xtc(topology, temp_file.path())
+    }
+
+    fn load(topology_file: &str, trajectory_file: &str) -> Result<Self, MoguraIoError> {
+        let topology = structure_loader(topology_file)?;
+        let local_file = LocalFile::new(trajectory_file, "xtc")
+            .map_err(|e| MoguraIoError::io(trajectory_file, e))?;
+        Self::load_xtc(topology.as_ref(), local_file.path())
+            .map_err(|e| e.with_file(Some(trajectory_file)))
+    }
+}
+
+#[cfg(feature = "groan_rs")]
+impl XtcData {
+    fn load_xtc(topology: &dyn StructureData, xtc_file: &str) -> Result<Self, MoguraIoError> {
+        // xtc only has positions, so the topology just has to give the number of atoms
+        let atoms = topology
+            .atoms()
+            .iter()
+            .map(|atom| {
+                groan_rs::prelude::Atom::new(
+                    atom.residue_id().max(0) as usize,
+                    atom.residue_name(),
+                    atom.atom_id(),
+                    atom.atom_name(),
+                )
+            })
+            .collect();
+        let mut topology = System::new("topology", atoms, None);
 
-        let trajectory = topology.xtc_iter(trajectory_file)?;
+        let trajectory = topology
+            .xtc_iter(xtc_file)
+            .map_err(|e| MoguraIoError::parse("XTC", Location::default(), &e.to_string()))?;
 
         let mut frames = Vec::new();
         for (frame_id, frame) in trajectory.enumerate() {
@@ -38,7 +88,11 @@ impl TrajectoryData for XtcData {
                 Err(e) => {
                     // panic!("{:?}", e);
                     // return Err(e.to_string())
-                    return Err(anyhow::anyhow!(e.to_string()));
+                    return Err(MoguraIoError::parse(
+                        "XTC",
+                        Location::default(),
+                        &format!("frame {}: {}", frame_id, e),
+                    ));
                 }
             }
         }
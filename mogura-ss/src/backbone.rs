@@ -0,0 +1,276 @@
+use crate::geom::*;
+use crate::*;
+use std::collections::VecDeque;
+
+const MAX_PEPTIDE_BOND_LENGTH: f32 = 2.5;
+
+#[derive(Debug, Clone)]
+pub(crate) struct Backbone {
+    pub n: [f32; 3],
+    pub ca: [f32; 3],
+    pub c: [f32; 3],
+    pub o: [f32; 3],
+    // amide hydrogen, opposite to C=O of the previous residue
+    // None for proline and residues after a break, they cannot donate
+    pub h: Option<[f32; 3]>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BridgeType {
+    Parallel,
+    AntiParallel,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct Bridge {
+    pub bridge_type: BridgeType,
+    pub i: VecDeque<usize>,
+    pub j: VecDeque<usize>,
+}
+
+// same chain and C(prev)-N(next) is a peptide bond
+pub(crate) fn is_peptide_bonded(prev: &Residue, next: &Residue) -> bool {
+    if prev.chain != next.chain {
+        return false;
+    }
+    match (find_atom(prev, "C"), find_atom(next, "N")) {
+        (Some(c), Some(n)) => distance(&position(&c), &position(&n)) <= MAX_PEPTIDE_BOND_LENGTH,
+        _ => false,
+    }
+}
+
+// residues with a full backbone (N, CA, C, O), the others are SS::Break
+#[derive(Debug, Clone)]
+pub(crate) struct Peptide {
+    pub residues: Vec<Backbone>,
+    // breaks[k]: no peptide bond between k and k + 1
+    pub breaks: Vec<bool>,
+    // index in Peptide::residues for each input residue
+    pub indices: Vec<Option<usize>>,
+}
+
+impl Peptide {
+    pub fn new(residues: &[Residue]) -> Self {
+        let mut backbones: Vec<Backbone> = Vec::with_capacity(residues.len());
+        let mut breaks = Vec::with_capacity(residues.len());
+        let mut indices = Vec::with_capacity(residues.len());
+        let mut prev_index = None;
+
+        for (index, residue) in residues.iter().enumerate() {
+            let (n, ca, c, o) = match (
+                find_atom(residue, "N"),
+                find_atom(residue, "CA"),
+                find_atom(residue, "C"),
+                find_atom(residue, "O"),
+            ) {
+                (Some(n), Some(ca), Some(c), Some(o)) => {
+                    (position(&n), position(&ca), position(&c), position(&o))
+                }
+                _ => {
+                    indices.push(None);
+                    continue;
+                }
+            };
+
+            let h = match backbones.last() {
+                Some(prev)
+                    if prev_index == Some(index - 1)
+                        && residues[index - 1].chain == residue.chain
+                        && distance(&prev.c, &n) <= MAX_PEPTIDE_BOND_LENGTH =>
+                {
+                    breaks.push(false);
+                    if residue.name == "PRO" {
+                        None
+                    } else {
+                        Some(add(&n, &normalize(&sub(&prev.c, &prev.o))))
+                    }
+                }
+                Some(_) => {
+                    breaks.push(true);
+                    None
+                }
+                None => None,
+            };
+
+            indices.push(Some(backbones.len()));
+            backbones.push(Backbone { n, ca, c, o, h });
+            prev_index = Some(index);
+        }
+        breaks.push(true);
+
+        Self {
+            residues: backbones,
+            breaks,
+            indices,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.residues.len()
+    }
+
+    pub fn no_chain_break(&self, from: usize, to: usize) -> bool {
+        !self.breaks[from..to].iter().any(|&b| b)
+    }
+
+    pub fn phi_psi(&self, k: usize) -> (Option<f32>, Option<f32>) {
+        let residues = &self.residues;
+        let current = &residues[k];
+        let phi = if k > 0 && !self.breaks[k - 1] {
+            Some(dihedral_position(
+                &residues[k - 1].c,
+                &current.n,
+                &current.ca,
+                &current.c,
+            ))
+        } else {
+            None
+        };
+        let psi = if !self.breaks[k] {
+            Some(dihedral_position(
+                &current.n,
+                &current.ca,
+                &current.c,
+                &residues[k + 1].n,
+            ))
+        } else {
+            None
+        };
+        (phi, psi)
+    }
+
+    // turns[stride - 3][i]: n-turn starting at i
+    pub fn turns(&self, has_hbond: impl Fn(usize, usize) -> bool) -> [Vec<bool>; 3] {
+        let n = self.len();
+        let turn = |stride: usize| {
+            (0..n)
+                .map(|i| {
+                    i + stride < n && self.no_chain_break(i, i + stride) && has_hbond(i + stride, i)
+                })
+                .collect::<Vec<bool>>()
+        };
+        [turn(3), turn(4), turn(5)]
+    }
+
+    pub fn bridge_type(
+        &self,
+        i: usize,
+        j: usize,
+        has_hbond: impl Fn(usize, usize) -> bool,
+    ) -> Option<BridgeType> {
+        if !self.no_chain_break(i - 1, i + 1) || !self.no_chain_break(j - 1, j + 1) {
+            return None;
+        }
+        if (has_hbond(i + 1, j) && has_hbond(j, i - 1))
+            || (has_hbond(j + 1, i) && has_hbond(i, j - 1))
+        {
+            Some(BridgeType::Parallel)
+        } else if (has_hbond(i + 1, j - 1) && has_hbond(j + 1, i - 1))
+            || (has_hbond(j, i) && has_hbond(i, j))
+        {
+            Some(BridgeType::AntiParallel)
+        } else {
+            None
+        }
+    }
+
+    pub fn ladders(&self, bridge_type: impl Fn(usize, usize) -> Option<BridgeType>) -> Vec<Bridge> {
+        let n = self.len();
+        let mut bridges: Vec<Bridge> = Vec::new();
+
+        for i in 1..n.saturating_sub(4) {
+            for j in i + 3..n - 1 {
+                let bridge_type = match bridge_type(i, j) {
+                    Some(bridge_type) => bridge_type,
+                    None => continue,
+                };
+                let extended = bridges.iter_mut().find(|bridge| {
+                    bridge.bridge_type == bridge_type
+                        && bridge.i.back() == Some(&(i - 1))
+                        && match bridge_type {
+                            BridgeType::Parallel => bridge.j.back() == Some(&(j - 1)),
+                            BridgeType::AntiParallel => bridge.j.front() == Some(&(j + 1)),
+                        }
+                });
+                match extended {
+                    Some(bridge) => {
+                        bridge.i.push_back(i);
+                        match bridge_type {
+                            BridgeType::Parallel => bridge.j.push_back(j),
+                            BridgeType::AntiParallel => bridge.j.push_front(j),
+                        }
+                    }
+                    None => bridges.push(Bridge {
+                        bridge_type,
+                        i: VecDeque::from([i]),
+                        j: VecDeque::from([j]),
+                    }),
+                }
+            }
+        }
+
+        // join ladders separated by a beta-bulge
+        let mut a = 0;
+        while a < bridges.len() {
+            let mut b = a + 1;
+            while b < bridges.len() {
+                let (ibi, iei) = (bridges[a].i[0], *bridges[a].i.back().unwrap());
+                let (jbi, jei) = (bridges[a].j[0], *bridges[a].j.back().unwrap());
+                let (ibj, iej) = (bridges[b].i[0], *bridges[b].i.back().unwrap());
+                let (jbj, jej) = (bridges[b].j[0], *bridges[b].j.back().unwrap());
+
+                let joinable = bridges[a].bridge_type == bridges[b].bridge_type
+                    && self.no_chain_break(ibi.min(ibj), iei.max(iej))
+                    && self.no_chain_break(jbi.min(jbj), jei.max(jej))
+                    && ibj < iei + 6
+                    && !(iei >= ibj && ibi <= iej);
+                let bulge = joinable
+                    && match bridges[a].bridge_type {
+                        BridgeType::Parallel => (jbj < jei + 6 && ibj < iei + 3) || jbj < jei + 3,
+                        BridgeType::AntiParallel => {
+                            (jbi < jej + 6 && ibj < iei + 3) || jbi < jej + 3
+                        }
+                    };
+
+                if bulge {
+                    let merged = bridges.remove(b);
+                    let bridge = &mut bridges[a];
+                    bridge.i.extend(merged.i);
+                    match bridge.bridge_type {
+                        BridgeType::Parallel => bridge.j.extend(merged.j),
+                        BridgeType::AntiParallel => {
+                            for j in merged.j.into_iter().rev() {
+                                bridge.j.push_front(j);
+                            }
+                        }
+                    }
+                } else {
+                    b += 1;
+                }
+            }
+            a += 1;
+        }
+
+        bridges
+    }
+
+    // peptide index -> input residue index
+    pub fn residue_indices(&self) -> Vec<usize> {
+        self.indices
+            .iter()
+            .enumerate()
+            .filter_map(|(index, k)| k.map(|_| index))
+            .collect()
+    }
+
+    // back to the input residues
+    pub fn expand(&self, ss: &[SS]) -> Vec<SS> {
+        self.indices
+            .iter()
+            .map(|index| match index {
+                Some(k) => ss[*k].clone(),
+                None => SS::Break,
+            })
+            .collect()
+    }
+}
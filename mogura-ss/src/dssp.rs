@@ -0,0 +1,333 @@
+// DSSP, Kabsch & Sander 1983, https://doi.org/10.1002/bip.360221211
+// follows mkdssp 4 for the details (pi-helix preference, PP helix)
+use crate::backbone::*;
+use crate::geom::*;
+use crate::*;
+
+// q1 * q2 * f = 0.42 * 0.20 * 332 (kcal/mol)
+const COUPLING: f32 = 27.888;
+const MIN_HBOND_ENERGY: f32 = -9.9;
+const MAX_HBOND_ENERGY: f32 = -0.5;
+const MIN_DISTANCE: f32 = 0.5;
+const MIN_CA_DISTANCE: f32 = 9.0;
+const MIN_BEND_ANGLE: f32 = 70.0;
+const PP_PHI: f32 = -75.0;
+const PP_PSI: f32 = 145.0;
+const PP_EPSILON: f32 = 29.0;
+const PP_STRETCH: usize = 3;
+
+#[derive(Debug, Clone, Copy)]
+struct HBond {
+    partner: usize,
+    energy: f32,
+}
+
+struct Dssp {
+    peptide: Peptide,
+    // two lowest energy acceptors for each donor
+    acceptors: Vec<Vec<HBond>>,
+}
+
+impl Dssp {
+    fn new(residues: &[Residue]) -> Self {
+        let peptide = Peptide::new(residues);
+        let mut dssp = Self {
+            acceptors: vec![Vec::with_capacity(2); peptide.len()],
+            peptide,
+        };
+        dssp.calculate_hbonds();
+        dssp
+    }
+
+    fn calculate_hbonds(&mut self) {
+        let n = self.peptide.len();
+        for i in 0..n {
+            for j in i + 1..n {
+                if distance(&self.peptide.residues[i].ca, &self.peptide.residues[j].ca)
+                    >= MIN_CA_DISTANCE
+                {
+                    continue;
+                }
+                self.add_hbond(i, j);
+                if j != i + 1 {
+                    self.add_hbond(j, i);
+                }
+            }
+        }
+    }
+
+    fn add_hbond(&mut self, donor: usize, acceptor: usize) {
+        let energy = hbond_energy(
+            &self.peptide.residues[donor],
+            &self.peptide.residues[acceptor],
+        );
+        let acceptors = &mut self.acceptors[donor];
+        acceptors.push(HBond {
+            partner: acceptor,
+            energy,
+        });
+        acceptors.sort_by(|a, b| a.energy.total_cmp(&b.energy));
+        acceptors.truncate(2);
+    }
+
+    // N-H of donor -> O=C of acceptor
+    fn has_hbond(&self, donor: usize, acceptor: usize) -> bool {
+        self.acceptors[donor]
+            .iter()
+            .any(|hbond| hbond.partner == acceptor && hbond.energy < MAX_HBOND_ENERGY)
+    }
+
+    fn is_bend(&self, k: usize) -> bool {
+        if k < 2 || k + 2 >= self.peptide.len() || !self.peptide.no_chain_break(k - 2, k + 2) {
+            return false;
+        }
+        let backbones = &self.peptide.residues;
+        let kappa = angle(
+            &sub(&backbones[k].ca, &backbones[k - 2].ca),
+            &sub(&backbones[k + 2].ca, &backbones[k].ca),
+        );
+        kappa > MIN_BEND_ANGLE
+    }
+
+    fn assign(&self) -> Vec<SS> {
+        let n = self.peptide.len();
+        let mut ss = vec![SS::Loop; n];
+
+        // E, B
+        for bridge in self
+            .peptide
+            .ladders(|i, j| self.peptide.bridge_type(i, j, |d, a| self.has_hbond(d, a)))
+        {
+            let bridge_ss = if bridge.i.len() > 1 { SS::E } else { SS::B };
+            for range in [
+                bridge.i[0]..=*bridge.i.back().unwrap(),
+                bridge.j[0]..=*bridge.j.back().unwrap(),
+            ] {
+                for k in range {
+                    if ss[k] != SS::E {
+                        ss[k] = bridge_ss.clone();
+                    }
+                }
+            }
+        }
+
+        let turns = self.peptide.turns(|d, a| self.has_hbond(d, a));
+        let is_helix = |stride: usize, i: usize| turns[stride - 3][i - 1] && turns[stride - 3][i];
+
+        // H
+        for i in 1..n.saturating_sub(4) {
+            if is_helix(4, i) {
+                for s in ss.iter_mut().skip(i).take(4) {
+                    *s = SS::H;
+                }
+            }
+        }
+
+        // I, preferred over H
+        for i in 1..n.saturating_sub(5) {
+            if is_helix(5, i)
+                && ss[i..i + 5]
+                    .iter()
+                    .all(|s| matches!(s, SS::Loop | SS::I | SS::H))
+            {
+                for s in ss.iter_mut().skip(i).take(5) {
+                    *s = SS::I;
+                }
+            }
+        }
+
+        // G
+        for i in 1..n.saturating_sub(3) {
+            if is_helix(3, i) && ss[i..i + 3].iter().all(|s| matches!(s, SS::Loop | SS::G)) {
+                for s in ss.iter_mut().skip(i).take(3) {
+                    *s = SS::G;
+                }
+            }
+        }
+
+        // P
+        let in_pp_region = |k: usize| match self.peptide.phi_psi(k) {
+            (Some(phi), Some(psi)) => {
+                (phi - PP_PHI).abs() <= PP_EPSILON && (psi - PP_PSI).abs() <= PP_EPSILON
+            }
+            _ => false,
+        };
+        for i in 1..n.saturating_sub(PP_STRETCH) {
+            if (i..i + PP_STRETCH).all(in_pp_region) {
+                for s in ss.iter_mut().skip(i).take(PP_STRETCH) {
+                    if *s == SS::Loop {
+                        *s = SS::P;
+                    }
+                }
+            }
+        }
+
+        // T, S
+        for k in 0..n {
+            if ss[k] != SS::Loop {
+                continue;
+            }
+            let is_turn =
+                (3..=5).any(|stride| (1..stride).any(|d| k >= d && turns[stride - 3][k - d]));
+            if is_turn {
+                ss[k] = SS::T;
+            } else if self.is_bend(k) {
+                ss[k] = SS::S;
+            }
+        }
+
+        ss
+    }
+}
+
+fn hbond_energy(donor: &Backbone, acceptor: &Backbone) -> f32 {
+    let h = match donor.h {
+        Some(h) => h,
+        None => return 0.0,
+    };
+    let r_on = distance(&acceptor.o, &donor.n);
+    let r_ch = distance(&acceptor.c, &h);
+    let r_oh = distance(&acceptor.o, &h);
+    let r_cn = distance(&acceptor.c, &donor.n);
+    if r_on < MIN_DISTANCE || r_ch < MIN_DISTANCE || r_oh < MIN_DISTANCE || r_cn < MIN_DISTANCE {
+        return MIN_HBOND_ENERGY;
+    }
+    let energy = COUPLING * (1. / r_on + 1. / r_ch - 1. / r_oh - 1. / r_cn);
+    ((energy * 1000.).round() / 1000.).max(MIN_HBOND_ENERGY)
+}
+
+// beta ladders with the input residue indices
+pub(crate) fn ladders(residues_in_protein: &[Residue]) -> Vec<Bridge> {
+    let dssp = Dssp::new(residues_in_protein);
+    let residue_indices = dssp.peptide.residue_indices();
+    dssp.peptide
+        .ladders(|i, j| dssp.peptide.bridge_type(i, j, |d, a| dssp.has_hbond(d, a)))
+        .into_iter()
+        .map(|mut bridge| {
+            for k in bridge.i.iter_mut().chain(bridge.j.iter_mut()) {
+                *k = residue_indices[*k];
+            }
+            bridge
+        })
+        .collect()
+}
+
+pub fn assign_ss(residues_in_protein: &[Residue]) -> Vec<SS> {
+    let dssp = Dssp::new(residues_in_protein);
+    dssp.peptide.expand(&dssp.assign())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dssp::*;
+    use crate::fixtures::{load_dssp_reference, load_fixture};
+
+    fn dssp_string(residues: &[Residue]) -> String {
+        assign_ss(residues).iter().map(|ss| ss.to_char()).collect()
+    }
+
+    // deposited structures, `<id>.pdb` with `<id>.dssp` written by `mkdssp --output-format dssp`
+    // (mkdssp 4), e.g. 1CRN and a mixed alpha/beta protein
+    #[test]
+    #[ignore = "needs reference structures and mkdssp output in tests/fixtures/reference"]
+    fn mkdssp_reference() {
+        let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/reference");
+        let mut n = 0;
+        let read = |path: &std::path::Path| {
+            std::fs::read_to_string(path).unwrap_or_else(|e| {
+                panic!(
+                    "{}: {e}, see tests/fixtures/reference/README.md",
+                    path.display()
+                )
+            })
+        };
+        for entry in std::fs::read_dir(&dir).unwrap_or_else(|e| panic!("{}: {e}", dir.display())) {
+            let path = entry.unwrap().path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some("pdb") {
+                continue;
+            }
+            let residues = load_fixture(&read(&path));
+            let reference = load_dssp_reference(&read(&path.with_extension("dssp")));
+            assert_eq!(dssp_string(&residues), reference, "{}", path.display());
+            n += 1;
+        }
+        assert!(
+            n > 0,
+            "no reference structures in {}, see README.md there",
+            dir.display()
+        );
+    }
+
+    #[test]
+    fn dssp_reference_format() {
+        let content = "\
+==== Secondary Structure Definition by the program DSSP ====
+  #  RESIDUE AA STRUCTURE BP1 BP2  ACC
+    1    1 A T              0   0  130
+    2    2 A T  E     -A   10   0A  62
+    3    3 A C  E     -A    9   0A  48
+    4        !              0   0    0
+    5    5 B A  H  > S+     0   0   72
+    6    6 B L  T  3 S+     0   0   95
+";
+        assert_eq!(load_dssp_reference(content), "~EEHT");
+    }
+
+    // the fixtures below are ideal backbones built from phi/psi, one per SS type,
+    // they check the assignment rules but not the agreement with mkdssp
+
+    #[test]
+    fn alpha_helix() {
+        let residues = load_fixture(include_str!("../tests/fixtures/alpha_helix.pdb"));
+        assert_eq!(dssp_string(&residues), "~HHHHHHHHHHHHHH~");
+    }
+
+    #[test]
+    fn helix_3_10() {
+        let residues = load_fixture(include_str!("../tests/fixtures/helix_3_10.pdb"));
+        assert_eq!(dssp_string(&residues), "~GGGGGGGGGGGGGG~");
+    }
+
+    #[test]
+    fn pi_helix() {
+        let residues = load_fixture(include_str!("../tests/fixtures/pi_helix.pdb"));
+        assert_eq!(dssp_string(&residues), "~IIIIIIIIIIIIII~");
+    }
+
+    #[test]
+    fn polyproline() {
+        let residues = load_fixture(include_str!("../tests/fixtures/polyproline.pdb"));
+        assert_eq!(dssp_string(&residues), "~PPPPPPPPPPPPPP~");
+    }
+
+    #[test]
+    fn antiparallel_sheet() {
+        let residues = load_fixture(include_str!("../tests/fixtures/antiparallel_sheet.pdb"));
+        assert_eq!(dssp_string(&residues), "~EEEEE~~EEEEE~");
+    }
+
+    #[test]
+    fn beta_bridge() {
+        let residues = load_fixture(include_str!("../tests/fixtures/beta_bridge.pdb"));
+        assert_eq!(dssp_string(&residues), "~B~~B~");
+    }
+
+    #[test]
+    fn incomplete_backbone() {
+        let mut residues = load_fixture(include_str!("../tests/fixtures/alpha_helix.pdb"));
+        residues[0].atoms.retain(|atom| atom.name != "O");
+        assert_eq!(dssp_string(&residues), "=~HHHHHHHHHHHHH~");
+    }
+
+    #[test]
+    fn hbond_energy_cutoff() {
+        let residues = load_fixture(include_str!("../tests/fixtures/alpha_helix.pdb"));
+        let dssp = Dssp::new(&residues);
+        // i -> i + 4 only
+        assert!(dssp.has_hbond(4, 0));
+        assert!(!dssp.has_hbond(3, 0));
+        assert!(!dssp.has_hbond(5, 0));
+        assert!(dssp.acceptors[4][0].energy < MAX_HBOND_ENERGY);
+        assert!(dssp.acceptors[4][0].energy >= MIN_HBOND_ENERGY);
+    }
+}
@@ -0,0 +1,177 @@
+use crate::*;
+
+pub(crate) fn find_atom(res: &Residue, name: &str) -> Option<Atom> {
+    res.atoms.iter().find(|atom| atom.name == name).cloned()
+}
+
+pub(crate) fn position(atom: &Atom) -> [f32; 3] {
+    [atom.x, atom.y, atom.z]
+}
+
+pub(crate) fn sub(u: &[f32; 3], v: &[f32; 3]) -> [f32; 3] {
+    [u[0] - v[0], u[1] - v[1], u[2] - v[2]]
+}
+
+pub(crate) fn add(u: &[f32; 3], v: &[f32; 3]) -> [f32; 3] {
+    [u[0] + v[0], u[1] + v[1], u[2] + v[2]]
+}
+
+pub(crate) fn cross(u: &[f32; 3], v: &[f32; 3]) -> [f32; 3] {
+    [
+        u[1] * v[2] - u[2] * v[1],
+        u[2] * v[0] - u[0] * v[2],
+        u[0] * v[1] - u[1] * v[0],
+    ]
+}
+
+pub(crate) fn dot(u: &[f32; 3], v: &[f32; 3]) -> f32 {
+    u[0] * v[0] + u[1] * v[1] + u[2] * v[2]
+}
+
+pub(crate) fn norm(v: &[f32; 3]) -> f32 {
+    (v[0] * v[0] + v[1] * v[1] + v[2] * v[2]).sqrt()
+}
+
+pub(crate) fn distance(u: &[f32; 3], v: &[f32; 3]) -> f32 {
+    norm(&sub(u, v))
+}
+
+pub(crate) fn normalize(v: &[f32; 3]) -> [f32; 3] {
+    let n = norm(v);
+    if n == 0.0 {
+        [0.0, 0.0, 0.0]
+    } else {
+        [v[0] / n, v[1] / n, v[2] / n]
+    }
+}
+
+// angle between two vectors in degrees
+pub(crate) fn angle(u: &[f32; 3], v: &[f32; 3]) -> f32 {
+    let cos = dot(u, v) / (norm(u) * norm(v));
+    cos.clamp(-1.0, 1.0).acos().to_degrees()
+}
+
+// difference of two angles in degrees, -180..180
+pub(crate) fn angle_diff(a: f32, b: f32) -> f32 {
+    (a - b + 540.).rem_euclid(360.) - 180.
+}
+
+pub(crate) fn dihedral_position(p1: &[f32; 3], p2: &[f32; 3], p3: &[f32; 3], p4: &[f32; 3]) -> f32 {
+    let b1 = sub(p2, p1);
+    let b2 = sub(p3, p2);
+    let b3 = sub(p4, p3);
+
+    let n1 = cross(&b1, &b2);
+    let n2 = cross(&b2, &b3);
+
+    let b2_norm = normalize(&b2);
+    let m1 = normalize(&n1);
+    let m2 = normalize(&n2);
+
+    let x = dot(&m1, &m2);
+    let y = dot(&cross(&m1, &m2), &b2_norm);
+    let angle = y.atan2(x);
+
+    angle.to_degrees()
+}
+
+pub(crate) fn dihedral(p1: &Atom, p2: &Atom, p3: &Atom, p4: &Atom) -> f32 {
+    dihedral_position(&position(p1), &position(p2), &position(p3), &position(p4))
+}
+
+pub(crate) fn centroid(points: &[[f32; 3]]) -> [f32; 3] {
+    let mut sum = [0.0; 3];
+    for p in points {
+        sum = add(&sum, p);
+    }
+    let n = points.len().max(1) as f32;
+    [sum[0] / n, sum[1] / n, sum[2] / n]
+}
+
+// RMSD after the optimal superposition (Kabsch), from the largest eigenvalue
+// of the quaternion key matrix, Horn 1987, https://doi.org/10.1364/JOSAA.4.000629
+pub(crate) fn superposed_rmsd(a: &[[f32; 3]], b: &[[f32; 3]]) -> f32 {
+    assert_eq!(a.len(), b.len());
+    if a.is_empty() {
+        return 0.0;
+    }
+    let (center_a, center_b) = (centroid(a), centroid(b));
+    let mut s = [[0.0f64; 3]; 3];
+    let mut g = 0.0f64;
+    for (p, q) in a.iter().zip(b) {
+        let x = sub(p, &center_a);
+        let y = sub(q, &center_b);
+        for i in 0..3 {
+            for j in 0..3 {
+                s[i][j] += x[i] as f64 * y[j] as f64;
+            }
+        }
+        g += dot(&x, &x) as f64 + dot(&y, &y) as f64;
+    }
+    let key = [
+        [
+            s[0][0] + s[1][1] + s[2][2],
+            s[1][2] - s[2][1],
+            s[2][0] - s[0][2],
+            s[0][1] - s[1][0],
+        ],
+        [
+            s[1][2] - s[2][1],
+            s[0][0] - s[1][1] - s[2][2],
+            s[0][1] + s[1][0],
+            s[2][0] + s[0][2],
+        ],
+        [
+            s[2][0] - s[0][2],
+            s[0][1] + s[1][0],
+            -s[0][0] + s[1][1] - s[2][2],
+            s[1][2] + s[2][1],
+        ],
+        [
+            s[0][1] - s[1][0],
+            s[2][0] + s[0][2],
+            s[1][2] + s[2][1],
+            -s[0][0] - s[1][1] + s[2][2],
+        ],
+    ];
+    let lambda = symmetric_eigenvalues(key)
+        .into_iter()
+        .fold(f64::MIN, f64::max);
+    ((g - 2.0 * lambda).max(0.0) / a.len() as f64).sqrt() as f32
+}
+
+// cyclic Jacobi rotations
+#[allow(clippy::needless_range_loop)]
+fn symmetric_eigenvalues<const N: usize>(mut m: [[f64; N]; N]) -> [f64; N] {
+    for _ in 0..50 {
+        let off: f64 = (0..N)
+            .flat_map(|i| (0..N).filter(move |&j| j != i).map(move |j| (i, j)))
+            .map(|(i, j)| m[i][j] * m[i][j])
+            .sum();
+        if off < 1e-18 {
+            break;
+        }
+        for p in 0..N {
+            for q in p + 1..N {
+                if m[p][q].abs() < 1e-300 {
+                    continue;
+                }
+                let theta = (m[q][q] - m[p][p]) / (2.0 * m[p][q]);
+                let t = theta.signum() / (theta.abs() + (theta * theta + 1.0).sqrt());
+                let c = 1.0 / (t * t + 1.0).sqrt();
+                let s = t * c;
+                for k in 0..N {
+                    let (mkp, mkq) = (m[k][p], m[k][q]);
+                    m[k][p] = c * mkp - s * mkq;
+                    m[k][q] = s * mkp + c * mkq;
+                }
+                for k in 0..N {
+                    let (mpk, mqk) = (m[p][k], m[q][k]);
+                    m[p][k] = c * mpk - s * mqk;
+                    m[q][k] = s * mpk + c * mqk;
+                }
+            }
+        }
+    }
+    std::array::from_fn(|i| m[i][i])
+}
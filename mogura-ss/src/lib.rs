@@ -1,18 +1,87 @@
+mod backbone;
+mod dssp;
+mod geom;
 mod rama;
+mod sst;
+mod stride;
+mod summary;
+mod timeline;
+mod torsion;
+
+pub use backbone::BridgeType;
+pub use summary::{Helix, Ladder, SSSummary, Sheet, summarize};
+pub use timeline::{SSTimeline, assign_ss_trajectory, assign_ss_trajectory_with_progress};
+pub use torsion::{
+    RamaRegion, Torsion, rama_region, torsions, torsions_frame, torsions_trajectory,
+};
 
 #[allow(dead_code)]
 #[derive(Debug, Clone)]
 pub struct Residue {
     name: String,
     atoms: Vec<Atom>,
+    // residues in different chains are never bonded
+    chain: String,
+    // assigned in the input file, used by SSAlgorithm::FromFile
+    annotation: Option<SS>,
 }
 
 impl Residue {
     pub fn new(name: String, atoms: Vec<Atom>) -> Self {
-        Self { name, atoms }
+        Self {
+            name,
+            atoms,
+            chain: String::new(),
+            annotation: None,
+        }
+    }
+
+    pub fn with_chain(mut self, chain: &str) -> Self {
+        self.chain = chain.to_string();
+        self
+    }
+
+    pub fn with_annotation(mut self, annotation: Option<SS>) -> Self {
+        self.annotation = annotation;
+        self
     }
 }
 
+impl From<&mogura_io::prelude::Residue> for Residue {
+    fn from(residue: &mogura_io::prelude::Residue) -> Self {
+        Residue::new(
+            residue.residue_name().to_string(),
+            residue.atoms().iter().map(Atom::from).collect(),
+        )
+        .with_chain(residue.chain_name())
+        .with_annotation(residue.secondary_structure().map(SS::from))
+    }
+}
+
+/// Groups consecutive atoms by model, chain, residue id and insertion code,
+/// so that neighbouring residues with the same name stay separate.
+pub fn residues_from_atoms(atoms: &[mogura_io::prelude::Atom]) -> Vec<Residue> {
+    let mut residues: Vec<Residue> = Vec::new();
+    let mut prev_key = None;
+    for atom in atoms {
+        let key = (
+            atom.model_id(),
+            atom.chain_name(),
+            atom.residue_id(),
+            atom.insertion_code(),
+        );
+        match residues.last_mut() {
+            Some(residue) if prev_key == Some(key) => residue.atoms.push(Atom::from(atom)),
+            _ => residues.push(
+                Residue::new(atom.residue_name().to_string(), vec![Atom::from(atom)])
+                    .with_chain(atom.chain_name()),
+            ),
+        }
+        prev_key = Some(key);
+    }
+    residues
+}
+
 #[derive(Debug, Clone)]
 pub struct Atom {
     name: String,
@@ -27,35 +96,148 @@ impl Atom {
     }
 }
 
-#[derive(Clone, Debug, PartialEq)]
+impl From<&mogura_io::prelude::Atom> for Atom {
+    fn from(atom: &mogura_io::prelude::Atom) -> Self {
+        Atom::new(atom.atom_name().to_string(), atom.x(), atom.y(), atom.z())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Default)]
 pub enum SSAlgorithm {
     Ramachandran,
+    #[default]
     DSSP,
-    STRIDE,
+    // DSSP patterns on STRIDE's H-bond energy, not STRIDE itself, see stride.rs
+    StrideLike,
     SST,
+    // HELIX/SHEET records of the input file
+    FromFile,
+}
+
+impl SSAlgorithm {
+    pub const ALL: [SSAlgorithm; 5] = [
+        SSAlgorithm::Ramachandran,
+        SSAlgorithm::DSSP,
+        SSAlgorithm::StrideLike,
+        SSAlgorithm::SST,
+        SSAlgorithm::FromFile,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            SSAlgorithm::Ramachandran => "Ramachandran",
+            SSAlgorithm::DSSP => "DSSP",
+            SSAlgorithm::StrideLike => "STRIDE-like",
+            SSAlgorithm::SST => "SST",
+            SSAlgorithm::FromFile => "From file",
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum SS {
     // DSSP v4, https://doi.org/10.1021/acs.jcim.3c01344
-    H, // 4-helix (alpha-helix)
-    // B,     // residue in isolated beta-bridge (beta-bridge)
-    E, // extended strand participates in beta-ladder (beta-strand)
-    // G,     // 3-helix (3_10-helix)
-    // I,     // 5-helix (pi-helix)
-    // P,     // kappa-helix (polyproline II helix)
-    // S,     // bend
-    // T,     // H-bonded turn
-    // Break, // =, !, break
-    Loop, // ~, <space> loop
+    H,     // 4-helix (alpha-helix)
+    B,     // residue in isolated beta-bridge (beta-bridge)
+    E,     // extended strand participates in beta-ladder (beta-strand)
+    G,     // 3-helix (3_10-helix)
+    I,     // 5-helix (pi-helix)
+    P,     // kappa-helix (polyproline II helix)
+    S,     // bend
+    T,     // H-bonded turn
+    Break, // =, !, break
+    Loop,  // ~, <space> loop
+}
+
+impl SS {
+    // one letter code, as in the DSSP output
+    pub fn to_char(&self) -> char {
+        match self {
+            SS::H => 'H',
+            SS::B => 'B',
+            SS::E => 'E',
+            SS::G => 'G',
+            SS::I => 'I',
+            SS::P => 'P',
+            SS::S => 'S',
+            SS::T => 'T',
+            SS::Break => '=',
+            SS::Loop => '~',
+        }
+    }
+}
+
+impl From<mogura_io::prelude::SecondaryStructure> for SS {
+    fn from(ss: mogura_io::prelude::SecondaryStructure) -> Self {
+        use mogura_io::prelude::SecondaryStructure;
+        match ss {
+            SecondaryStructure::AlphaHelix | SecondaryStructure::OtherHelix => SS::H,
+            SecondaryStructure::Helix310 => SS::G,
+            SecondaryStructure::PiHelix => SS::I,
+            SecondaryStructure::PolyProline => SS::P,
+            SecondaryStructure::Strand => SS::E,
+            SecondaryStructure::Turn => SS::T,
+        }
+    }
 }
 
 pub fn assign_ss(residues_in_protein: &Vec<Residue>, algo: SSAlgorithm) -> Vec<SS> {
     match algo {
         SSAlgorithm::Ramachandran => rama::assign_ss(residues_in_protein),
-        SSAlgorithm::DSSP => todo!(),
-        SSAlgorithm::STRIDE => todo!(),
-        SSAlgorithm::SST => todo!(),
+        SSAlgorithm::DSSP => dssp::assign_ss(residues_in_protein),
+        SSAlgorithm::StrideLike => stride::assign_ss(residues_in_protein),
+        SSAlgorithm::SST => sst::assign_ss(residues_in_protein),
+        SSAlgorithm::FromFile => residues_in_protein
+            .iter()
+            .map(|residue| residue.annotation.clone().unwrap_or(SS::Loop))
+            .collect(),
+    }
+}
+
+// tests/fixtures/*.pdb
+#[cfg(test)]
+mod fixtures {
+    use crate::*;
+
+    // only N, CA, C, O of ATOM records of the first model are needed
+    pub(crate) fn load_fixture(content: &str) -> Vec<Residue> {
+        let mut residues: Vec<(String, Residue)> = Vec::new();
+        for line in content
+            .lines()
+            .take_while(|line| !line.starts_with("ENDMDL"))
+            .filter(|line| line.starts_with("ATOM"))
+        {
+            let key = line[21..27].to_string();
+            let atom = Atom::new(
+                line[12..16].trim().to_string(),
+                line[30..38].trim().parse().unwrap(),
+                line[38..46].trim().parse().unwrap(),
+                line[46..54].trim().parse().unwrap(),
+            );
+            match residues.last_mut() {
+                Some((last_key, residue)) if *last_key == key => residue.atoms.push(atom),
+                _ => residues.push((
+                    key,
+                    Residue::new(line[17..20].to_string(), vec![atom]).with_chain(&line[21..22]),
+                )),
+            }
+        }
+        residues.into_iter().map(|(_, residue)| residue).collect()
+    }
+
+    // 8-state string of the classic output of mkdssp, `~` for loops,
+    // the `!` lines of chain breaks are not residues
+    pub(crate) fn load_dssp_reference(content: &str) -> String {
+        content
+            .lines()
+            .skip_while(|line| !line.starts_with("  #  RESIDUE"))
+            .skip(1)
+            .filter(|line| line.chars().nth(13) != Some('!'))
+            .map(|line| match line.chars().nth(16) {
+                Some(' ') | None => '~',
+                Some(ss) => ss,
+            })
+            .collect()
     }
 }
 
@@ -63,33 +245,47 @@ pub fn assign_ss(residues_in_protein: &Vec<Residue>, algo: SSAlgorithm) -> Vec<S
 #[cfg(test)]
 mod tests {
     use crate::*;
-    use itertools::Itertools;
     use mogura_io::prelude::*;
 
     struct Atoms(pub Vec<mogura_io::prelude::Atom>); // to avoid E0117
 
     impl From<Atoms> for Vec<crate::Residue> {
         fn from(atoms_in_protein: Atoms) -> Self {
-            atoms_in_protein
-                .0
-                .into_iter()
-                .chunk_by(|atom| atom.residue_name().to_string())
-                .into_iter()
-                .map(|(res_name, group)| crate::Residue {
-                    name: res_name.to_string(),
-                    atoms: group
-                        .map(|atom| crate::Atom {
-                            name: atom.atom_name().to_string(),
-                            x: atom.x(),
-                            y: atom.y(),
-                            z: atom.z(),
-                        })
-                        .collect(),
-                })
-                .collect()
+            residues_from_atoms(&atoms_in_protein.0)
         }
     }
 
+    #[test]
+    fn from_file() {
+        let residues = vec![
+            crate::Residue::new("ALA".to_string(), vec![]),
+            crate::Residue::new("ALA".to_string(), vec![])
+                .with_annotation(Some(SecondaryStructure::Helix310.into())),
+        ];
+        assert_eq!(
+            assign_ss(&residues, SSAlgorithm::FromFile),
+            vec![SS::Loop, SS::G]
+        );
+    }
+
+    #[test]
+    fn chain_break() {
+        let mut residues =
+            crate::fixtures::load_fixture(include_str!("../tests/fixtures/alpha_helix.pdb"));
+        for residue in residues.iter_mut().skip(8) {
+            residue.chain = "B".to_string();
+        }
+        let ss_string = |algo| -> String {
+            assign_ss(&residues, algo)
+                .iter()
+                .map(|ss| ss.to_char())
+                .collect()
+        };
+        // no dihedrals and no H-bonds across the chains
+        assert_eq!(ss_string(SSAlgorithm::Ramachandran), "~HHHHHH~~HHHHHH~");
+        assert_eq!(ss_string(SSAlgorithm::DSSP), "~HHHHHH~~HHHHHH~");
+    }
+
     // #[test]
     // fn pdb_5AWL_rama() {
     //     let pdb = pollster::block_on(async { PDBData::download("5AWL").await });
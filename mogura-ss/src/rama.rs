@@ -1,100 +1,24 @@
+use crate::torsion::torsions;
 use crate::*;
 
-fn find_atom(res: &Residue, name: &str) -> Option<Atom> {
-    res.atoms.iter().find(|atom| atom.name == name).cloned()
-}
-
-fn cross(u: &[f32; 3], v: &[f32; 3]) -> [f32; 3] {
-    [
-        u[1] * v[2] - u[2] * v[1],
-        u[2] * v[0] - u[0] * v[2],
-        u[0] * v[1] - u[1] * v[0],
-    ]
-}
-
-fn dot(u: &[f32; 3], v: &[f32; 3]) -> f32 {
-    u[0] * v[0] + u[1] * v[1] + u[2] * v[2]
-}
-
-fn norm(v: &[f32; 3]) -> f32 {
-    (v[0] * v[0] + v[1] * v[1] + v[2] * v[2]).sqrt()
-}
-
-fn normalize(v: &[f32; 3]) -> [f32; 3] {
-    let n = norm(v);
-    if n == 0.0 {
-        [0.0, 0.0, 0.0]
-    } else {
-        [v[0] / n, v[1] / n, v[2] / n]
-    }
-}
-
-fn dihedral(p1: &Atom, p2: &Atom, p3: &Atom, p4: &Atom) -> f32 {
-    let b1 = [p2.x - p1.x, p2.y - p1.y, p2.z - p1.z];
-    let b2 = [p3.x - p2.x, p3.y - p2.y, p3.z - p2.z];
-    let b3 = [p4.x - p3.x, p4.y - p3.y, p4.z - p3.z];
-
-    let n1 = cross(&b1, &b2);
-    let n2 = cross(&b2, &b3);
-
-    let b2_norm = normalize(&b2);
-    let m1 = normalize(&n1);
-    let m2 = normalize(&n2);
-
-    let x = dot(&m1, &m2);
-    let y = dot(&cross(&m1, &m2), &b2_norm);
-    let angle = y.atan2(x);
-
-    angle.to_degrees()
-}
-
 #[allow(clippy::ptr_arg)]
 pub fn assign_ss(residues_in_protein: &Vec<Residue>) -> Vec<SS> {
-    let n_res = residues_in_protein.len();
-    let mut ss = vec![SS::Loop; n_res];
-
-    for i in 0..n_res {
-        let current = &residues_in_protein[i];
-        let n_atom = match find_atom(current, "N") {
-            Some(a) => a,
-            None => continue,
-        };
-        let ca_atom = match find_atom(current, "CA") {
-            Some(a) => a,
-            None => continue,
-        };
-        let c_atom = match find_atom(current, "C") {
-            Some(a) => a,
-            None => continue,
-        };
-
-        // φ = dihedral(C(i-1), N(i), CA(i), C(i))
-        let phi = if i > 0 {
-            let prev = &residues_in_protein[i - 1];
-            find_atom(prev, "C").map(|prev_c| dihedral(&prev_c, &n_atom, &ca_atom, &c_atom))
-        } else {
-            None
-        };
-
-        // ψ = dihedral(N(i), CA(i), C(i), N(i+1))
-        let psi = if i < n_res - 1 {
-            let next = &residues_in_protein[i + 1];
-            find_atom(next, "N").map(|next_n| dihedral(&n_atom, &ca_atom, &c_atom, &next_n))
-        } else {
-            None
-        };
-
-        if let (Some(phi_val), Some(psi_val)) = (phi, psi) {
+    torsions(residues_in_protein)
+        .iter()
+        .map(|torsion| match (torsion.phi, torsion.psi) {
             // α-helix, φ ∈ [–90, –30], ψ ∈ [–77, –17]）
-            if (-90.0..=-30.0).contains(&phi_val) && (-77.0..=-17.0).contains(&psi_val) {
-                ss[i] = SS::H;
+            (Some(phi), Some(psi))
+                if (-90.0..=-30.0).contains(&phi) && (-77.0..=-17.0).contains(&psi) =>
+            {
+                SS::H
             }
             // β-strand, φ ∈ [–150, –90], ψ ∈ [90, 180]）
-            else if (-150.0..=-90.0).contains(&phi_val) && (90.0..=180.0).contains(&psi_val) {
-                ss[i] = SS::E;
+            (Some(phi), Some(psi))
+                if (-150.0..=-90.0).contains(&phi) && (90.0..=180.0).contains(&psi) =>
+            {
+                SS::E
             }
-        }
-    }
-
-    ss
+            _ => SS::Loop,
+        })
+        .collect()
 }
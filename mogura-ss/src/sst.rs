@@ -0,0 +1,394 @@
+// SST, Konagurthu et al. 2012, https://doi.org/10.1093/bioinformatics/bts223
+// CA only. The trace is segmented by dynamic programming so that the total message length,
+// ideal secondary structure templates superposed on each segment vs a null model of coil, is minimum.
+use crate::geom::*;
+use crate::*;
+
+const MAX_CA_DISTANCE: f32 = 4.2;
+const CA_DISTANCE: f32 = 3.8;
+// width of the CA-CA shell for a CA without predecessors
+const CA_SHELL: f32 = 0.4;
+const CA_DISTANCE_SIGMA: f32 = 0.1;
+// the null model places each CA by the CA-CA-CA angle and the CA dihedral of loops,
+// (weight, mean, sigma) in degrees on top of a uniform floor, as in the
+// helical and extended basins of the virtual bond angles
+const LOOP_ANGLE: [(f32, f32, f32); 2] = [(0.3, 92.0, 6.0), (0.55, 120.0, 12.0)];
+const LOOP_DIHEDRAL: [(f32, f32, f32); 2] = [(0.25, 50.0, 20.0), (0.45, -150.0, 40.0)];
+// deviation from an ideal template
+const SIGMA: f32 = 0.3;
+const MAX_SEGMENT_LENGTH: usize = 30;
+
+// ideal CA helix, the strand is a 2-fold helix
+struct Template {
+    ss: SS,
+    rise: f32,
+    twist: f32,
+    radius: f32,
+    min_length: usize,
+}
+
+const TEMPLATES: [Template; 5] = [
+    Template {
+        ss: SS::H,
+        rise: 1.5,
+        twist: 100.0,
+        radius: 2.3,
+        min_length: 4,
+    },
+    Template {
+        ss: SS::G,
+        rise: 2.0,
+        twist: 120.0,
+        radius: 1.9,
+        min_length: 3,
+    },
+    Template {
+        ss: SS::I,
+        rise: 1.15,
+        twist: 87.0,
+        radius: 2.65,
+        min_length: 5,
+    },
+    Template {
+        ss: SS::E,
+        rise: 3.4,
+        twist: 180.0,
+        radius: 0.85,
+        min_length: 3,
+    },
+    // left-handed
+    Template {
+        ss: SS::P,
+        rise: 3.1,
+        twist: -120.0,
+        radius: 1.27,
+        min_length: 3,
+    },
+];
+
+impl Template {
+    fn trace(&self, length: usize) -> Vec<[f32; 3]> {
+        (0..length)
+            .map(|k| {
+                let t = (self.twist * k as f32).to_radians();
+                [
+                    self.radius * t.cos(),
+                    self.radius * t.sin(),
+                    self.rise * k as f32,
+                ]
+            })
+            .collect()
+    }
+}
+
+// density per radian of gaussians in degrees and a uniform remainder over range
+fn mixture(components: &[(f32, f32, f32)], x: f32, range: f32) -> f32 {
+    let uniform = 1. - components.iter().map(|c| c.0).sum::<f32>();
+    components
+        .iter()
+        .map(|&(weight, mean, sigma)| {
+            let z = angle_diff(x, mean) / sigma;
+            weight * (-0.5 * z * z).exp()
+                / ((2. * std::f32::consts::PI).sqrt() * sigma.to_radians())
+        })
+        .sum::<f32>()
+        + uniform / range.to_radians()
+}
+
+// residues with CA, the others are SS::Break
+struct Trace {
+    positions: Vec<[f32; 3]>,
+    // breaks[k]: k and k + 1 are not bonded
+    breaks: Vec<bool>,
+    indices: Vec<Option<usize>>,
+}
+
+impl Trace {
+    fn new(residues: &[Residue]) -> Self {
+        let mut positions: Vec<[f32; 3]> = Vec::with_capacity(residues.len());
+        let mut breaks = Vec::with_capacity(residues.len());
+        let mut indices = Vec::with_capacity(residues.len());
+        let mut prev_index = None;
+        for (index, residue) in residues.iter().enumerate() {
+            let ca = match find_atom(residue, "CA") {
+                Some(ca) => position(&ca),
+                None => {
+                    indices.push(None);
+                    continue;
+                }
+            };
+            if let Some(prev) = positions.last() {
+                breaks.push(
+                    prev_index != Some(index - 1)
+                        || residues[index - 1].chain != residue.chain
+                        || distance(prev, &ca) > MAX_CA_DISTANCE,
+                );
+            }
+            indices.push(Some(positions.len()));
+            positions.push(ca);
+            prev_index = Some(index);
+        }
+        breaks.push(true);
+        Self {
+            positions,
+            breaks,
+            indices,
+        }
+    }
+
+    // nats to state CA k given the preceding CAs of the same chain
+    fn coil_cost(&self, k: usize) -> f32 {
+        use std::f32::consts::PI;
+        let bonded = |back: usize| k >= back && !self.breaks[k - back..k].iter().any(|&b| b);
+        let p = &self.positions;
+        if !bonded(1) {
+            return (4. * PI * CA_DISTANCE * CA_DISTANCE * CA_SHELL).ln();
+        }
+        // the volume element is d^2 sin(theta) dd dtheta dtau
+        let d = distance(&p[k - 1], &p[k]);
+        let mut cost = (2. * PI * CA_DISTANCE_SIGMA * CA_DISTANCE_SIGMA).ln() / 2.
+            + (d - CA_DISTANCE).powi(2) / (2. * CA_DISTANCE_SIGMA * CA_DISTANCE_SIGMA)
+            + (d * d).ln();
+        if !bonded(2) {
+            return cost + (4. * PI).ln();
+        }
+        let theta = angle(&sub(&p[k - 2], &p[k - 1]), &sub(&p[k], &p[k - 1]));
+        cost += theta.to_radians().sin().max(1e-3).ln() - mixture(&LOOP_ANGLE, theta, 180.).ln();
+        if !bonded(3) {
+            return cost + (2. * PI).ln();
+        }
+        let tau = dihedral_position(&p[k - 3], &p[k - 2], &p[k - 1], &p[k]);
+        cost - mixture(&LOOP_DIHEDRAL, tau, 360.).ln()
+    }
+
+    fn assign(&self) -> Vec<SS> {
+        let n = self.positions.len();
+        // nats, the precision of the coordinates cancels out
+        let residue_cost = 1.5 * (2. * std::f32::consts::PI * SIGMA * SIGMA).ln();
+        let header_cost = ((TEMPLATES.len() + 1) as f32).ln() + (MAX_SEGMENT_LENGTH as f32).ln();
+        let traces: Vec<Vec<[f32; 3]>> = TEMPLATES
+            .iter()
+            .map(|template| template.trace(MAX_SEGMENT_LENGTH))
+            .collect();
+        // the superposition is part of the message, its rotation is stated to the
+        // precision that moves the template by SIGMA and the first CA anchors it
+        let orientation_costs: Vec<Vec<f32>> = traces
+            .iter()
+            .map(|trace| {
+                (0..=MAX_SEGMENT_LENGTH)
+                    .map(|length| {
+                        let segment = &trace[..length];
+                        let center = centroid(segment);
+                        let radius = (segment
+                            .iter()
+                            .map(|p| dot(&sub(p, &center), &sub(p, &center)))
+                            .sum::<f32>()
+                            / length.max(1) as f32)
+                            .sqrt();
+                        (8. * std::f32::consts::PI.powi(2)).ln()
+                            + 3. * (radius / SIGMA).max(1.).ln()
+                    })
+                    .collect()
+            })
+            .collect();
+        let coil_costs: Vec<f32> = (0..n).map(|k| self.coil_cost(k)).collect();
+
+        // cost[k]: shortest message for 0..k, choice[k]: last segment
+        let mut cost = vec![0.0f32; n + 1];
+        let mut choice: Vec<Option<(usize, usize)>> = vec![None; n + 1];
+        for k in 1..=n {
+            cost[k] = cost[k - 1] + coil_costs[k - 1];
+            for (t, template) in TEMPLATES.iter().enumerate() {
+                for length in template.min_length..=MAX_SEGMENT_LENGTH.min(k) {
+                    let start = k - length;
+                    if self.breaks[start..k - 1].iter().any(|&b| b) {
+                        break;
+                    }
+                    let rmsd = superposed_rmsd(&self.positions[start..k], &traces[t][..length]);
+                    let segment_cost = header_cost
+                        + orientation_costs[t][length]
+                        + coil_costs[start]
+                        + (length - 1) as f32 * residue_cost
+                        + length as f32 * rmsd * rmsd / (2. * SIGMA * SIGMA);
+                    if cost[start] + segment_cost < cost[k] {
+                        cost[k] = cost[start] + segment_cost;
+                        choice[k] = Some((t, length));
+                    }
+                }
+            }
+        }
+
+        let mut ss = vec![SS::Loop; n];
+        let mut k = n;
+        while k > 0 {
+            match choice[k] {
+                Some((t, length)) => {
+                    for s in ss.iter_mut().skip(k - length).take(length) {
+                        *s = TEMPLATES[t].ss.clone();
+                    }
+                    k -= length;
+                }
+                None => k -= 1,
+            }
+        }
+        ss
+    }
+}
+
+pub fn assign_ss(residues_in_protein: &[Residue]) -> Vec<SS> {
+    let trace = Trace::new(residues_in_protein);
+    let ss = trace.assign();
+    trace
+        .indices
+        .iter()
+        .map(|index| match index {
+            Some(k) => ss[*k].clone(),
+            None => SS::Break,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fixtures::load_fixture;
+    use crate::sst::*;
+
+    // CA only, as in coarse-grained models
+    fn sst_string(content: &str) -> String {
+        let mut residues = load_fixture(content);
+        for residue in residues.iter_mut() {
+            residue.atoms.retain(|atom| atom.name == "CA");
+        }
+        assign_ss(&residues).iter().map(|ss| ss.to_char()).collect()
+    }
+
+    // CA trace from (CA-CA-CA angle, CA dihedral) in degrees
+    fn ca_trace(angles: &[(f32, f32)]) -> Vec<Residue> {
+        let mut positions = vec![[0., 0., 0.], [CA_DISTANCE, 0., 0.]];
+        let theta = angles[0].0.to_radians();
+        positions.push([
+            CA_DISTANCE * (1. - theta.cos()),
+            CA_DISTANCE * theta.sin(),
+            0.,
+        ]);
+        for &(theta, tau) in &angles[1..] {
+            let (theta, tau) = (theta.to_radians(), tau.to_radians());
+            let [a, b, c] = [3, 2, 1].map(|back| positions[positions.len() - back]);
+            let bc = normalize(&sub(&c, &b));
+            let n = normalize(&cross(&sub(&b, &a), &bc));
+            let m = cross(&n, &bc);
+            let (x, y, z) = (
+                -CA_DISTANCE * theta.cos(),
+                CA_DISTANCE * theta.sin() * tau.cos(),
+                CA_DISTANCE * theta.sin() * tau.sin(),
+            );
+            positions.push(std::array::from_fn(|i| {
+                c[i] + x * bc[i] + y * m[i] + z * n[i]
+            }));
+        }
+        positions
+            .iter()
+            .map(|p| {
+                Residue::new(
+                    "GLY".to_string(),
+                    vec![Atom::new("CA".to_string(), p[0], p[1], p[2])],
+                )
+                .with_chain("A")
+            })
+            .collect()
+    }
+
+    // angles drawn uniformly over the sterically allowed range
+    fn random_coil(n: usize, seed: u64) -> Vec<(f32, f32)> {
+        let mut state = seed;
+        let mut uniform = move || {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (state >> 40) as f32 / (1u64 << 24) as f32
+        };
+        (0..n)
+            .map(|_| (85. + 60. * uniform(), -180. + 360. * uniform()))
+            .collect()
+    }
+
+    fn to_string(ss: &[SS]) -> String {
+        ss.iter().map(|ss| ss.to_char()).collect()
+    }
+
+    #[test]
+    fn superposition() {
+        let template = TEMPLATES[0].trace(8);
+        // rotated by 90 degrees around x and translated
+        let moved: Vec<[f32; 3]> = template
+            .iter()
+            .map(|p| [p[0] + 10., -p[2] + 5., p[1] - 3.])
+            .collect();
+        assert!(superposed_rmsd(&template, &moved) < 1e-3);
+        assert!(superposed_rmsd(&template, &TEMPLATES[3].trace(8)) > 1.);
+    }
+
+    #[test]
+    fn alpha_helix() {
+        let ss = sst_string(include_str!("../tests/fixtures/alpha_helix.pdb"));
+        assert_eq!(ss, "H".repeat(16));
+    }
+
+    #[test]
+    fn helix_3_10() {
+        let ss = sst_string(include_str!("../tests/fixtures/helix_3_10.pdb"));
+        assert_eq!(ss, "G".repeat(16));
+    }
+
+    #[test]
+    fn pi_helix() {
+        let ss = sst_string(include_str!("../tests/fixtures/pi_helix.pdb"));
+        assert_eq!(ss, "I".repeat(16));
+    }
+
+    #[test]
+    fn polyproline() {
+        let ss = sst_string(include_str!("../tests/fixtures/polyproline.pdb"));
+        assert_eq!(ss, "P".repeat(16));
+    }
+
+    #[test]
+    fn strand() {
+        let ss = sst_string(include_str!("../tests/fixtures/antiparallel_sheet.pdb"));
+        assert_eq!(ss, "E".repeat(14));
+    }
+
+    #[test]
+    fn missing_ca() {
+        let mut residues = load_fixture(include_str!("../tests/fixtures/alpha_helix.pdb"));
+        residues[8].atoms.clear();
+        let ss: String = assign_ss(&residues).iter().map(|ss| ss.to_char()).collect();
+        assert_eq!(ss, "HHHHHHHH=HHHHHHH");
+    }
+
+    #[test]
+    fn random_coil_is_loop() {
+        let ss = to_string(&assign_ss(&ca_trace(&random_coil(800, 1))));
+        let loops = ss.chars().filter(|&c| c == '~').count();
+        assert!(loops > 760, "{ss}");
+    }
+
+    #[test]
+    fn helix_between_loops() {
+        // an alpha helix with the spread of real ones, CA angle 91 and dihedral 50
+        let wobble = random_coil(16, 2);
+        let helix: Vec<(f32, f32)> = wobble
+            .iter()
+            .map(|(theta, tau)| (91. + (theta - 115.) / 10., 50. + tau / 18.))
+            .collect();
+        let angles = [random_coil(20, 3), helix, random_coil(20, 4)].concat();
+        let ss = to_string(&assign_ss(&ca_trace(&angles)));
+        // a single helix in the middle, loops on both sides
+        let helix = ss.trim_matches('~');
+        assert!(helix.len() >= 12 && helix.chars().all(|c| c == 'H'), "{ss}");
+        assert!(
+            ss.starts_with(&"~".repeat(16)) && ss.ends_with(&"~".repeat(16)),
+            "{ss}"
+        );
+    }
+}
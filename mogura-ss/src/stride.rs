@@ -0,0 +1,263 @@
+// An approximation of STRIDE, Frishman & Argos 1995, https://doi.org/10.1002/prot.340230412
+// The turns and ladders are the H-bond patterns of DSSP, with the H-bond energy of
+// Stickle et al. 1992 as in STRIDE, kept only where the phi/psi of the residues are in
+// the helix or sheet region. Those regions are gaussian wells, not the knowledge-based
+// phi/psi tables and weighted criteria of STRIDE, so the result can differ from `stride`.
+use crate::backbone::*;
+use crate::geom::*;
+use crate::*;
+
+// 8-6 potential with its minimum -E_M at R_M, kcal/mol and angstrom
+const E_M: f32 = 2.8;
+const R_M: f32 = 3.0;
+const MAX_NO_DISTANCE: f32 = 3.5;
+const MAX_HBOND_ENERGY: f32 = -0.9;
+const MIN_CA_DISTANCE: f32 = 9.0;
+// helix and sheet regions of the Ramachandran plot
+const HELIX_PHI: f32 = -62.0;
+const HELIX_PSI: f32 = -41.0;
+const SHEET_PHI: f32 = -120.0;
+const SHEET_PSI: f32 = 130.0;
+const PROPENSITY_WIDTH: f32 = 40.0;
+const MIN_HELIX_PROPENSITY: f32 = 0.5;
+const MIN_SHEET_PROPENSITY: f32 = 0.3;
+const MAX_TURN_CA_DISTANCE: f32 = 7.0;
+
+struct Stride {
+    peptide: Peptide,
+    // hbonds[donor]: acceptors
+    hbonds: Vec<Vec<usize>>,
+    helix_propensity: Vec<f32>,
+    sheet_propensity: Vec<f32>,
+}
+
+impl Stride {
+    fn new(residues: &[Residue]) -> Self {
+        let peptide = Peptide::new(residues);
+        let n = peptide.len();
+
+        let mut hbonds = vec![Vec::new(); n];
+        for (donor, acceptors) in hbonds.iter_mut().enumerate() {
+            for acceptor in 0..n {
+                if donor.abs_diff(acceptor) < 2
+                    || distance(&peptide.residues[donor].ca, &peptide.residues[acceptor].ca)
+                        >= MIN_CA_DISTANCE
+                {
+                    continue;
+                }
+                if hbond_energy(&peptide.residues[donor], &peptide.residues[acceptor])
+                    < MAX_HBOND_ENERGY
+                {
+                    acceptors.push(acceptor);
+                }
+            }
+        }
+
+        let propensity = |phi_0: f32, psi_0: f32| {
+            (0..n)
+                .map(|k| match peptide.phi_psi(k) {
+                    (Some(phi), Some(psi)) => {
+                        let d2 = angle_diff(phi, phi_0).powi(2) + angle_diff(psi, psi_0).powi(2);
+                        (-d2 / (2. * PROPENSITY_WIDTH * PROPENSITY_WIDTH)).exp()
+                    }
+                    _ => 0.,
+                })
+                .collect::<Vec<f32>>()
+        };
+        let helix_propensity = propensity(HELIX_PHI, HELIX_PSI);
+        let sheet_propensity = propensity(SHEET_PHI, SHEET_PSI);
+
+        Self {
+            peptide,
+            hbonds,
+            helix_propensity,
+            sheet_propensity,
+        }
+    }
+
+    fn has_hbond(&self, donor: usize, acceptor: usize) -> bool {
+        self.hbonds[donor].contains(&acceptor)
+    }
+
+    fn assign(&self) -> Vec<SS> {
+        let n = self.peptide.len();
+        let mut ss = vec![SS::Loop; n];
+
+        // E, B: H-bond pattern of DSSP, and both residues must be in the sheet region
+        let ladders = self.peptide.ladders(|i, j| {
+            if (self.sheet_propensity[i] + self.sheet_propensity[j]) / 2. < MIN_SHEET_PROPENSITY {
+                return None;
+            }
+            self.peptide.bridge_type(i, j, |d, a| self.has_hbond(d, a))
+        });
+        for bridge in ladders {
+            let bridge_ss = if bridge.i.len() > 1 { SS::E } else { SS::B };
+            for range in [
+                bridge.i[0]..=*bridge.i.back().unwrap(),
+                bridge.j[0]..=*bridge.j.back().unwrap(),
+            ] {
+                for k in range {
+                    if ss[k] != SS::E {
+                        ss[k] = bridge_ss.clone();
+                    }
+                }
+            }
+        }
+
+        let turns = self.peptide.turns(|d, a| self.has_hbond(d, a));
+        let is_helix = |stride: usize, i: usize| {
+            turns[stride - 3][i - 1]
+                && turns[stride - 3][i]
+                && self.helix_propensity[i..i + stride].iter().sum::<f32>() / stride as f32
+                    >= MIN_HELIX_PROPENSITY
+        };
+
+        // H
+        for i in 1..n.saturating_sub(4) {
+            if is_helix(4, i) {
+                for s in ss.iter_mut().skip(i).take(4) {
+                    *s = SS::H;
+                }
+            }
+        }
+
+        // I, preferred over H as in DSSP 4
+        for i in 1..n.saturating_sub(5) {
+            if is_helix(5, i)
+                && ss[i..i + 5]
+                    .iter()
+                    .all(|s| matches!(s, SS::Loop | SS::I | SS::H))
+            {
+                for s in ss.iter_mut().skip(i).take(5) {
+                    *s = SS::I;
+                }
+            }
+        }
+
+        // G
+        for i in 1..n.saturating_sub(3) {
+            if is_helix(3, i) && ss[i..i + 3].iter().all(|s| matches!(s, SS::Loop | SS::G)) {
+                for s in ss.iter_mut().skip(i).take(3) {
+                    *s = SS::G;
+                }
+            }
+        }
+
+        // T: i -> i + 3 H-bond or close CA, on the two central residues
+        for i in 0..n.saturating_sub(3) {
+            if !self.peptide.no_chain_break(i, i + 3) {
+                continue;
+            }
+            let residues = &self.peptide.residues;
+            if turns[0][i] || distance(&residues[i].ca, &residues[i + 3].ca) < MAX_TURN_CA_DISTANCE
+            {
+                for s in ss.iter_mut().skip(i + 1).take(2) {
+                    if *s == SS::Loop {
+                        *s = SS::T;
+                    }
+                }
+            }
+        }
+
+        ss
+    }
+}
+
+// E = Er * Et * Ep
+fn hbond_energy(donor: &Backbone, acceptor: &Backbone) -> f32 {
+    let h = match donor.h {
+        Some(h) => h,
+        None => return 0.,
+    };
+    let r = distance(&donor.n, &acceptor.o);
+    if r > MAX_NO_DISTANCE {
+        return 0.;
+    }
+    let e_r = E_M * (3. * (R_M / r).powi(8) - 4. * (R_M / r).powi(6));
+
+    // deviation of N-H...O from linear
+    let theta = 180. - angle(&sub(&donor.n, &h), &sub(&acceptor.o, &h));
+    if theta >= 90. {
+        return 0.;
+    }
+    let e_t = theta.to_radians().cos().powi(2);
+
+    // position of H around the sp2 acceptor, in and out of the C=O-CA plane
+    let normal = normalize(&cross(
+        &sub(&acceptor.o, &acceptor.c),
+        &sub(&acceptor.ca, &acceptor.c),
+    ));
+    let oh = sub(&h, &acceptor.o);
+    let out_of_plane = dot(&oh, &normal);
+    let in_plane = sub(
+        &oh,
+        &[
+            normal[0] * out_of_plane,
+            normal[1] * out_of_plane,
+            normal[2] * out_of_plane,
+        ],
+    );
+    let t_o = (out_of_plane / norm(&oh)).clamp(-1., 1.).asin().abs();
+    let t_i = angle(&sub(&acceptor.o, &acceptor.c), &in_plane);
+    let e_p = if t_i <= 90. {
+        (0.9 + 0.1 * (2. * t_i).to_radians().sin()) * t_o.cos()
+    } else if t_i <= 110. {
+        let k2 = 110f32.to_radians().cos().powi(2);
+        let k1 = 0.9 / k2;
+        k1 * (k2 - t_i.to_radians().cos().powi(2)) * t_o.cos()
+    } else {
+        0.
+    };
+
+    e_r * e_t * e_p
+}
+
+pub fn assign_ss(residues_in_protein: &[Residue]) -> Vec<SS> {
+    let stride = Stride::new(residues_in_protein);
+    stride.peptide.expand(&stride.assign())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fixtures::load_fixture;
+    use crate::stride::*;
+
+    fn stride_string(residues: &[Residue]) -> String {
+        assign_ss(residues).iter().map(|ss| ss.to_char()).collect()
+    }
+
+    #[test]
+    fn alpha_helix() {
+        let residues = load_fixture(include_str!("../tests/fixtures/alpha_helix.pdb"));
+        assert_eq!(stride_string(&residues), "~HHHHHHHHHHHHHH~");
+    }
+
+    #[test]
+    fn helix_3_10() {
+        let residues = load_fixture(include_str!("../tests/fixtures/helix_3_10.pdb"));
+        assert_eq!(stride_string(&residues), "~GGGGGGGGGGGGGG~");
+    }
+
+    #[test]
+    fn antiparallel_sheet() {
+        let residues = load_fixture(include_str!("../tests/fixtures/antiparallel_sheet.pdb"));
+        assert_eq!(stride_string(&residues), "~EEEEE~~EEEEE~");
+    }
+
+    #[test]
+    fn polyproline() {
+        // no H-bonds, and PP is not a STRIDE class
+        let residues = load_fixture(include_str!("../tests/fixtures/polyproline.pdb"));
+        assert_eq!(stride_string(&residues), "~".repeat(16));
+    }
+
+    #[test]
+    fn hbond_energy_minimum() {
+        let residues = load_fixture(include_str!("../tests/fixtures/alpha_helix.pdb"));
+        let stride = Stride::new(&residues);
+        assert!(stride.has_hbond(4, 0));
+        assert!(!stride.has_hbond(2, 0));
+        let energy = hbond_energy(&stride.peptide.residues[4], &stride.peptide.residues[0]);
+        assert!((-E_M..MAX_HBOND_ENERGY).contains(&energy));
+    }
+}
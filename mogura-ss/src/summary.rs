@@ -0,0 +1,385 @@
+// higher-level description of the secondary structure: beta ladders, sheets and helix geometry
+// residues are referred to by their index in residues_in_protein
+use crate::backbone::BridgeType;
+use crate::geom::*;
+use crate::*;
+use std::ops::RangeInclusive;
+
+// 3-10 helices can be 3 residues long
+const MIN_HELIX_LENGTH: usize = 3;
+// below this, the bisectors are too close to parallel for a radius
+const MIN_TWIST_SIN: f32 = 1e-3;
+
+/// Pair of strands connected by consecutive bridges of one type, as in DSSP.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Ladder {
+    pub bridge_type: BridgeType,
+    pub strands: [RangeInclusive<usize>; 2],
+    /// index in SSSummary::sheets
+    pub sheet: usize,
+}
+
+/// Ladders sharing residues.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sheet {
+    pub ladders: Vec<usize>,
+    /// sorted
+    pub residues: Vec<usize>,
+}
+
+/// Consecutive residues with the same helix letter (H, G, I).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Helix {
+    pub ss: SS,
+    pub residues: RangeInclusive<usize>,
+    /// one point on the axis for each residue except the terminal ones,
+    /// or the terminal CA when the helix is too short or flat for the local axis
+    pub axis: Vec<[f32; 3]>,
+    /// unit vector from the N- to the C-terminal
+    pub direction: [f32; 3],
+    /// angstrom, along the axis
+    pub length: f32,
+    /// angstrom, mean distance of CA to the axis
+    pub radius: f32,
+    /// 1/angstrom, mean curvature of the axis, 0 for a straight helix
+    pub curvature: f32,
+}
+
+#[derive(Debug, Clone)]
+pub struct SSSummary {
+    pub ss: Vec<SS>,
+    pub ladders: Vec<Ladder>,
+    pub sheets: Vec<Sheet>,
+    pub helices: Vec<Helix>,
+}
+
+fn sheets(ladders: &mut [Ladder]) -> Vec<Sheet> {
+    let residues = |ladder: &Ladder| -> Vec<usize> {
+        ladder
+            .strands
+            .iter()
+            .flat_map(|strand| strand.clone())
+            .collect()
+    };
+    let mut sheets: Vec<Sheet> = Vec::new();
+    let mut assigned = vec![false; ladders.len()];
+    for first in 0..ladders.len() {
+        if assigned[first] {
+            continue;
+        }
+        assigned[first] = true;
+        let mut sheet = Sheet {
+            ladders: vec![first],
+            residues: residues(&ladders[first]),
+        };
+        // grow until no ladder shares a residue
+        let mut k = 0;
+        while k < sheet.ladders.len() {
+            let current = residues(&ladders[sheet.ladders[k]]);
+            for other in 0..ladders.len() {
+                if !assigned[other]
+                    && residues(&ladders[other])
+                        .iter()
+                        .any(|residue| current.contains(residue))
+                {
+                    assigned[other] = true;
+                    sheet.ladders.push(other);
+                    sheet.residues.extend(residues(&ladders[other]));
+                }
+            }
+            k += 1;
+        }
+        sheet.ladders.sort();
+        sheet.residues.sort();
+        sheet.residues.dedup();
+        for &ladder in &sheet.ladders {
+            ladders[ladder].sheet = sheets.len();
+        }
+        sheets.push(sheet);
+    }
+    sheets
+}
+
+// local axis of Kahn 1989, from the bisectors of CA(i-1) CA(i) CA(i+1)
+fn helix(ss: SS, residues: RangeInclusive<usize>, ca: &[[f32; 3]]) -> Option<Helix> {
+    if ca.len() < MIN_HELIX_LENGTH {
+        return None;
+    }
+    let bisectors: Vec<[f32; 3]> = (1..ca.len() - 1)
+        .map(|k| normalize(&add(&sub(&ca[k - 1], &ca[k]), &sub(&ca[k + 1], &ca[k]))))
+        .collect();
+    // radius from the chord perpendicular to the axis and the twist between the bisectors,
+    // none without a twist
+    let radii: Vec<Option<f32>> = bisectors
+        .windows(2)
+        .enumerate()
+        .map(|(k, pair)| {
+            let axis = normalize(&cross(&pair[0], &pair[1]));
+            let chord = sub(&ca[k + 2], &ca[k + 1]);
+            let along = dot(&chord, &axis);
+            let perpendicular = sub(&chord, &[axis[0] * along, axis[1] * along, axis[2] * along]);
+            let twist = angle(&pair[0], &pair[1]).to_radians();
+            let sin = (twist / 2.).sin();
+            if sin.is_nan() || sin < MIN_TWIST_SIN {
+                None
+            } else {
+                Some(norm(&perpendicular) / (2. * sin))
+            }
+        })
+        .collect();
+    let mean = |radii: &[Option<f32>]| {
+        let radii: Vec<f32> = radii.iter().flatten().copied().collect();
+        if radii.is_empty() {
+            None
+        } else {
+            Some(radii.iter().sum::<f32>() / radii.len() as f32)
+        }
+    };
+    // e.g. 3-residue 3-10 helices
+    let Some(radius) = mean(&radii) else {
+        return Some(straight_helix(ss, residues, ca));
+    };
+    let axis: Vec<[f32; 3]> = bisectors
+        .iter()
+        .enumerate()
+        .map(|(k, bisector)| {
+            // the pairs on both sides
+            let neighbours = &radii[k.saturating_sub(1)..(k + 1).min(radii.len())];
+            let r = mean(neighbours).unwrap_or(radius);
+            add(
+                &ca[k + 1],
+                &[bisector[0] * r, bisector[1] * r, bisector[2] * r],
+            )
+        })
+        .collect();
+
+    let first = axis[0];
+    let last = *axis.last().unwrap();
+    let curvatures: Vec<f32> = axis
+        .windows(3)
+        .map(|p| {
+            // Menger curvature, 4 * area / (a * b * c)
+            let area = norm(&cross(&sub(&p[1], &p[0]), &sub(&p[2], &p[0]))) / 2.;
+            let abc = distance(&p[0], &p[1]) * distance(&p[1], &p[2]) * distance(&p[0], &p[2]);
+            if abc == 0. { 0. } else { 4. * area / abc }
+        })
+        .collect();
+    Some(Helix {
+        ss,
+        residues,
+        direction: normalize(&sub(&last, &first)),
+        length: axis.windows(2).map(|p| distance(&p[0], &p[1])).sum(),
+        radius,
+        curvature: if curvatures.is_empty() {
+            0.
+        } else {
+            curvatures.iter().sum::<f32>() / curvatures.len() as f32
+        },
+        axis,
+    })
+}
+
+// axis through the terminal CA, radius from the distance of the other CA to it
+fn straight_helix(ss: SS, residues: RangeInclusive<usize>, ca: &[[f32; 3]]) -> Helix {
+    let first = ca[0];
+    let last = ca[ca.len() - 1];
+    let direction = normalize(&sub(&last, &first));
+    let inner = &ca[1..ca.len() - 1];
+    let radius = inner
+        .iter()
+        .map(|p| norm(&cross(&sub(p, &first), &direction)))
+        .sum::<f32>()
+        / inner.len() as f32;
+    Helix {
+        ss,
+        residues,
+        axis: vec![first, last],
+        direction,
+        length: distance(&first, &last),
+        radius,
+        curvature: 0.,
+    }
+}
+
+fn helices(residues_in_protein: &[Residue], ss: &[SS]) -> Vec<Helix> {
+    let mut helices = Vec::new();
+    let mut start = 0;
+    while start < ss.len() {
+        let mut end = start;
+        while end + 1 < ss.len() && ss[end + 1] == ss[start] {
+            end += 1;
+        }
+        if matches!(ss[start], SS::H | SS::G | SS::I) {
+            let ca: Option<Vec<[f32; 3]>> = residues_in_protein[start..=end]
+                .iter()
+                .map(|residue| find_atom(residue, "CA").map(|ca| position(&ca)))
+                .collect();
+            if let Some(ca) = ca {
+                helices.extend(helix(ss[start].clone(), start..=end, &ca));
+            }
+        }
+        start = end + 1;
+    }
+    helices
+}
+
+/// Ladders and sheets come from the H-bonds of DSSP, helices from the SS of `algo`.
+pub fn summarize(residues_in_protein: &Vec<Residue>, algo: SSAlgorithm) -> SSSummary {
+    let ss = assign_ss(residues_in_protein, algo);
+    let mut ladders: Vec<Ladder> = dssp::ladders(residues_in_protein)
+        .into_iter()
+        .map(|bridge| Ladder {
+            bridge_type: bridge.bridge_type,
+            strands: [
+                bridge.i[0]..=*bridge.i.back().unwrap(),
+                bridge.j[0]..=*bridge.j.back().unwrap(),
+            ],
+            sheet: 0,
+        })
+        .collect();
+    let sheets = sheets(&mut ladders);
+    let helices = helices(residues_in_protein, &ss);
+    SSSummary {
+        ss,
+        ladders,
+        sheets,
+        helices,
+    }
+}
+
+// one line per ladder and helix, residues are 1-based
+impl std::fmt::Display for SSSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        for (k, ladder) in self.ladders.iter().enumerate() {
+            writeln!(
+                f,
+                "LADDER {:>4} sheet {:>3} {:<12} {:>5}-{:<5} {:>5}-{:<5}",
+                k + 1,
+                ladder.sheet + 1,
+                match ladder.bridge_type {
+                    BridgeType::Parallel => "parallel",
+                    BridgeType::AntiParallel => "antiparallel",
+                },
+                ladder.strands[0].start() + 1,
+                ladder.strands[0].end() + 1,
+                ladder.strands[1].start() + 1,
+                ladder.strands[1].end() + 1,
+            )?;
+        }
+        for (k, helix) in self.helices.iter().enumerate() {
+            writeln!(
+                f,
+                "HELIX  {:>4} {} {:>5}-{:<5} length {:>6.2} radius {:>5.2} curvature {:>6.4} direction {:>6.3} {:>6.3} {:>6.3}",
+                k + 1,
+                helix.ss.to_char(),
+                helix.residues.start() + 1,
+                helix.residues.end() + 1,
+                helix.length,
+                helix.radius,
+                helix.curvature,
+                helix.direction[0],
+                helix.direction[1],
+                helix.direction[2],
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fixtures::load_fixture;
+    use crate::summary::*;
+
+    #[test]
+    fn antiparallel_sheet() {
+        let residues = load_fixture(include_str!("../tests/fixtures/antiparallel_sheet.pdb"));
+        let summary = summarize(&residues, SSAlgorithm::DSSP);
+        assert_eq!(summary.ladders.len(), 1);
+        assert_eq!(summary.ladders[0].bridge_type, BridgeType::AntiParallel);
+        assert_eq!(summary.ladders[0].strands, [1..=5, 8..=12]);
+        assert_eq!(summary.sheets.len(), 1);
+        assert_eq!(
+            summary.sheets[0].residues,
+            vec![1, 2, 3, 4, 5, 8, 9, 10, 11, 12]
+        );
+        assert!(summary.helices.is_empty());
+    }
+
+    #[test]
+    fn sheets() {
+        let ladder = |strands: [RangeInclusive<usize>; 2]| Ladder {
+            bridge_type: BridgeType::Parallel,
+            strands,
+            sheet: 0,
+        };
+        // 3 strands in one sheet, and an isolated ladder
+        let mut ladders = vec![
+            ladder([1..=4, 10..=13]),
+            ladder([30..=32, 40..=42]),
+            ladder([10..=13, 20..=23]),
+        ];
+        let sheets = super::sheets(&mut ladders);
+        assert_eq!(sheets.len(), 2);
+        assert_eq!(sheets[0].ladders, vec![0, 2]);
+        assert_eq!(
+            ladders
+                .iter()
+                .map(|ladder| ladder.sheet)
+                .collect::<Vec<_>>(),
+            vec![0, 1, 0]
+        );
+    }
+
+    #[test]
+    fn alpha_helix() {
+        let residues = load_fixture(include_str!("../tests/fixtures/alpha_helix.pdb"));
+        let summary = summarize(&residues, SSAlgorithm::DSSP);
+        assert!(summary.ladders.is_empty());
+        assert_eq!(summary.helices.len(), 1);
+        let helix = &summary.helices[0];
+        assert_eq!(helix.residues, 1..=14);
+        assert_eq!(helix.axis.len(), 12);
+        // 1.5 angstrom rise per residue
+        assert!((helix.length - 16.5).abs() < 0.5, "{}", helix.length);
+        assert!((helix.radius - 2.3).abs() < 0.1, "{}", helix.radius);
+        assert!(helix.curvature < 0.01, "{}", helix.curvature);
+        let n_to_c = sub(
+            &position(&find_atom(&residues[14], "CA").unwrap()),
+            &position(&find_atom(&residues[1], "CA").unwrap()),
+        );
+        assert!(dot(&helix.direction, &n_to_c) > 0.);
+        assert_eq!(summary.to_string().lines().count(), 1);
+    }
+
+    #[test]
+    fn short_helix() {
+        // 3 CA of a 3-10 helix, 1.9 angstrom radius, 2.0 angstrom rise and 120 degrees per residue
+        let ca: Vec<[f32; 3]> = (0..3)
+            .map(|k| {
+                let t = (120. * k as f32).to_radians();
+                [1.9 * t.cos(), 1.9 * t.sin(), 2.0 * k as f32]
+            })
+            .collect();
+        let helix = helix(SS::G, 5..=7, &ca).unwrap();
+        assert_eq!(helix.residues, 5..=7);
+        assert_eq!(helix.axis, vec![ca[0], ca[2]]);
+        assert!((helix.length - distance(&ca[0], &ca[2])).abs() < 1e-5);
+        assert!(helix.radius > 0. && helix.radius.is_finite());
+        assert_eq!(helix.curvature, 0.);
+        assert!(helix.direction[2] > 0.);
+
+        assert!(super::helix(SS::G, 5..=6, &ca[..2]).is_none());
+    }
+
+    #[test]
+    fn zero_twist() {
+        // parallel bisectors at the 2 inner CA
+        let ca = [[0., 0., 0.], [1., 1., 0.], [2., 1., 0.], [3., 0., 0.]];
+        let helix = helix(SS::H, 0..=3, &ca).unwrap();
+        assert!((helix.radius - 1.).abs() < 1e-5, "{}", helix.radius);
+        assert!((helix.length - 3.).abs() < 1e-5, "{}", helix.length);
+        assert_eq!(helix.direction, [1., 0., 0.]);
+        assert!(helix.axis.iter().flatten().all(|x| x.is_finite()));
+    }
+}
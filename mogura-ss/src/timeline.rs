@@ -0,0 +1,184 @@
+use crate::*;
+use mogura_io::prelude::{StructureData, TrajectoryData};
+
+/// Secondary structure of every protein residue in every frame, frames x residues.
+#[derive(Debug, Clone)]
+pub struct SSTimeline {
+    algo: SSAlgorithm,
+    n_residue: usize,
+    ss: Vec<SS>,
+}
+
+impl SSTimeline {
+    pub fn algo(&self) -> SSAlgorithm {
+        self.algo
+    }
+
+    pub fn n_frame(&self) -> usize {
+        self.ss.len().checked_div(self.n_residue).unwrap_or(0)
+    }
+
+    pub fn n_residue(&self) -> usize {
+        self.n_residue
+    }
+
+    pub fn frame(&self, frame_id: usize) -> &[SS] {
+        &self.ss[frame_id * self.n_residue..(frame_id + 1) * self.n_residue]
+    }
+
+    /// SS of one residue over the frames
+    pub fn residue(&self, residue_id: usize) -> impl Iterator<Item = &SS> {
+        self.ss
+            .iter()
+            .skip(residue_id)
+            .step_by(self.n_residue.max(1))
+    }
+}
+
+struct TopologyResidue {
+    name: String,
+    chain: String,
+    annotation: Option<SS>,
+    // atom names with their index in the frame positions
+    atoms: Vec<(String, usize)>,
+}
+
+pub(crate) struct Topology(Vec<TopologyResidue>);
+
+impl Topology {
+    pub(crate) fn protein(structure: &dyn StructureData) -> Self {
+        Self(
+            structure
+                .residues()
+                .iter()
+                .filter(|residue| residue.is_protein())
+                .map(|residue| TopologyResidue {
+                    name: residue.residue_name().to_string(),
+                    chain: residue.chain_name().to_string(),
+                    annotation: residue.secondary_structure().map(SS::from),
+                    atoms: residue
+                        .atoms()
+                        .iter()
+                        .map(|atom| (atom.atom_name().to_string(), atom.id()))
+                        .collect(),
+                })
+                .collect(),
+        )
+    }
+
+    pub(crate) fn residues(&self, positions: &[[f32; 3]]) -> Vec<Residue> {
+        self.0
+            .iter()
+            .map(|residue| {
+                Residue::new(
+                    residue.name.clone(),
+                    residue
+                        .atoms
+                        .iter()
+                        .filter_map(|(atom_name, index)| {
+                            let [x, y, z] = positions.get(*index)?;
+                            Some(Atom::new(atom_name.clone(), *x, *y, *z))
+                        })
+                        .collect(),
+                )
+                .with_chain(&residue.chain)
+                .with_annotation(residue.annotation.clone())
+            })
+            .collect()
+    }
+
+    fn timeline<'a>(
+        &self,
+        frames: impl Iterator<Item = &'a [[f32; 3]]>,
+        algo: SSAlgorithm,
+        progress: impl Fn(usize),
+    ) -> SSTimeline {
+        let mut ss = Vec::new();
+        for (k, positions) in frames.enumerate() {
+            ss.extend(assign_ss(&self.residues(positions), algo));
+            progress(k + 1);
+        }
+        SSTimeline {
+            algo,
+            n_residue: self.0.len(),
+            ss,
+        }
+    }
+}
+
+/// Runs `algo` on the protein residues of `structure` with the positions of each frame.
+pub fn assign_ss_trajectory(
+    structure: &dyn StructureData,
+    trajectory: &dyn TrajectoryData,
+    algo: SSAlgorithm,
+) -> SSTimeline {
+    assign_ss_trajectory_with_progress(structure, trajectory, algo, |_| {})
+}
+
+/// As assign_ss_trajectory, calling `progress` with the number of frames done after each frame.
+pub fn assign_ss_trajectory_with_progress(
+    structure: &dyn StructureData,
+    trajectory: &dyn TrajectoryData,
+    algo: SSAlgorithm,
+    progress: impl Fn(usize),
+) -> SSTimeline {
+    Topology::protein(structure).timeline(
+        trajectory
+            .frames()
+            .iter()
+            .map(|frame| frame.positions().as_slice()),
+        algo,
+        progress,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fixtures::load_fixture;
+    use crate::timeline::*;
+
+    #[test]
+    fn unfolding_helix() {
+        let residues = load_fixture(include_str!("../tests/fixtures/alpha_helix.pdb"));
+        let mut atoms = Vec::new();
+        let topology = Topology(
+            residues
+                .iter()
+                .map(|residue| {
+                    let atom_names = residue
+                        .atoms
+                        .iter()
+                        .map(|atom| {
+                            atoms.push([atom.x, atom.y, atom.z]);
+                            (atom.name.clone(), atoms.len() - 1)
+                        })
+                        .collect();
+                    TopologyResidue {
+                        name: residue.name.clone(),
+                        chain: residue.chain.clone(),
+                        annotation: None,
+                        atoms: atom_names,
+                    }
+                })
+                .collect(),
+        );
+        // stretched along every axis, no more H-bonds
+        let stretched: Vec<[f32; 3]> = atoms
+            .iter()
+            .map(|[x, y, z]| [x * 2., y * 2., z * 2.])
+            .collect();
+
+        let frames = [atoms.as_slice(), stretched.as_slice(), atoms.as_slice()];
+        let done = std::cell::Cell::new(0);
+        let timeline = topology.timeline(frames.into_iter(), SSAlgorithm::DSSP, |k| done.set(k));
+        assert_eq!(done.get(), 3);
+        assert_eq!(timeline.n_frame(), 3);
+        assert_eq!(timeline.n_residue(), 16);
+        assert_eq!(timeline.frame(0)[5], SS::H);
+        assert!(timeline.frame(1).iter().all(|ss| *ss != SS::H));
+        assert_eq!(
+            timeline.residue(5).cloned().collect::<Vec<SS>>(),
+            vec![SS::H, SS::Loop, SS::H]
+        );
+    }
+}
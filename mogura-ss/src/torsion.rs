@@ -0,0 +1,229 @@
+// backbone and sidechain torsions, IUPAC-IUB 1970 conventions, in degrees -180..180
+use crate::backbone::is_peptide_bonded;
+use crate::geom::*;
+use crate::timeline::Topology;
+use crate::*;
+use mogura_io::prelude::{StructureData, TrajectoryData};
+
+/// `None` when an atom is missing or the neighbour is not bonded (chain break, terminus).
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Torsion {
+    /// C(i-1) N CA C
+    pub phi: Option<f32>,
+    /// N CA C N(i+1)
+    pub psi: Option<f32>,
+    /// CA C N(i+1) CA(i+1)
+    pub omega: Option<f32>,
+    /// chi1..chi4
+    pub chi: [Option<f32>; 4],
+}
+
+/// Region of the Ramachandran plot for the general case (non Gly/Pro).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RamaRegion {
+    Favored,
+    Allowed,
+    Outlier,
+}
+
+// gaussian wells approximating the general case of Lovell et al. 2003,
+// phi, psi and their widths
+const RAMA_WELLS: [(f32, f32, f32, f32); 5] = [
+    (-120., 130., 35., 35.), // beta
+    (-65., 145., 20., 25.),  // polyproline II
+    (-63., -42., 20., 20.),  // right-handed alpha
+    (-90., 0., 25., 20.),    // bridge
+    (60., 40., 15., 20.),    // left-handed alpha
+];
+// distance from the nearest well in units of its width
+const MAX_FAVORED_DEVIATION: f32 = 2.0;
+const MAX_ALLOWED_DEVIATION: f32 = 3.0;
+
+pub fn rama_region(phi: f32, psi: f32) -> RamaRegion {
+    let deviation = RAMA_WELLS
+        .iter()
+        .map(|&(phi_0, psi_0, phi_width, psi_width)| {
+            ((angle_diff(phi, phi_0) / phi_width).powi(2)
+                + (angle_diff(psi, psi_0) / psi_width).powi(2))
+            .sqrt()
+        })
+        .fold(f32::INFINITY, f32::min);
+    if deviation <= MAX_FAVORED_DEVIATION {
+        RamaRegion::Favored
+    } else if deviation <= MAX_ALLOWED_DEVIATION {
+        RamaRegion::Allowed
+    } else {
+        RamaRegion::Outlier
+    }
+}
+
+// sidechain atoms of chi1..chi4, each after N, CA
+fn chi_atoms(residue_name: &str) -> &'static [&'static str] {
+    match residue_name {
+        "ARG" => &["CB", "CG", "CD", "NE", "CZ"],
+        "ASN" => &["CB", "CG", "OD1"],
+        "ASP" | "ASH" => &["CB", "CG", "OD1"],
+        "CYS" | "CYX" | "CYM" => &["CB", "SG"],
+        "GLN" => &["CB", "CG", "CD", "OE1"],
+        "GLU" | "GLH" => &["CB", "CG", "CD", "OE1"],
+        "HIS" | "HID" | "HIE" | "HIP" | "HSD" | "HSE" | "HSP" => &["CB", "CG", "ND1"],
+        "ILE" => &["CB", "CG1", "CD1"],
+        "LEU" => &["CB", "CG", "CD1"],
+        "LYS" | "LYN" => &["CB", "CG", "CD", "CE", "NZ"],
+        "MET" => &["CB", "CG", "SD", "CE"],
+        "PHE" => &["CB", "CG", "CD1"],
+        "PRO" => &["CB", "CG", "CD"],
+        "SER" => &["CB", "OG"],
+        "THR" => &["CB", "OG1"],
+        "TRP" => &["CB", "CG", "CD1"],
+        "TYR" => &["CB", "CG", "CD1"],
+        "VAL" => &["CB", "CG1"],
+        // ALA, GLY
+        _ => &[],
+    }
+}
+
+fn chi(residue: &Residue) -> [Option<f32>; 4] {
+    let mut chi = [None; 4];
+    let names: Vec<&str> = ["N", "CA"]
+        .into_iter()
+        .chain(chi_atoms(&residue.name).iter().copied())
+        .collect();
+    for (k, quad) in names.windows(4).enumerate() {
+        let atoms: Option<Vec<Atom>> = quad.iter().map(|name| find_atom(residue, name)).collect();
+        chi[k] = atoms.map(|a| dihedral(&a[0], &a[1], &a[2], &a[3]));
+    }
+    chi
+}
+
+/// Torsions of each residue, in the order of `residues_in_protein`.
+pub fn torsions(residues_in_protein: &[Residue]) -> Vec<Torsion> {
+    let n_res = residues_in_protein.len();
+    (0..n_res)
+        .map(|i| {
+            let current = &residues_in_protein[i];
+            let prev = i
+                .checked_sub(1)
+                .map(|k| &residues_in_protein[k])
+                .filter(|prev| is_peptide_bonded(prev, current));
+            let next = residues_in_protein
+                .get(i + 1)
+                .filter(|next| is_peptide_bonded(current, next));
+            let atom = |residue: Option<&Residue>, name| residue.and_then(|r| find_atom(r, name));
+
+            let n = atom(Some(current), "N");
+            let ca = atom(Some(current), "CA");
+            let c = atom(Some(current), "C");
+            let dihedral4 = |atoms: [Option<Atom>; 4]| match atoms {
+                [Some(p1), Some(p2), Some(p3), Some(p4)] => Some(dihedral(&p1, &p2, &p3, &p4)),
+                _ => None,
+            };
+            Torsion {
+                phi: dihedral4([atom(prev, "C"), n.clone(), ca.clone(), c.clone()]),
+                psi: dihedral4([n, ca.clone(), c.clone(), atom(next, "N")]),
+                omega: dihedral4([ca, c, atom(next, "N"), atom(next, "CA")]),
+                chi: chi(current),
+            }
+        })
+        .collect()
+}
+
+/// Torsions of the protein residues of `structure` with the positions of one frame,
+/// indexed by atom id.
+pub fn torsions_frame(structure: &dyn StructureData, positions: &[[f32; 3]]) -> Vec<Torsion> {
+    torsions(&Topology::protein(structure).residues(positions))
+}
+
+/// Torsions of the protein residues of `structure` in every frame, frames x residues.
+pub fn torsions_trajectory(
+    structure: &dyn StructureData,
+    trajectory: &dyn TrajectoryData,
+) -> Vec<Vec<Torsion>> {
+    let topology = Topology::protein(structure);
+    trajectory
+        .frames()
+        .iter()
+        .map(|frame| torsions(&topology.residues(frame.positions())))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fixtures::load_fixture;
+    use crate::torsion::*;
+
+    fn assert_angle(angle: Option<f32>, expected: f32) {
+        let angle = angle.unwrap();
+        assert!((angle - expected).abs() < 0.5, "{} != {}", angle, expected);
+    }
+
+    #[test]
+    fn alpha_helix() {
+        let residues = load_fixture(include_str!("../tests/fixtures/alpha_helix.pdb"));
+        let torsions = torsions(&residues);
+        assert_eq!(torsions.len(), 16);
+        assert_eq!(torsions[0].phi, None);
+        assert_eq!(torsions[15].psi, None);
+        assert_eq!(torsions[15].omega, None);
+        assert_angle(torsions[5].phi, -57.8);
+        assert!(torsions[5].omega.unwrap().abs() > 175.);
+        // ALA
+        assert_eq!(torsions[5].chi, [None; 4]);
+    }
+
+    #[test]
+    fn chain_break() {
+        let mut residues = load_fixture(include_str!("../tests/fixtures/alpha_helix.pdb"));
+        for residue in residues.iter_mut().skip(8) {
+            residue.chain = "B".to_string();
+        }
+        let torsions = torsions(&residues);
+        assert_eq!(torsions[7].psi, None);
+        assert_eq!(torsions[7].omega, None);
+        assert_eq!(torsions[8].phi, None);
+        assert!(torsions[8].psi.is_some());
+    }
+
+    #[test]
+    fn regions() {
+        assert_eq!(rama_region(-63., -42.), RamaRegion::Favored);
+        assert_eq!(rama_region(-120., 130.), RamaRegion::Favored);
+        // beta region across psi = 180
+        assert_eq!(rama_region(-120., -175.), RamaRegion::Favored);
+        assert_eq!(rama_region(60., 40.), RamaRegion::Favored);
+        assert_eq!(rama_region(-100., -60.), RamaRegion::Allowed);
+        assert_eq!(rama_region(60., -120.), RamaRegion::Outlier);
+        assert_eq!(rama_region(0., 180.), RamaRegion::Outlier);
+    }
+
+    #[test]
+    fn chi() {
+        // chi1 of gauche+ SER, and LYS with chi1..chi4
+        let ser = Residue::new(
+            "SER".to_string(),
+            vec![
+                Atom::new("N".to_string(), 1., 0., 0.),
+                Atom::new("CA".to_string(), 0., 0., 0.),
+                Atom::new("CB".to_string(), 0., 0., 1.5),
+                Atom::new("OG".to_string(), 0.5, 0.866, 1.5),
+            ],
+        );
+        let torsions = torsions(&[ser]);
+        assert_angle(torsions[0].chi[0], 60.);
+        assert_eq!(torsions[0].chi[1], None);
+
+        // all trans zigzag
+        let lys = Residue::new(
+            "LYS".to_string(),
+            ["N", "CA", "CB", "CG", "CD", "CE", "NZ"]
+                .iter()
+                .enumerate()
+                .map(|(k, name)| Atom::new(name.to_string(), 1.25 * k as f32, (k % 2) as f32, 0.))
+                .collect(),
+        );
+        let chi = super::chi(&lys);
+        for angle in chi {
+            assert!(angle.unwrap().abs() > 179.);
+        }
+    }
+}